@@ -0,0 +1,118 @@
+use crate::types::event_store_error::{EventStoreError, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/**
+Format event payloads and metadata are stored in, chosen when a store is constructed.
+Every row records the codec it was written with, so a store moved to another codec
+still reads the events written before.
+*/
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Codec {
+    #[default]
+    Json,
+    MessagePack,
+    Bincode,
+}
+
+/// Value encoded by a `Codec`, JSON is kept as a document so stores can still query into it
+#[derive(Clone, Debug)]
+pub enum Encoded {
+    Json(Value),
+    Binary(Vec<u8>),
+}
+
+impl Codec {
+    /// Name recorded on every row written with this codec
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::MessagePack => "msgpack",
+            Codec::Bincode => "bincode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Codec> {
+        match name {
+            "json" => Ok(Codec::Json),
+            "msgpack" => Ok(Codec::MessagePack),
+            "bincode" => Ok(Codec::Bincode),
+            _ => Err(EventStoreError::serialization(format!(
+                "Unknown codec {}",
+                name
+            ))),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Encoded> {
+        match self {
+            Codec::Json => serde_json::to_value(value)
+                .map(Encoded::Json)
+                .map_err(EventStoreError::serialization),
+            Codec::MessagePack => rmp_serde::to_vec(value)
+                .map(Encoded::Binary)
+                .map_err(EventStoreError::serialization),
+            Codec::Bincode => bincode::serialize(value)
+                .map(Encoded::Binary)
+                .map_err(EventStoreError::serialization),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, encoded: Encoded) -> Result<T> {
+        match (self, encoded) {
+            (Codec::Json, Encoded::Json(v)) => {
+                serde_json::from_value(v).map_err(EventStoreError::serialization)
+            }
+            (Codec::MessagePack, Encoded::Binary(b)) => {
+                rmp_serde::from_slice(&b).map_err(EventStoreError::serialization)
+            }
+            (Codec::Bincode, Encoded::Binary(b)) => {
+                bincode::deserialize(&b).map_err(EventStoreError::serialization)
+            }
+            (codec, _) => Err(EventStoreError::serialization(format!(
+                "Value was not encoded with {}",
+                codec.name()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::codec::{Codec, Encoded};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Payload {
+        name: String,
+        count: u32,
+    }
+
+    #[test]
+    fn round_trips_every_codec() {
+        let payload = Payload {
+            name: String::from("name"),
+            count: 3,
+        };
+        for codec in [Codec::Json, Codec::MessagePack, Codec::Bincode] {
+            let encoded = codec.encode(&payload).unwrap();
+            let decoded: Payload = codec.decode(encoded).unwrap();
+            assert_eq!(decoded, payload);
+            assert_eq!(Codec::from_name(codec.name()).unwrap(), codec);
+        }
+    }
+
+    #[test]
+    fn json_stays_a_document() {
+        let encoded = Codec::Json.encode(&vec![1, 2]).unwrap();
+        assert!(matches!(encoded, Encoded::Json(v) if v == serde_json::json!([1, 2])));
+    }
+
+    #[test]
+    fn decoding_with_other_codec_fails() {
+        let encoded = Codec::Bincode.encode(&1_u32).unwrap();
+        assert!(Codec::Json.decode::<u32>(encoded).is_err());
+        assert!(Codec::from_name("xml").is_err());
+    }
+}
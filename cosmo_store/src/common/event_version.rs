@@ -0,0 +1,302 @@
+use crate::traits::version::Version;
+use crate::types::event_read::EventRead;
+use crate::types::event_store_error::{EventStoreError, Result};
+use crate::types::event_stream::{EventStream, StreamState};
+use crate::types::event_write::EventWrite;
+use crate::types::expected_version::ExpectedVersion;
+use crate::types::stream_metadata::StreamMetadata;
+use chrono::Utc;
+use std::fmt::Debug;
+
+/**
+Integer type a stream version is counted in.
+Arithmetic and error reporting go through `i64`, which every implementation converts to and from,
+so only types whose every value fits an `i64` implement it.
+*/
+pub trait VersionNumber: Copy + Debug + Eq + Ord + Send + Sync + 'static {
+    /// Fails with `VersionOutOfRange` if `v` doesn't fit the type
+    fn from_i64(v: i64) -> Result<Self>;
+    fn to_i64(self) -> i64;
+}
+
+macro_rules! version_number {
+    ($($t:ty),*) => {
+        $(
+            impl VersionNumber for $t {
+                fn from_i64(v: i64) -> Result<Self> {
+                    <$t>::try_from(v).map_err(|_| EventStoreError::VersionOutOfRange(v))
+                }
+
+                fn to_i64(self) -> i64 {
+                    self as i64
+                }
+            }
+        )*
+    };
+}
+
+version_number!(i32, i64, u32);
+
+fn validate_version<N: VersionNumber>(
+    stream_id: &str,
+    version: &ExpectedVersion<EventVersion<N>>,
+    next_ver: i64,
+) -> Result<i64> {
+    match version {
+        ExpectedVersion::Any => Ok(next_ver),
+        ExpectedVersion::NoStream => {
+            if next_ver > 1 {
+                Err(EventStoreError::WrongExpectedVersion {
+                    stream_id: stream_id.to_string(),
+                    expected: 1,
+                    actual: next_ver,
+                })
+            } else {
+                Ok(next_ver)
+            }
+        }
+        ExpectedVersion::Exact(expected_version) => {
+            if next_ver != expected_version.0.to_i64() {
+                Err(EventStoreError::WrongExpectedVersion {
+                    stream_id: stream_id.to_string(),
+                    expected: expected_version.0.to_i64(),
+                    actual: next_ver,
+                })
+            } else {
+                Ok(next_ver)
+            }
+        }
+    }
+}
+
+/// Version of an event within its stream, shared by all stores.
+/// Defaults to `i64`, which every backend supports.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct EventVersion<N = i64>(pub N);
+
+impl<N: VersionNumber> EventVersion<N> {
+    pub fn add(&self, a: i64) -> Result<EventVersion<N>> {
+        let v = self.0.to_i64();
+        let sum = v
+            .checked_add(a)
+            .ok_or(EventStoreError::VersionOutOfRange(v))?;
+        Ok(EventVersion(N::from_i64(sum)?))
+    }
+
+    pub fn new(a: N) -> EventVersion<N> {
+        EventVersion(a)
+    }
+}
+
+impl<N: VersionNumber> Version<EventVersion<N>> for EventVersion<N> {
+    fn next_version(
+        &self,
+        stream_id: &str,
+        version: &ExpectedVersion<EventVersion<N>>,
+    ) -> Result<EventVersion<N>> {
+        let next = self.add(1)?;
+        let _ = validate_version(stream_id, version, next.0.to_i64())?;
+        Ok(next)
+    }
+}
+
+pub fn event_writes_to_reads<Payload: Clone, Meta: Clone, N: VersionNumber>(
+    stream_id: &str,
+    next: &EventVersion<N>,
+    first_position: i64,
+    payload: &[EventWrite<Payload, Meta>],
+) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+    payload
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            Ok(EventRead::from_event_write(
+                stream_id,
+                next.add(i as i64)?,
+                first_position + i as i64,
+                Utc::now(),
+                e,
+            ))
+        })
+        .collect()
+}
+
+pub fn updated_stream<N: VersionNumber>(
+    stream_id: &str,
+    count: usize,
+    last: (EventVersion<N>, Option<EventStream<EventVersion<N>>>),
+) -> Result<EventStream<EventVersion<N>>> {
+    let stream = match last.1 {
+        Some(r) => EventStream {
+            last_version: r.last_version.add(count as i64)?,
+            last_updated_utc: Utc::now(),
+            state: StreamState::Active,
+            ..(r)
+        },
+        None => EventStream {
+            id: stream_id.to_string(),
+            last_version: EventVersion(N::from_i64(count as i64)?),
+            last_updated_utc: Utc::now(),
+            state: StreamState::Active,
+            metadata: StreamMetadata::default(),
+            deleted_before: None,
+        },
+    };
+    Ok(stream)
+}
+
+type Event<Payload, Meta, N> = EventRead<Payload, Meta, EventVersion<N>>;
+
+/**
+Original events of a retried append, none if no event of `payload` was stored before.
+`stored` are the stored events sharing an id with `payload`, in any order.
+Retrying the same batch at the same version returns the events as first written,
+any other overlap with stored events fails.
+*/
+pub fn already_appended<Payload, Meta, N: VersionNumber>(
+    stream_id: &str,
+    version: &ExpectedVersion<EventVersion<N>>,
+    payload: &[EventWrite<Payload, Meta>],
+    mut stored: Vec<Event<Payload, Meta, N>>,
+) -> Result<Option<Vec<Event<Payload, Meta, N>>>> {
+    if stored.is_empty() {
+        return Ok(None);
+    }
+    stored.sort_by_key(|e| e.position);
+    let first = stored[0].version.0.to_i64();
+    let same_batch = stored.len() == payload.len()
+        && stored.iter().zip(payload).enumerate().all(|(i, (s, p))| {
+            s.id == p.id && s.stream_id == stream_id && s.version.0.to_i64() == first + i as i64
+        });
+    let same_version = match version {
+        ExpectedVersion::Any => true,
+        ExpectedVersion::NoStream => first == 1,
+        ExpectedVersion::Exact(v) => v.0.to_i64() == first,
+    };
+    if same_batch && same_version {
+        Ok(Some(stored))
+    } else {
+        Err(EventStoreError::DuplicateEvents {
+            stream_id: stream_id.to_string(),
+            ids: stored.iter().map(|e| e.id).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::event_version::{
+        already_appended, event_writes_to_reads, EventVersion, VersionNumber,
+    };
+    use crate::traits::version::Version;
+    use crate::types::event_store_error::EventStoreError;
+    use crate::types::event_write::EventWrite;
+    use crate::types::expected_version::ExpectedVersion;
+    use uuid::Uuid;
+
+    fn writes(count: usize) -> Vec<EventWrite<(), ()>> {
+        (0..count)
+            .map(|_| EventWrite {
+                id: Uuid::new_v4(),
+                correlation_id: None,
+                causation_id: None,
+                name: String::from("event"),
+                data: (),
+                metadata: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_version() {
+        let version = EventVersion(1_i64);
+        assert_eq!(1_i64, version.0);
+        let version = EventVersion(1_u32);
+        assert_eq!(1_u32, version.0);
+    }
+
+    #[test]
+    fn next_version() {
+        let version = EventVersion(1_i64);
+        let res = version
+            .next_version("stream", &ExpectedVersion::Any)
+            .unwrap();
+        assert_eq!(2_i64, res.0);
+        let version = EventVersion(1_u32);
+        let res = version
+            .next_version("stream", &ExpectedVersion::Any)
+            .unwrap();
+        assert_eq!(2_u32, res.0)
+    }
+
+    #[test]
+    fn next_version_mismatch() {
+        let version = EventVersion(1_i64);
+        let res = version.next_version("stream", &ExpectedVersion::Exact(EventVersion(1_i64)));
+        assert!(matches!(
+            res,
+            Err(EventStoreError::WrongExpectedVersion {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+        let version = EventVersion(1_u32);
+        let res = version.next_version("stream", &ExpectedVersion::Exact(EventVersion(1_u32)));
+        assert!(matches!(
+            res,
+            Err(EventStoreError::WrongExpectedVersion {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ))
+    }
+
+    #[test]
+    fn already_appended_returns_stored_batch() {
+        let payload = writes(2);
+        let stored = event_writes_to_reads("stream", &EventVersion(3_i64), 10, &payload).unwrap();
+        let res = already_appended(
+            "stream",
+            &ExpectedVersion::Exact(EventVersion(3_i64)),
+            &payload,
+            stored.into_iter().rev().collect(),
+        )
+        .unwrap()
+        .unwrap();
+        let positions: Vec<i64> = res.iter().map(|e| e.position).collect();
+        assert_eq!(positions, vec![10, 11])
+    }
+
+    #[test]
+    fn already_appended_rejects_overlapping_batch() {
+        let payload = writes(2);
+        let stored =
+            event_writes_to_reads("stream", &EventVersion(1_u32), 1, &payload[..1]).unwrap();
+        let res = already_appended("stream", &ExpectedVersion::Any, &payload, stored);
+        assert!(matches!(
+            res,
+            Err(EventStoreError::DuplicateEvents { ids, .. }) if ids == vec![payload[0].id]
+        ))
+    }
+
+    #[test]
+    fn version_out_of_range_is_typed_error() {
+        assert_eq!(u32::from_i64(7).unwrap(), 7);
+        assert!(matches!(
+            u32::from_i64(-1),
+            Err(EventStoreError::VersionOutOfRange(-1))
+        ));
+        let res = EventVersion(u32::MAX).next_version("stream", &ExpectedVersion::Any);
+        assert!(matches!(
+            res,
+            Err(EventStoreError::VersionOutOfRange(v)) if v == u32::MAX as i64 + 1
+        ));
+        let res = EventVersion(i64::MAX).next_version("stream", &ExpectedVersion::Any);
+        assert!(matches!(
+            res,
+            Err(EventStoreError::VersionOutOfRange(i64::MAX))
+        ))
+    }
+}
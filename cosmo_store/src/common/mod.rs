@@ -1,2 +1,5 @@
+pub mod codec;
+pub mod event_version;
 pub mod i64_event_version;
 pub mod u32_event_version;
+pub mod upcast;
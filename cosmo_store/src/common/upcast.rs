@@ -0,0 +1,145 @@
+use crate::common::codec::Encoded;
+use crate::types::event_store_error::{EventStoreError, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+type Upcaster = Arc<dyn Fn(Encoded) -> Result<Encoded> + Send + Sync>;
+
+/**
+Upcasters keyed by event name and schema version, each lifting a stored payload one version up.
+New events are written at the current schema version of their name, stored events of older
+versions run through every upcaster from their version on before being deserialized.
+*/
+#[derive(Clone, Default)]
+pub struct Upcasters {
+    upcasters: HashMap<String, BTreeMap<i32, Upcaster>>,
+}
+
+impl Upcasters {
+    pub fn new() -> Upcasters {
+        Upcasters::default()
+    }
+
+    /// Registers the upcaster lifting payloads of `name` events from schema version `from` to `from + 1`
+    pub fn register<F>(mut self, name: &str, from: i32, upcast: F) -> Upcasters
+    where
+        F: Fn(Encoded) -> Result<Encoded> + Send + Sync + 'static,
+    {
+        self.upcasters
+            .entry(name.to_string())
+            .or_default()
+            .insert(from, Arc::new(upcast));
+        self
+    }
+
+    /// Same as `register` for payloads stored as JSON documents
+    pub fn register_json<F>(self, name: &str, from: i32, upcast: F) -> Upcasters
+    where
+        F: Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        let event_name = name.to_string();
+        self.register(name, from, move |encoded| match encoded {
+            Encoded::Json(v) => upcast(v).map(Encoded::Json),
+            Encoded::Binary(_) => Err(EventStoreError::serialization(format!(
+                "Upcaster of {} from schema version {} expects JSON",
+                event_name, from
+            ))),
+        })
+    }
+
+    /// Schema version new `name` events are written at, one past the last registered upcaster
+    pub fn current_version(&self, name: &str) -> i32 {
+        self.upcasters
+            .get(name)
+            .and_then(|u| u.keys().next_back())
+            .map_or(1, |from| from + 1)
+    }
+
+    /// Lifts a payload of a `name` event stored at schema `version` to the current version
+    pub fn upcast(&self, name: &str, version: i32, payload: Encoded) -> Result<Encoded> {
+        let current = self.current_version(name);
+        (version..current).try_fold(payload, |payload, from| {
+            let upcast = self.upcasters.get(name).and_then(|u| u.get(&from));
+            match upcast {
+                Some(upcast) => upcast(payload),
+                None => Err(EventStoreError::serialization(format!(
+                    "No upcaster for {} from schema version {}",
+                    name, from
+                ))),
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Upcasters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let versions: BTreeMap<&String, Vec<&i32>> = self
+            .upcasters
+            .iter()
+            .map(|(name, u)| (name, u.keys().collect()))
+            .collect();
+        f.debug_struct("Upcasters")
+            .field("upcasters", &versions)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::codec::Encoded;
+    use crate::common::upcast::Upcasters;
+    use crate::types::event_store_error::Result;
+    use serde_json::{json, Value};
+
+    fn add_field(field: &'static str, value: Value) -> impl Fn(Value) -> Result<Value> {
+        move |mut v| {
+            v[field] = value.clone();
+            Ok(v)
+        }
+    }
+
+    #[test]
+    fn chains_upcasters_up_to_current_version() {
+        let upcasters = Upcasters::new()
+            .register_json("OrderPlaced", 1, add_field("quantity", json!(1)))
+            .register_json("OrderPlaced", 2, add_field("note", json!("")));
+        assert_eq!(upcasters.current_version("OrderPlaced"), 3);
+        assert_eq!(upcasters.current_version("OrderShipped"), 1);
+
+        let upcast = upcasters
+            .upcast("OrderPlaced", 1, Encoded::Json(json!({"item": "book"})))
+            .unwrap();
+        assert!(matches!(upcast, Encoded::Json(v)
+            if v == json!({"item": "book", "quantity": 1, "note": ""})));
+
+        let current = upcasters
+            .upcast("OrderPlaced", 3, Encoded::Json(json!({"item": "pen"})))
+            .unwrap();
+        assert!(matches!(current, Encoded::Json(v) if v == json!({"item": "pen"})));
+    }
+
+    #[test]
+    fn upcasts_raw_bytes() {
+        let upcasters = Upcasters::new().register("Pinged", 1, |encoded| match encoded {
+            Encoded::Binary(mut b) => {
+                b.push(0);
+                Ok(Encoded::Binary(b))
+            }
+            json => Ok(json),
+        });
+        let upcast = upcasters
+            .upcast("Pinged", 1, Encoded::Binary(vec![1]))
+            .unwrap();
+        assert!(matches!(upcast, Encoded::Binary(b) if b == vec![1, 0]));
+    }
+
+    #[test]
+    fn missing_upcaster_in_chain_fails() {
+        let upcasters = Upcasters::new().register_json("OrderPlaced", 2, Ok);
+        assert!(upcasters
+            .upcast("OrderPlaced", 1, Encoded::Json(json!({})))
+            .is_err());
+    }
+}
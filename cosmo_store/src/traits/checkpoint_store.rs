@@ -0,0 +1,16 @@
+use crate::types::event_store_error::Result;
+use async_trait::async_trait;
+
+/**
+Positions projections got to, so a restarted projection resumes where it stopped.
+A checkpoint is the position of the last event a projection is done with.
+*/
+#[async_trait]
+pub trait CheckpointStore {
+    /// Checkpoint of `projection`, none if it never saved one or was reset
+    async fn get_checkpoint(&self, projection: &str) -> Result<Option<i64>>;
+    /// Replaces the checkpoint of `projection`
+    async fn save_checkpoint(&self, projection: &str, position: i64) -> Result<()>;
+    /// Forgets the checkpoint of `projection`, it starts over from the first event
+    async fn reset_checkpoint(&self, projection: &str) -> Result<()>;
+}
@@ -1,8 +1,42 @@
-use crate::types::command_write::CommandWrite;
-use anyhow::Result;
+use crate::types::command_status::CommandStatus;
+use crate::types::command_write::{CommandRead, CommandWrite};
+use crate::types::event_store_error::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
 #[async_trait]
 pub trait CommandStore<Payload> {
     async fn append_command(&self, payload: &CommandWrite<Payload>) -> Result<()>;
+    /// Records a command as `Received`. A command whose id was recorded before is not written again,
+    /// the recorded command is returned instead so its outcome can be answered without running it twice.
+    async fn receive_command(
+        &self,
+        payload: &CommandWrite<Payload>,
+    ) -> Result<CommandRead<Payload>>;
+    /// Moves a command to `status`, failing with `InvalidCommandTransition` if its current status
+    /// can't transition to it
+    async fn transition_command(
+        &self,
+        id: &Uuid,
+        status: &CommandStatus,
+    ) -> Result<CommandRead<Payload>>;
+    /// Fails with `CommandNotFound` for unknown ids
+    async fn get_command(&self, id: &Uuid) -> Result<CommandRead<Payload>>;
+    /// Commands of a conversation, ordered by creation time
+    async fn get_commands_by_correlation_id(
+        &self,
+        correlation_id: &Uuid,
+    ) -> Result<Vec<CommandRead<Payload>>>;
+    /// Commands sent in response to the message `causation_id`, ordered by creation time
+    async fn get_commands_by_causation_id(
+        &self,
+        causation_id: &Uuid,
+    ) -> Result<Vec<CommandRead<Payload>>>;
+    /// Commands created in `[from, to)`, ordered by creation time
+    async fn get_commands_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> Result<Vec<CommandRead<Payload>>>;
 }
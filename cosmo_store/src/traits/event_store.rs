@@ -1,11 +1,16 @@
+use crate::types::delete_mode::DeleteMode;
 use crate::types::event_read::EventRead;
-use crate::types::event_read_range::EventsReadRange;
+use crate::types::event_read_range::{EventOrder, EventsReadRange};
+use crate::types::event_store_error::Result;
 use crate::types::event_stream::EventStream;
 use crate::types::event_write::EventWrite;
 use crate::types::expected_version::ExpectedVersion;
-use crate::types::stream_read_filter::StreamsReadFilter;
-use anyhow::Result;
+use crate::types::stream_append::StreamAppend;
+use crate::types::stream_metadata::StreamMetadata;
+use crate::types::stream_read_filter::{DeletedStreams, StreamsReadFilter};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
 use uuid::Uuid;
 
 #[async_trait]
@@ -19,12 +24,20 @@ where
         version: &ExpectedVersion<Version>,
         payload: &EventWrite<Payload, Meta>,
     ) -> Result<EventRead<Payload, Meta, Version>>;
+    /// Appending events whose ids are already stored returns the stored events if it retries
+    /// the very same batch at the same version, and `DuplicateEvents` otherwise.
     async fn append_events(
         &self,
         stream_id: &str,
         version: &ExpectedVersion<Version>,
         payload: Vec<EventWrite<Payload, Meta>>,
     ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    /// Appends to several streams in one go, nothing is written if any expected version is wrong.
+    /// Results are returned in the order of `appends`.
+    async fn append_to_streams(
+        &self,
+        appends: Vec<StreamAppend<Payload, Meta, Version>>,
+    ) -> Result<Vec<Vec<EventRead<Payload, Meta, Version>>>>;
     async fn get_event(
         &self,
         stream_id: &str,
@@ -35,14 +48,69 @@ where
         stream_id: &str,
         range: &EventsReadRange<Version>,
     ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    /// Same as `get_events`, but yields events one by one instead of collecting them first
+    fn get_events_stream<'a>(
+        &'a self,
+        stream_id: &'a str,
+        range: &'a EventsReadRange<Version>,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, Version>>>;
+    /// Reads events of all streams ordered by position, starting at `from_position`
+    async fn get_all_events(
+        &self,
+        from_position: i64,
+        page_size: usize,
+    ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
     async fn get_events_by_correlation_id(
         &self,
         correlation_id: &Uuid,
     ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    fn get_events_by_correlation_id_stream<'a>(
+        &'a self,
+        correlation_id: &'a Uuid,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, Version>>>;
     async fn get_events_by_causation_id(
         &self,
         causation_id: &Uuid,
     ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
-    async fn get_streams(&self, filter: &StreamsReadFilter) -> Result<Vec<EventStream<Version>>>;
+    /// Reads events with the given name across all streams
+    async fn get_events_by_name(
+        &self,
+        name: &str,
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    /// Reads events with any of the given names across all streams
+    async fn get_events_by_names(
+        &self,
+        names: &[&str],
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    /// Reads events created in `[from, to)` of the streams matching `filter`, ordered by creation time
+    async fn get_events_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        filter: &StreamsReadFilter,
+    ) -> Result<Vec<EventRead<Payload, Meta, Version>>>;
+    async fn get_streams(
+        &self,
+        filter: &StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> Result<Vec<EventStream<Version>>>;
+    fn get_streams_stream<'a>(
+        &'a self,
+        filter: &'a StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> BoxStream<'a, Result<EventStream<Version>>>;
+    /// Fails with `StreamNotFound` for deleted streams
     async fn get_stream(&self, stream_id: &str) -> Result<EventStream<Version>>;
+    /// Fails with `StreamNotFound` for unknown streams and `StreamTombstoned` for tombstoned ones
+    async fn delete_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()>;
+    /// Replaces the retention settings of an existing stream
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<Version>,
+    ) -> Result<EventStream<Version>>;
+    /// Physically removes events hidden by stream metadata or deletion, returns how many were removed
+    async fn scavenge(&self) -> Result<u64>;
 }
@@ -0,0 +1,18 @@
+use crate::types::event_store_error::Result;
+use async_trait::async_trait;
+
+/**
+Encryption keys of subjects, e.g. a stream or the person a stream is about.
+Deleting a key shreds the subject: everything encrypted with the key becomes unreadable
+and no new key can be created for it.
+*/
+#[async_trait]
+pub trait KeyStore {
+    /// Key of `subject`, none if it was never created or was shredded
+    async fn get_key(&self, subject: &str) -> Result<Option<Vec<u8>>>;
+    /// Stores `key` unless `subject` already has one and returns the key in use,
+    /// fails with `SubjectShredded` if the subject was shredded
+    async fn create_key(&self, subject: &str, key: &[u8]) -> Result<Vec<u8>>;
+    /// Shreds `subject`, whether it has a key or not
+    async fn delete_key(&self, subject: &str) -> Result<()>;
+}
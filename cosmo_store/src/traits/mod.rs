@@ -1,3 +1,7 @@
+pub mod checkpoint_store;
 pub mod command_store;
 pub mod event_store;
+pub mod key_store;
+pub mod outbox_sink;
+pub mod snapshot_store;
 pub mod version;
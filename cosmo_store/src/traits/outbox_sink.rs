@@ -0,0 +1,10 @@
+use crate::types::event_read::EventRead;
+use crate::types::event_store_error::Result;
+use async_trait::async_trait;
+
+/// Destination events written to an outbox are published to, e.g. a message broker
+#[async_trait]
+pub trait OutboxSink<Payload, Meta, Version> {
+    /// Failing publishes the event again later, so an event can be published more than once
+    async fn publish(&self, event: &EventRead<Payload, Meta, Version>) -> Result<()>;
+}
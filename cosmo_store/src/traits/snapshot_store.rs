@@ -0,0 +1,24 @@
+use crate::types::event_store_error::Result;
+use crate::types::snapshot::Snapshot;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait SnapshotStore<State, Version>
+where
+    Version: Eq + PartialEq,
+{
+    /// Saves `state` as the state of the stream at `version`, replacing a snapshot taken at the same version
+    async fn save_snapshot(
+        &self,
+        stream_id: &str,
+        version: &Version,
+        state: &State,
+    ) -> Result<Snapshot<State, Version>>;
+    /// Snapshot with the highest version of the stream, if any was taken
+    async fn get_latest_snapshot(
+        &self,
+        stream_id: &str,
+    ) -> Result<Option<Snapshot<State, Version>>>;
+    /// Removes every snapshot of the stream, e.g. along with the stream itself
+    async fn delete_snapshots(&self, stream_id: &str) -> Result<()>;
+}
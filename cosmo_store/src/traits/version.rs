@@ -1,6 +1,6 @@
+use crate::types::event_store_error::Result;
 use crate::types::expected_version::ExpectedVersion;
-use anyhow::Result;
 
 pub trait Version<Version> {
-    fn next_version(&self, version: &ExpectedVersion<Version>) -> Result<Version>;
+    fn next_version(&self, stream_id: &str, version: &ExpectedVersion<Version>) -> Result<Version>;
 }
@@ -0,0 +1,43 @@
+use uuid::Uuid;
+
+/// Where a command is in its lifecycle, commands start out as `Received`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CommandStatus {
+    Received,
+    Processing,
+    /// Handled, producing the events with `event_ids`
+    Succeeded {
+        event_ids: Vec<Uuid>,
+    },
+    /// Rejected or failed while being handled
+    Failed {
+        reason: String,
+    },
+}
+
+impl CommandStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandStatus::Received => "received",
+            CommandStatus::Processing => "processing",
+            CommandStatus::Succeeded { .. } => "succeeded",
+            CommandStatus::Failed { .. } => "failed",
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            CommandStatus::Succeeded { .. } | CommandStatus::Failed { .. }
+        )
+    }
+
+    /// Received commands start processing, received and processing ones end up succeeded or failed
+    pub fn can_transition_to(&self, next: &CommandStatus) -> bool {
+        match (self, next) {
+            (CommandStatus::Received, CommandStatus::Processing) => true,
+            (CommandStatus::Received | CommandStatus::Processing, next) => next.is_final(),
+            _ => false,
+        }
+    }
+}
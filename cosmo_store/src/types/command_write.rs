@@ -1,3 +1,4 @@
+use crate::types::command_status::CommandStatus;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
@@ -26,5 +27,6 @@ pub struct CommandRead<Payload> {
     pub causation_id: Uuid,
     pub data: Payload,
     pub name: String,
+    pub status: CommandStatus,
     pub created_utc: DateTime<Utc>,
 }
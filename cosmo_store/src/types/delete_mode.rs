@@ -0,0 +1,9 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeleteMode {
+    /// Hides the stream and its events, appending to it again recreates it at the next version
+    Soft,
+    /// Removes the stream and its events, the stream id can be reused from version 1
+    Hard,
+    /// Hides the stream and its events for good, appending to it fails with `StreamTombstoned`
+    Tombstone,
+}
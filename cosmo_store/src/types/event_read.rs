@@ -9,6 +9,8 @@ pub struct EventRead<Payload, Meta, Version> {
     pub causation_id: Option<Uuid>,
     pub stream_id: String,
     pub version: Version,
+    /// Store wide position of the event, increasing across all streams
+    pub position: i64,
     pub name: String,
     pub data: Payload,
     pub metadata: Option<Meta>,
@@ -19,6 +21,7 @@ impl<Payload: Clone, Meta: Clone, Version> EventRead<Payload, Meta, Version> {
     pub fn from_event_write(
         stream_id: &str,
         version: Version,
+        position: i64,
         created_utc: DateTime<Utc>,
         event_write: &EventWrite<Payload, Meta>,
     ) -> EventRead<Payload, Meta, Version> {
@@ -32,6 +35,7 @@ impl<Payload: Clone, Meta: Clone, Version> EventRead<Payload, Meta, Version> {
             metadata: event_write.metadata.clone(),
             created_utc,
             version,
+            position,
         }
     }
 }
@@ -1,3 +1,16 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadDirection {
+    Forward,
+    Backward,
+}
+
+/// Order of events read across streams, events created at the same time keep their position order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventOrder {
+    Position,
+    CreatedUtc,
+}
+
 #[derive(Clone, Debug)]
 pub enum EventsReadRange<Version> {
     AllEvents,
@@ -7,4 +20,12 @@ pub enum EventsReadRange<Version> {
         from_version: Version,
         to_version: Version,
     },
+    /// At most `max_count` events, starting at `from_version` and moving in `direction`.
+    /// Without `from_version` forward reads start at the first event of the stream
+    /// and backward reads at the last one.
+    Page {
+        from_version: Option<Version>,
+        direction: ReadDirection,
+        max_count: usize,
+    },
 }
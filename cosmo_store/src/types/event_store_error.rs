@@ -0,0 +1,66 @@
+use std::error::Error;
+use thiserror::Error;
+use uuid::Uuid;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/**
+Error returned by every `EventStore` and `CommandStore` implementation.
+Versions are reported as `i64` regardless of the version type used by the store,
+`expected` and `actual` being the version the next event was expected to get
+and the version it would actually get.
+*/
+#[derive(Debug, Error)]
+pub enum EventStoreError {
+    #[error("Stream {stream_id} was expected to have next version {expected}, but has {actual}")]
+    WrongExpectedVersion {
+        stream_id: String,
+        expected: i64,
+        actual: i64,
+    },
+    #[error("StreamID: {0} not present in store")]
+    StreamNotFound(String),
+    #[error("Stream {0} is tombstoned")]
+    StreamTombstoned(String),
+    #[error("Event with version {version} not present in stream {stream_id}")]
+    EventNotFound { stream_id: String, version: i64 },
+    #[error(
+        "Events {ids:?} were already appended to stream {stream_id} as part of a different batch"
+    )]
+    DuplicateEvents { stream_id: String, ids: Vec<Uuid> },
+    #[error("Version {0} is out of range of the store's version type")]
+    VersionOutOfRange(i64),
+    #[error("Command {0} not present in store")]
+    CommandNotFound(Uuid),
+    #[error("Command {id} can't go from {from} to {to}")]
+    InvalidCommandTransition { id: Uuid, from: String, to: String },
+    #[error("Key of subject {0} was shredded")]
+    SubjectShredded(String),
+    #[error("Process {process} failed to react to event {event_id}: {source}")]
+    ReactionFailed {
+        process: String,
+        event_id: Uuid,
+        #[source]
+        source: BoxError,
+    },
+    #[error("Failed to serialize or deserialize payload: {0}")]
+    Serialization(#[source] BoxError),
+    #[error("Backend error: {0}")]
+    Backend(#[source] BoxError),
+}
+
+impl EventStoreError {
+    pub fn serialization<E: Into<BoxError>>(e: E) -> EventStoreError {
+        EventStoreError::Serialization(e.into())
+    }
+
+    pub fn backend<E: Into<BoxError>>(e: E) -> EventStoreError {
+        EventStoreError::Backend(e.into())
+    }
+
+    pub fn is_concurrency_conflict(&self) -> bool {
+        matches!(self, EventStoreError::WrongExpectedVersion { .. })
+    }
+}
+
+pub type Result<T, E = EventStoreError> = std::result::Result<T, E>;
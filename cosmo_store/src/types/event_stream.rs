@@ -1,8 +1,23 @@
+use crate::types::stream_metadata::StreamMetadata;
 use chrono::{DateTime, Utc};
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamState {
+    Active,
+    /// Soft deleted, events are hidden until the stream is written to again
+    Deleted,
+    /// Permanently deleted, appending to it fails
+    Tombstoned,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct EventStream<Version: Eq + PartialEq> {
     pub id: String,
     pub last_version: Version,
     pub last_updated_utc: DateTime<Utc>,
+    pub state: StreamState,
+    pub metadata: StreamMetadata<Version>,
+    /// Events before this version were deleted along with the stream,
+    /// apart from the retention settings so replacing those doesn't bring them back
+    pub deleted_before: Option<Version>,
 }
@@ -1,7 +1,14 @@
+pub mod command_status;
 pub mod command_write;
+pub mod delete_mode;
 pub mod event_read;
 pub mod event_read_range;
+pub mod event_store_error;
 pub mod event_stream;
 pub mod event_write;
 pub mod expected_version;
+pub mod outbox;
+pub mod snapshot;
+pub mod stream_append;
+pub mod stream_metadata;
 pub mod stream_read_filter;
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct OutboxSettings {
+    /// Number of outbox rows claimed at once
+    pub batch_size: usize,
+    /// Publishing an event is given up after this many failed attempts, leaving its row failed
+    pub max_attempts: u32,
+    /// Delay before the first retry, growing linearly with every failed attempt
+    pub retry_delay: Duration,
+    /// Delay before checking the outbox again once it is empty
+    pub poll_interval: Duration,
+    /// How long claimed rows stay hidden from other dispatchers, so an interrupted batch is
+    /// claimed again once this elapsed
+    pub claim_timeout: Duration,
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        OutboxSettings {
+            batch_size: 100,
+            max_attempts: 10,
+            retry_delay: Duration::from_secs(1),
+            poll_interval: Duration::from_millis(500),
+            claim_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl OutboxSettings {
+    /// Delay before retrying an event that failed `attempts` times
+    pub fn retry_after(&self, attempts: u32) -> Duration {
+        self.retry_delay * attempts
+    }
+}
+
+/// Outcome of dispatching one batch of outbox rows
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OutboxDispatch {
+    pub delivered: usize,
+    pub failed: usize,
+}
+
+/// Outbox row publishing was given up for after `max_attempts` failed attempts
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutboxFailure {
+    pub position: i64,
+    pub event_id: Uuid,
+    pub stream_id: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub failed_utc: DateTime<Utc>,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+
+/**
+State of a stream after folding every event up to and including `version`.
+Rehydration starts from the snapshot and only applies the events written after it.
+*/
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot<State, Version: Eq + PartialEq> {
+    pub stream_id: String,
+    pub version: Version,
+    pub state: State,
+    pub created_utc: DateTime<Utc>,
+}
@@ -0,0 +1,34 @@
+use crate::types::event_store_error::{EventStoreError, Result};
+use crate::types::event_write::EventWrite;
+use crate::types::expected_version::ExpectedVersion;
+use std::collections::HashSet;
+
+/// Events appended to one stream as part of a multi-stream append
+#[derive(Clone, Debug)]
+pub struct StreamAppend<Payload, Meta, Version> {
+    pub stream_id: String,
+    pub version: ExpectedVersion<Version>,
+    pub events: Vec<EventWrite<Payload, Meta>>,
+}
+
+/// Fails if an event id shows up more than once across `appends`
+pub fn ensure_unique_event_ids<Payload, Meta, Version>(
+    appends: &[StreamAppend<Payload, Meta, Version>],
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    for append in appends {
+        let ids: Vec<_> = append
+            .events
+            .iter()
+            .map(|e| e.id)
+            .filter(|id| !seen.insert(*id))
+            .collect();
+        if !ids.is_empty() {
+            return Err(EventStoreError::DuplicateEvents {
+                stream_id: append.stream_id.clone(),
+                ids,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+/**
+Retention settings of a stream.
+Events beyond any of the limits are hidden from reads right away
+and physically removed by the next `scavenge`.
+*/
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamMetadata<Version> {
+    /// Only the last `max_count` events are kept
+    pub max_count: Option<u64>,
+    /// Events older than `max_age` are dropped, sqlx stores keep whole seconds
+    pub max_age: Option<Duration>,
+    /// Events before this version are dropped
+    pub truncate_before: Option<Version>,
+}
+
+impl<Version> Default for StreamMetadata<Version> {
+    fn default() -> Self {
+        StreamMetadata {
+            max_count: None,
+            max_age: None,
+            truncate_before: None,
+        }
+    }
+}
@@ -1,7 +1,15 @@
 #[derive(Clone, Debug)]
 pub enum StreamsReadFilter {
     AllStreams,
+    Equals(String),
     StartsWith(String),
     EndsWith(String),
     Contains(String),
 }
+
+/// Whether soft deleted and tombstoned streams are listed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeletedStreams {
+    Exclude,
+    Include,
+}
@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[derive(Default)]
+pub struct CheckpointStoreInMemory {
+    checkpoints: RwLock<HashMap<String, i64>>,
+}
+
+impl CheckpointStoreInMemory {
+    pub fn new() -> CheckpointStoreInMemory {
+        CheckpointStoreInMemory::default()
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for CheckpointStoreInMemory {
+    async fn get_checkpoint(&self, projection: &str) -> Result<Option<i64>> {
+        let checkpoints = self
+            .checkpoints
+            .read()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        Ok(checkpoints.get(projection).copied())
+    }
+
+    async fn save_checkpoint(&self, projection: &str, position: i64) -> Result<()> {
+        let mut checkpoints = self
+            .checkpoints
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        checkpoints.insert(projection.to_string(), position);
+        Ok(())
+    }
+
+    async fn reset_checkpoint(&self, projection: &str) -> Result<()> {
+        let mut checkpoints = self
+            .checkpoints
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        checkpoints.remove(projection);
+        Ok(())
+    }
+}
@@ -1,86 +1,380 @@
-use anyhow::{bail, Result};
 use async_trait::async_trait;
-use cosmo_store::common::u32_event_version::{event_writes_to_reads, updated_stream, EventVersion};
+use chrono::{DateTime, Utc};
+use cosmo_store::common::event_version::{
+    already_appended, event_writes_to_reads, updated_stream, EventVersion, VersionNumber,
+};
 use cosmo_store::traits::event_store::EventStore;
 use cosmo_store::traits::version::Version;
+use cosmo_store::types::delete_mode::DeleteMode;
 use cosmo_store::types::event_read::EventRead;
-use cosmo_store::types::event_read_range::EventsReadRange;
-use cosmo_store::types::event_stream::EventStream;
+use cosmo_store::types::event_read_range::{EventOrder, EventsReadRange, ReadDirection};
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::{EventStream, StreamState};
 use cosmo_store::types::event_write::EventWrite;
 use cosmo_store::types::expected_version::ExpectedVersion;
-use cosmo_store::types::stream_read_filter::StreamsReadFilter;
+use cosmo_store::types::stream_append::{ensure_unique_event_ids, StreamAppend};
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store::types::stream_read_filter::{DeletedStreams, StreamsReadFilter};
+use futures::stream::{self, BoxStream};
+use futures::{future, StreamExt, TryStreamExt};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use uuid::Uuid;
 
-pub struct EventStoreInMemory<Payload, Meta, Version : Eq + PartialEq> {
-    streams: HashMap<String, EventStream<Version>>,
-    events: HashMap<String, EventRead<Payload, Meta, Version>>,
+type Event<Payload, Meta, N> = EventRead<Payload, Meta, EventVersion<N>>;
+
+pub struct EventStoreInMemory<Payload, Meta, Version: Eq + PartialEq> {
+    streams: RwLock<HashMap<String, EventStream<Version>>>,
+    events: RwLock<HashMap<String, EventRead<Payload, Meta, Version>>>,
+    last_position: AtomicI64,
+}
+
+fn read<T>(lock: &RwLock<T>) -> Result<RwLockReadGuard<'_, T>> {
+    lock.read()
+        .map_err(|e| EventStoreError::backend(e.to_string()))
+}
+
+fn in_range<N: VersionNumber>(
+    version: &EventVersion<N>,
+    range: &EventsReadRange<EventVersion<N>>,
+) -> bool {
+    match range {
+        EventsReadRange::AllEvents => true,
+        EventsReadRange::FromVersion(v) => version.0 >= v.0,
+        EventsReadRange::ToVersion(v) => version.0.to_i64() > 0 && version.0 <= v.0,
+        EventsReadRange::VersionRange {
+            from_version,
+            to_version,
+        } => version.0 >= from_version.0 && version.0 <= to_version.0,
+        EventsReadRange::Page {
+            from_version: None, ..
+        } => true,
+        EventsReadRange::Page {
+            from_version: Some(v),
+            direction: ReadDirection::Forward,
+            ..
+        } => version.0 >= v.0,
+        EventsReadRange::Page {
+            from_version: Some(v),
+            direction: ReadDirection::Backward,
+            ..
+        } => version.0 <= v.0,
+    }
+}
+
+// Events hidden by deletion or retention settings of their stream are left out of reads
+fn is_visible<Payload, Meta, N: VersionNumber>(
+    stream: &EventStream<EventVersion<N>>,
+    event: &EventRead<Payload, Meta, EventVersion<N>>,
+    now: DateTime<Utc>,
+) -> bool {
+    let deleted = stream
+        .deleted_before
+        .as_ref()
+        .is_some_and(|v| event.version.0 < v.0);
+    let metadata = &stream.metadata;
+    let truncated = metadata
+        .truncate_before
+        .as_ref()
+        .is_some_and(|v| event.version.0 < v.0);
+    let over_count = metadata
+        .max_count
+        .is_some_and(|c| event.version.0.to_i64() + c as i64 <= stream.last_version.0.to_i64());
+    let expired = metadata.max_age.is_some_and(|age| {
+        now.signed_duration_since(event.created_utc)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed > age)
+    });
+    !(deleted || truncated || over_count || expired)
+}
+
+fn page_of<N: VersionNumber>(
+    range: &EventsReadRange<EventVersion<N>>,
+) -> (ReadDirection, Option<usize>) {
+    match range {
+        EventsReadRange::Page {
+            direction,
+            max_count,
+            ..
+        } => (*direction, Some(*max_count)),
+        _ => (ReadDirection::Forward, None),
+    }
+}
+
+fn stream_id_filter(filter: &StreamsReadFilter, id: &str) -> bool {
+    match filter {
+        StreamsReadFilter::AllStreams => true,
+        StreamsReadFilter::Equals(c) => id == c,
+        StreamsReadFilter::StartsWith(c) => id.starts_with(c),
+        StreamsReadFilter::EndsWith(c) => id.ends_with(c),
+        StreamsReadFilter::Contains(c) => id.contains(c),
+    }
+}
+
+fn stream_filter<N: VersionNumber>(
+    filter: &StreamsReadFilter,
+    deleted: DeletedStreams,
+    stream: &EventStream<EventVersion<N>>,
+) -> bool {
+    let listed = deleted == DeletedStreams::Include || stream.state == StreamState::Active;
+    listed && stream_id_filter(filter, &stream.id)
+}
+
+fn write<T>(lock: &RwLock<T>) -> Result<RwLockWriteGuard<'_, T>> {
+    lock.write()
+        .map_err(|e| EventStoreError::backend(e.to_string()))
 }
 
-impl<Payload: Clone, Meta: Clone> Default for EventStoreInMemory<Payload, Meta, EventVersion> {
+impl<Payload: Clone, Meta: Clone, N: VersionNumber> Default
+    for EventStoreInMemory<Payload, Meta, EventVersion<N>>
+{
     fn default() -> Self {
         EventStoreInMemory::new()
     }
 }
 
-impl<Payload: Clone, Meta: Clone> EventStoreInMemory<Payload, Meta, EventVersion> {
-    pub fn new() -> EventStoreInMemory<Payload, Meta, EventVersion> {
+impl<Payload: Clone, Meta: Clone, N: VersionNumber>
+    EventStoreInMemory<Payload, Meta, EventVersion<N>>
+{
+    pub fn new() -> EventStoreInMemory<Payload, Meta, EventVersion<N>> {
         EventStoreInMemory {
-            streams: HashMap::new(),
-            events: HashMap::new(),
+            streams: RwLock::new(HashMap::new()),
+            events: RwLock::new(HashMap::new()),
+            last_position: AtomicI64::new(0),
         }
     }
 
-    fn get_stream_values(&self, keys: Vec<String>) -> Vec<EventStream<EventVersion>> {
-        let res: Vec<EventStream<EventVersion>> = keys
-            .iter()
-            .map(|x| self.streams.get(x).unwrap())
+    fn get_stream_values<F>(&self, filter: F) -> Result<Vec<EventStream<EventVersion<N>>>>
+    where
+        F: Fn(&EventStream<EventVersion<N>>) -> bool,
+    {
+        let res: Vec<EventStream<EventVersion<N>>> = read(&self.streams)?
+            .values()
+            .filter(|v| filter(v))
             .cloned()
             .collect();
-        res
+        Ok(res)
     }
-    async fn process_events(
-        &mut self,
-        stream_id: &str,
-        version: &ExpectedVersion<EventVersion>,
-        payload: Vec<EventWrite<Payload, Meta>>,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        let exist: Option<&EventStream<EventVersion>> = self.streams.get(stream_id);
-        let last: (EventVersion, Option<EventStream<EventVersion>>) = match exist {
-            Some(r) => (r.last_version.clone(), Some(r.clone())),
-            None => (EventVersion::new(0), None),
-        };
 
-        let next = last.0.next_version(version)?;
+    fn process_appends(
+        &self,
+        appends: Vec<StreamAppend<Payload, Meta, EventVersion<N>>>,
+    ) -> Result<Vec<Vec<Event<Payload, Meta, N>>>> {
+        // Both locks are held for the whole append, so version checks and writes are atomic
+        let mut streams = write(&self.streams)?;
+        let mut events = write(&self.events)?;
+
+        ensure_unique_event_ids(&appends)?;
+
+        // Each append is checked against the streams as left by the appends before it,
+        // nothing is written until all of them passed
+        let mut updated: HashMap<String, EventStream<EventVersion<N>>> = HashMap::new();
+        let mut checked = Vec::new();
+        let mut retried = HashMap::new();
+        for (i, append) in appends.iter().enumerate() {
+            if append.events.is_empty() {
+                checked.push(None);
+                continue;
+            }
+            let stream_id = append.stream_id.as_str();
+            let stored = append
+                .events
+                .iter()
+                .filter_map(|e| events.get(&e.id.to_string()).cloned())
+                .collect();
+            if let Some(original) =
+                already_appended(stream_id, &append.version, &append.events, stored)?
+            {
+                retried.insert(i, original);
+                checked.push(None);
+                continue;
+            }
+            let current = updated.get(stream_id).or_else(|| streams.get(stream_id));
+            let last: (EventVersion<N>, Option<EventStream<EventVersion<N>>>) = match current {
+                Some(r) if r.state == StreamState::Tombstoned => {
+                    return Err(EventStoreError::StreamTombstoned(stream_id.to_string()))
+                }
+                Some(r) => (r.last_version.clone(), Some(r.clone())),
+                None => (EventVersion::new(N::from_i64(0)?), None),
+            };
+            // A soft deleted stream does not exist anymore, but keeps counting its versions
+            let version = match (&last.1, &append.version) {
+                (Some(r), ExpectedVersion::NoStream) if r.state == StreamState::Deleted => {
+                    &ExpectedVersion::Any
+                }
+                (_, version) => version,
+            };
+
+            let next = last.0.next_version(stream_id, version)?;
+            let stream = updated_stream(stream_id, append.events.len(), last)?;
+            updated.insert(stream_id.to_string(), stream);
+            checked.push(Some((next, append.events.len())));
+        }
 
-        let ops: Vec<EventRead<Payload, Meta, EventVersion>> =
-            event_writes_to_reads(stream_id, &next, &payload);
-        let updated_stream = updated_stream(stream_id, payload.len() as u32, last);
-        // Updating stream
-        self.streams
-            .entry(stream_id.to_string())
-            .or_insert(updated_stream);
+        let count: usize = checked.iter().flatten().map(|(_, n)| n).sum();
+        let mut position = self.last_position.fetch_add(count as i64, Ordering::SeqCst) + 1;
+        let results: Vec<Vec<EventRead<Payload, Meta, EventVersion<N>>>> = appends
+            .iter()
+            .zip(checked)
+            .enumerate()
+            .map(|(i, (append, next))| match next {
+                Some((next, _)) => {
+                    let ops =
+                        event_writes_to_reads(&append.stream_id, &next, position, &append.events)?;
+                    position += ops.len() as i64;
+                    Ok(ops)
+                }
+                None => Ok(retried.remove(&i).unwrap_or_default()),
+            })
+            .collect::<Result<_>>()?;
+        // Updating streams
+        streams.extend(updated);
         // Updating EVENTS
-        ops.iter().for_each(|x| {
-            let _ = self.events.insert(x.id.to_string(), x.clone());
+        results.iter().flatten().for_each(|x| {
+            let _ = events.entry(x.id.to_string()).or_insert_with(|| x.clone());
+        });
+        Ok(results)
+    }
+
+    fn remove_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()> {
+        let mut streams = write(&self.streams)?;
+        let mut events = write(&self.events)?;
+
+        let stream = match streams.get_mut(stream_id) {
+            Some(s) if s.state == StreamState::Tombstoned => {
+                return Err(EventStoreError::StreamTombstoned(stream_id.to_string()))
+            }
+            Some(s) => s,
+            None => return Err(EventStoreError::StreamNotFound(stream_id.to_string())),
+        };
+        // Events stay in place, reads skip everything written before the deletion
+        let state = match mode {
+            DeleteMode::Soft => StreamState::Deleted,
+            DeleteMode::Tombstone => StreamState::Tombstoned,
+            DeleteMode::Hard => {
+                let _ = streams.remove(stream_id);
+                events.retain(|_, e| e.stream_id != stream_id);
+                return Ok(());
+            }
+        };
+        stream.state = state;
+        stream.deleted_before = Some(stream.last_version.add(1)?);
+        Ok(())
+    }
+
+    fn update_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<EventVersion<N>>,
+    ) -> Result<EventStream<EventVersion<N>>> {
+        let mut streams = write(&self.streams)?;
+        match streams.get_mut(stream_id) {
+            Some(s) if s.state == StreamState::Active => {
+                s.metadata = metadata.clone();
+                Ok(s.clone())
+            }
+            Some(s) if s.state == StreamState::Tombstoned => {
+                Err(EventStoreError::StreamTombstoned(stream_id.to_string()))
+            }
+            _ => Err(EventStoreError::StreamNotFound(stream_id.to_string())),
+        }
+    }
+
+    fn remove_hidden_events(&self) -> Result<u64> {
+        let streams = read(&self.streams)?;
+        let mut events = write(&self.events)?;
+        let now = Utc::now();
+
+        let before = events.len();
+        events.retain(|_, e| {
+            streams
+                .get(&e.stream_id)
+                .is_some_and(|s| is_visible(s, e, now))
         });
-        Ok(ops)
+        Ok((before - events.len()) as u64)
+    }
+
+    // Streams are locked before events, same as appends do
+    fn map_visible_events<T, F, M>(&self, filter: F, map: M) -> Result<Vec<T>>
+    where
+        F: Fn(&EventRead<Payload, Meta, EventVersion<N>>) -> bool,
+        M: Fn(&EventRead<Payload, Meta, EventVersion<N>>) -> T,
+    {
+        let streams = read(&self.streams)?;
+        let events = read(&self.events)?;
+        let now = Utc::now();
+
+        let res: Vec<T> = events
+            .values()
+            .filter(|x| {
+                filter(x)
+                    && streams
+                        .get(&x.stream_id)
+                        .is_some_and(|s| is_visible(s, x, now))
+            })
+            .map(map)
+            .collect();
+        Ok(res)
+    }
+
+    fn visible_events<F>(&self, filter: F) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>>
+    where
+        F: Fn(&EventRead<Payload, Meta, EventVersion<N>>) -> bool,
+    {
+        self.map_visible_events(filter, |x| x.clone())
+    }
+
+    // Only the keys are collected up front, events are cloned while the stream is consumed
+    fn lazy_events<'a, F>(
+        &'a self,
+        filter: F,
+        direction: ReadDirection,
+        max_count: Option<usize>,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, EventVersion<N>>>>
+    where
+        Payload: Send + Sync,
+        Meta: Send + Sync,
+        F: Fn(&EventRead<Payload, Meta, EventVersion<N>>) -> bool,
+    {
+        let mut keys: Vec<(i64, String)> =
+            match self.map_visible_events(filter, |x| (x.position, x.id.to_string())) {
+                Ok(keys) => keys,
+                Err(e) => return stream::once(future::ready(Err(e))).boxed(),
+            };
+        keys.sort();
+        if direction == ReadDirection::Backward {
+            keys.reverse();
+        }
+        if let Some(max_count) = max_count {
+            keys.truncate(max_count);
+        }
+
+        stream::iter(keys)
+            .filter_map(move |(_, key)| {
+                let event = match read(&self.events) {
+                    Ok(events) => events.get(&key).cloned().map(Ok),
+                    Err(e) => Some(Err(e)),
+                };
+                future::ready(event)
+            })
+            .boxed()
     }
 }
 
 #[async_trait]
-impl<Payload: Clone, Meta: Clone> EventStore<Payload, Meta, EventVersion>
-    for EventStoreInMemory<Payload, Meta, EventVersion>
+impl<Payload: Clone, Meta: Clone, N: VersionNumber> EventStore<Payload, Meta, EventVersion<N>>
+    for EventStoreInMemory<Payload, Meta, EventVersion<N>>
 where
     Payload: Send + Sync + 'static,
     Meta: Send + Sync + 'static,
 {
     async fn append_event(
-        &mut self,
+        &self,
         stream_id: &str,
-        version: &ExpectedVersion<EventVersion>,
+        version: &ExpectedVersion<EventVersion<N>>,
         payload: &EventWrite<Payload, Meta>,
-    ) -> Result<EventRead<Payload, Meta, EventVersion>> {
+    ) -> Result<EventRead<Payload, Meta, EventVersion<N>>> {
         let res = self
             .append_events(stream_id, version, vec![payload.clone()])
             .await?;
@@ -88,139 +382,199 @@ where
     }
 
     async fn append_events(
-        &mut self,
+        &self,
         stream_id: &str,
-        version: &ExpectedVersion<EventVersion>,
+        version: &ExpectedVersion<EventVersion<N>>,
         payload: Vec<EventWrite<Payload, Meta>>,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
         if payload.is_empty() {
             return Ok(Vec::new());
         }
 
-        self.process_events(stream_id, version, payload).await
+        let append = StreamAppend {
+            stream_id: stream_id.to_string(),
+            version: version.clone(),
+            events: payload,
+        };
+        let mut res = self.process_appends(vec![append])?;
+        Ok(res.remove(0))
+    }
+
+    async fn append_to_streams(
+        &self,
+        appends: Vec<StreamAppend<Payload, Meta, EventVersion<N>>>,
+    ) -> Result<Vec<Vec<EventRead<Payload, Meta, EventVersion<N>>>>> {
+        self.process_appends(appends)
     }
 
     async fn get_event(
         &self,
         stream_id: &str,
-        version: &EventVersion,
-    ) -> Result<EventRead<Payload, Meta, EventVersion>> {
+        version: &EventVersion<N>,
+    ) -> Result<EventRead<Payload, Meta, EventVersion<N>>> {
         let filter = EventsReadRange::VersionRange {
             from_version: version.clone(),
-            to_version: EventVersion(version.0 + 1),
+            to_version: version.add(1)?,
         };
         let events = self.get_events(stream_id, &filter).await?;
-        Ok(events[0].clone())
+        events
+            .into_iter()
+            .find(|e| e.version == *version)
+            .ok_or_else(|| EventStoreError::EventNotFound {
+                stream_id: stream_id.to_string(),
+                version: version.0.to_i64(),
+            })
     }
 
     async fn get_events(
         &self,
         stream_id: &str,
-        version: &EventsReadRange<EventVersion>,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        let events: Vec<EventRead<Payload, Meta, EventVersion>> = self
-            .events
-            .values()
-            .filter(|x| x.stream_id == *stream_id)
-            .cloned()
-            .collect();
+        range: &EventsReadRange<EventVersion<N>>,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        self.get_events_stream(stream_id, range).try_collect().await
+    }
 
-        let mut fetch = match version {
-            EventsReadRange::AllEvents => events,
-            EventsReadRange::FromVersion(v) => events
-                .iter()
-                .filter(|p| p.version.0 >= v.0)
-                .cloned()
-                .collect(),
-            EventsReadRange::ToVersion(v) => events
-                .iter()
-                .filter(|p| p.version.0 > 0 && p.version.0 <= v.0)
-                .cloned()
-                .collect(),
-            EventsReadRange::VersionRange {
-                from_version,
-                to_version,
-            } => events
-                .iter()
-                .filter(|p| p.version.0 >= from_version.0 && p.version.0 <= to_version.0)
-                .cloned()
-                .collect(),
-        };
+    fn get_events_stream<'a>(
+        &'a self,
+        stream_id: &'a str,
+        range: &'a EventsReadRange<EventVersion<N>>,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, EventVersion<N>>>> {
+        let (direction, max_count) = page_of(range);
+        self.lazy_events(
+            |x| x.stream_id == *stream_id && in_range(&x.version, range),
+            direction,
+            max_count,
+        )
+    }
 
-        fetch.sort_by(|a, b| a.version.0.cmp(&b.version.0));
-        Ok(fetch)
+    async fn get_all_events(
+        &self,
+        from_position: i64,
+        page_size: usize,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        let mut events = self.visible_events(|x| x.position >= from_position)?;
+        events.sort_by_key(|a| a.position);
+        events.truncate(page_size);
+        Ok(events)
     }
 
     async fn get_events_by_correlation_id(
         &self,
         correlation_id: &Uuid,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        Ok(self
-            .events
-            .values()
-            .filter(|x| x.correlation_id == Some(*correlation_id))
-            .cloned()
-            .collect())
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        self.get_events_by_correlation_id_stream(correlation_id)
+            .try_collect()
+            .await
+    }
+
+    fn get_events_by_correlation_id_stream<'a>(
+        &'a self,
+        correlation_id: &'a Uuid,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, EventVersion<N>>>> {
+        self.lazy_events(
+            |x| x.correlation_id == Some(*correlation_id),
+            ReadDirection::Forward,
+            None,
+        )
     }
 
     async fn get_events_by_causation_id(
         &self,
         causation_id: &Uuid,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        Ok(self
-            .events
-            .values()
-            .filter(|x| x.causation_id == Some(*causation_id))
-            .cloned()
-            .collect())
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        let mut events = self.visible_events(|x| x.causation_id == Some(*causation_id))?;
+        events.sort_by_key(|a| a.position);
+        Ok(events)
+    }
+
+    async fn get_events_by_name(
+        &self,
+        name: &str,
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        self.get_events_by_names(&[name], order).await
+    }
+
+    async fn get_events_by_names(
+        &self,
+        names: &[&str],
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        let mut events = self.visible_events(|x| names.contains(&x.name.as_str()))?;
+        match order {
+            EventOrder::Position => events.sort_by_key(|a| a.position),
+            EventOrder::CreatedUtc => events.sort_by_key(|a| (a.created_utc, a.position)),
+        }
+        Ok(events)
+    }
+
+    async fn get_events_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        filter: &StreamsReadFilter,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion<N>>>> {
+        let mut events = self.visible_events(|x| {
+            x.created_utc >= *from && x.created_utc < *to && stream_id_filter(filter, &x.stream_id)
+        })?;
+        events.sort_by_key(|a| (a.created_utc, a.position));
+        Ok(events)
     }
 
     async fn get_streams(
         &self,
         filter: &StreamsReadFilter,
-    ) -> Result<Vec<EventStream<EventVersion>>> {
-        match filter {
-            StreamsReadFilter::AllStreams => Ok(self.streams.values().cloned().collect()),
-            StreamsReadFilter::StartsWith(c) => {
-                let keys: Vec<String> = self
-                    .streams
-                    .keys()
-                    .filter(|p| p.starts_with(c))
-                    .map(|x| x.to_string())
-                    .collect();
-                let res = self.get_stream_values(keys);
-                Ok(res)
-            }
-            StreamsReadFilter::EndsWith(c) => {
-                let keys = self
-                    .streams
-                    .keys()
-                    .filter(|p| p.ends_with(c))
-                    .map(|x| x.to_string())
-                    .collect();
-                let res = self.get_stream_values(keys);
-                Ok(res)
-            }
-            StreamsReadFilter::Contains(c) => {
-                let keys = self
-                    .streams
-                    .keys()
-                    .filter(|p| p.contains(c))
-                    .map(|x| x.to_string())
-                    .collect();
-                let res = self.get_stream_values(keys);
-                Ok(res)
-            }
-        }
+        deleted: DeletedStreams,
+    ) -> Result<Vec<EventStream<EventVersion<N>>>> {
+        self.get_stream_values(|s| stream_filter(filter, deleted, s))
     }
 
-    async fn get_stream(&self, stream_id: &str) -> Result<EventStream<EventVersion>> {
-        let res = self.streams.get(stream_id);
-        match res {
-            None => {
-                bail!("StreamID: {} not present in store", stream_id.to_string())
-            }
-            Some(r) => Ok(r.clone()),
-        }
+    fn get_streams_stream<'a>(
+        &'a self,
+        filter: &'a StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> BoxStream<'a, Result<EventStream<EventVersion<N>>>> {
+        let keys: Vec<String> = match read(&self.streams) {
+            Ok(streams) => streams
+                .values()
+                .filter(|s| stream_filter(filter, deleted, s))
+                .map(|s| s.id.clone())
+                .collect(),
+            Err(e) => return stream::once(future::ready(Err(e))).boxed(),
+        };
+
+        stream::iter(keys)
+            .filter_map(move |key| {
+                let stream = match read(&self.streams) {
+                    Ok(streams) => streams.get(&key).cloned().map(Ok),
+                    Err(e) => Some(Err(e)),
+                };
+                future::ready(stream)
+            })
+            .boxed()
+    }
+
+    async fn get_stream(&self, stream_id: &str) -> Result<EventStream<EventVersion<N>>> {
+        let res = read(&self.streams)?
+            .get(stream_id)
+            .filter(|s| s.state == StreamState::Active)
+            .cloned();
+        res.ok_or_else(|| EventStoreError::StreamNotFound(stream_id.to_string()))
+    }
+
+    async fn delete_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()> {
+        self.remove_stream(stream_id, mode)
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<EventVersion<N>>,
+    ) -> Result<EventStream<EventVersion<N>>> {
+        self.update_metadata(stream_id, metadata)
+    }
+
+    async fn scavenge(&self) -> Result<u64> {
+        self.remove_hidden_events()
     }
 }
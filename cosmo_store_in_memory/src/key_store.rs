@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use cosmo_store::traits::key_store::KeyStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[derive(Default)]
+pub struct KeyStoreInMemory {
+    // Shredded subjects are kept without a key
+    keys: RwLock<HashMap<String, Option<Vec<u8>>>>,
+}
+
+impl KeyStoreInMemory {
+    pub fn new() -> KeyStoreInMemory {
+        KeyStoreInMemory::default()
+    }
+}
+
+#[async_trait]
+impl KeyStore for KeyStoreInMemory {
+    async fn get_key(&self, subject: &str) -> Result<Option<Vec<u8>>> {
+        let keys = self
+            .keys
+            .read()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        Ok(keys.get(subject).cloned().flatten())
+    }
+
+    async fn create_key(&self, subject: &str, key: &[u8]) -> Result<Vec<u8>> {
+        let mut keys = self
+            .keys
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        keys.entry(subject.to_string())
+            .or_insert_with(|| Some(key.to_vec()))
+            .clone()
+            .ok_or_else(|| EventStoreError::SubjectShredded(subject.to_string()))
+    }
+
+    async fn delete_key(&self, subject: &str) -> Result<()> {
+        let mut keys = self
+            .keys
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        keys.insert(subject.to_string(), None);
+        Ok(())
+    }
+}
@@ -1 +1,4 @@
+pub mod checkpoint_store;
 pub mod event_store;
+pub mod key_store;
+pub mod snapshot_store;
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use cosmo_store::common::event_version::{EventVersion, VersionNumber};
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::snapshot::Snapshot;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+pub struct SnapshotStoreInMemory<State, Version: Eq + PartialEq> {
+    snapshots: RwLock<HashMap<String, Vec<Snapshot<State, Version>>>>,
+}
+
+impl<State: Clone, N: VersionNumber> Default for SnapshotStoreInMemory<State, EventVersion<N>> {
+    fn default() -> Self {
+        SnapshotStoreInMemory::new()
+    }
+}
+
+impl<State: Clone, N: VersionNumber> SnapshotStoreInMemory<State, EventVersion<N>> {
+    pub fn new() -> SnapshotStoreInMemory<State, EventVersion<N>> {
+        SnapshotStoreInMemory {
+            snapshots: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl<State: Clone, N: VersionNumber> SnapshotStore<State, EventVersion<N>>
+    for SnapshotStoreInMemory<State, EventVersion<N>>
+where
+    State: Send + Sync + 'static,
+{
+    async fn save_snapshot(
+        &self,
+        stream_id: &str,
+        version: &EventVersion<N>,
+        state: &State,
+    ) -> Result<Snapshot<State, EventVersion<N>>> {
+        let snapshot = Snapshot {
+            stream_id: stream_id.to_string(),
+            version: version.clone(),
+            state: state.clone(),
+            created_utc: Utc::now(),
+        };
+        let mut snapshots = self
+            .snapshots
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        let stream = snapshots.entry(stream_id.to_string()).or_default();
+        stream.retain(|s| s.version != *version);
+        stream.push(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    async fn get_latest_snapshot(
+        &self,
+        stream_id: &str,
+    ) -> Result<Option<Snapshot<State, EventVersion<N>>>> {
+        let snapshots = self
+            .snapshots
+            .read()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        let res = snapshots
+            .get(stream_id)
+            .and_then(|s| s.iter().max_by_key(|x| x.version.0))
+            .cloned();
+        Ok(res)
+    }
+
+    async fn delete_snapshots(&self, stream_id: &str) -> Result<()> {
+        let mut snapshots = self
+            .snapshots
+            .write()
+            .map_err(|e| EventStoreError::backend(e.to_string()))?;
+        let _ = snapshots.remove(stream_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use cosmo_store_in_memory::checkpoint_store::CheckpointStoreInMemory;
+use cosmo_store_tests::checkpoint_store_basic_tests as cs;
+
+#[actix_rt::test]
+async fn save_checkpoint() {
+    cs::save_checkpoint(&CheckpointStoreInMemory::new(), |res| {
+        assert_eq!(res, Some(7));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn get_checkpoint_of_unknown_projection() {
+    cs::get_checkpoint_of_unknown_projection(&CheckpointStoreInMemory::new(), |res| {
+        assert!(res.is_none());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn checkpoints_are_kept_per_projection() {
+    cs::checkpoints_are_kept_per_projection(&CheckpointStoreInMemory::new(), |people, orders| {
+        assert_eq!(people, Some(3));
+        assert_eq!(orders, Some(5));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn reset_checkpoint() {
+    cs::reset_checkpoint(&CheckpointStoreInMemory::new(), |people, orders| {
+        assert!(people.is_none());
+        assert_eq!(orders, Some(5));
+    })
+    .await;
+}
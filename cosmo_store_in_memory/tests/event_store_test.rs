@@ -1,29 +1,35 @@
+use cosmo_store::common::event_version;
 use cosmo_store::common::u32_event_version::EventVersion;
 use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::delete_mode::DeleteMode;
 use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::EventOrder;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::StreamState;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_metadata::StreamMetadata;
 use cosmo_store_in_memory::event_store::EventStoreInMemory;
-use cosmo_store_tests::event_generator::{get_event, get_stream_id};
 use cosmo_store_tests::event_store_basic_tests as bt;
 use cosmo_store_tests::event_store_basic_tests::{check_position, Meta, Payload};
-use std::collections::HashMap;
-use std::future::Future;
+use itertools::Itertools;
 use std::panic;
+use uuid::Uuid;
 
-fn setup() {
-    println!("Event Store will be initialized here...");
-}
-
-fn teardown() {
-    println!("Event Store will be destroyed here");
-}
+// fn setup() {
+//     println!("Event Store will be initialized here...");
+// }
+//
+// fn teardown() {
+//     println!("Event Store will be destroyed here");
+// }
 
 fn get_store() -> impl EventStore<Payload, Meta, EventVersion> {
     EventStoreInMemory::new()
 }
 
-fn run_test<T>(test: T) -> ()
+fn run_test<T>(test: T)
 where
-    T: FnOnce(&dyn EventStore<Payload, Meta, EventVersion>) -> () + panic::UnwindSafe,
+    T: FnOnce(&dyn EventStore<Payload, Meta, EventVersion>) + panic::UnwindSafe,
 {
     // setup();
     let store: EventStoreInMemory<Payload, Meta, EventVersion> = EventStoreInMemory::new();
@@ -47,15 +53,19 @@ fn are_ascending(items: Vec<EventRead<Payload, Meta, EventVersion>>) {
 
 #[actix_rt::test]
 async fn append_event() {
-    bt::append_event(Box::new(get_store()), |res| {
-        assert_eq!(res.version.0, 1_u32)
-    })
-    .await;
+    bt::append_event(&get_store(), |res| assert_eq!(res.version.0, 1_u32)).await;
+}
+
+#[actix_rt::test]
+async fn append_event_with_shared_version_type() {
+    let store: EventStoreInMemory<Payload, Meta, event_version::EventVersion> =
+        EventStoreInMemory::new();
+    bt::append_event(&store, |res| assert_eq!(res.version.0, 1_i64)).await;
 }
 
 #[actix_rt::test]
 async fn append_100_events() {
-    bt::append_100_events(Box::new(get_store()), |res| {
+    bt::append_100_events(&get_store(), |res| {
         assert_eq!(res.len(), 100);
         are_ascending(res);
     })
@@ -64,7 +74,7 @@ async fn append_100_events() {
 
 #[actix_rt::test]
 async fn get_single_event() {
-    bt::get_single_event(Box::new(get_store()), &EventVersion::new(3_u32), |res| {
+    bt::get_single_event(&get_store(), &EventVersion::new(3_u32), |res| {
         assert_eq!(res.version.0, 3_u32);
         assert_eq!(res.name, "Created_3");
     })
@@ -73,7 +83,7 @@ async fn get_single_event() {
 
 #[actix_rt::test]
 async fn get_all_events() {
-    bt::get_all_events(Box::new(get_store()), |res| {
+    bt::get_all_events(&get_store(), |res| {
         assert_eq!(res.len(), 10);
     })
     .await;
@@ -81,7 +91,7 @@ async fn get_all_events() {
 
 #[actix_rt::test]
 async fn get_events_from_version() {
-    bt::get_events_from_version(Box::new(get_store()), EventVersion::new(6), |res| {
+    bt::get_events_from_version(&get_store(), EventVersion::new(6), |res| {
         assert_eq!(res.len(), 5);
         are_ascending(res);
     })
@@ -90,7 +100,7 @@ async fn get_events_from_version() {
 
 #[actix_rt::test]
 async fn get_events_to_version() {
-    bt::get_events_to_version(Box::new(get_store()), EventVersion::new(5), |res| {
+    bt::get_events_to_version(&get_store(), EventVersion::new(5), |res| {
         assert_eq!(res.len(), 5);
         are_ascending(res);
     })
@@ -100,7 +110,7 @@ async fn get_events_to_version() {
 #[actix_rt::test]
 async fn get_events_version_range() {
     bt::get_events_version_range(
-        Box::new(get_store()),
+        &get_store(),
         EventVersion::new(5),
         EventVersion::new(7),
         |res| {
@@ -110,3 +120,446 @@ async fn get_events_version_range() {
     )
     .await;
 }
+
+#[actix_rt::test]
+async fn fails_to_append_to_existing_version() {
+    bt::fails_to_append_to_existing_version(&get_store(), EventVersion::new(1_u32), |res| {
+        assert!(matches!(
+            res,
+            Err(EventStoreError::WrongExpectedVersion {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn fails_to_append_to_existing_stream_if_is_not_expected_to_exist() {
+    bt::fails_to_append_to_existing_stream_if_is_not_expected_to_exist(&get_store(), |res| {
+        assert!(matches!(
+            res,
+            Err(EventStoreError::WrongExpectedVersion { .. })
+        ));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn appending_no_events_does_not_affect_stream_metadata() {
+    bt::appending_no_events_does_not_affect_stream_metadata(
+        &get_store(),
+        &ExpectedVersion::Exact(EventVersion::new(1_u32)),
+        |stream, stream_after_append| {
+            assert_eq!(stream, stream_after_append);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn appending_1000_events_can_be_read_back() {
+    bt::appending_1000_events_can_be_read_back(&get_store(), |stream, events| {
+        assert_eq!(stream.last_version.0, 1000);
+        assert_eq!(events.len(), 1000)
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_correlation_id() {
+    bt::can_read_events_by_correlation_id(&get_store(), |events| {
+        let unique_streams: Vec<String> = events
+            .iter()
+            .map(|x| x.stream_id.to_owned())
+            .unique()
+            .sorted()
+            .collect();
+        assert_eq!(unique_streams, vec!["CORR_1", "CORR_2", "CORR_3"]);
+        assert_eq!(events.len(), 30);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_causation_id() {
+    bt::can_read_events_by_causation_id(&get_store(), |events| {
+        let names: Vec<String> = events.iter().map(|x| x.name.clone()).collect();
+        let expected: Vec<String> = (1..=9).map(|i| format!("Created_{}", i)).collect();
+        assert_eq!(names, expected);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn get_missing_event_is_typed_error() {
+    let res = get_store()
+        .get_event("missing", &EventVersion::new(1))
+        .await;
+    assert!(matches!(res, Err(EventStoreError::EventNotFound { .. })));
+    let res = get_store().get_stream("missing").await;
+    assert!(matches!(res, Err(EventStoreError::StreamNotFound(_))));
+}
+
+fn single_winner(
+    results: Vec<Result<Vec<EventRead<Payload, Meta, EventVersion>>>>,
+    events: Vec<EventRead<Payload, Meta, EventVersion>>,
+    expected_events: usize,
+) {
+    let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+    assert_eq!(ok.len(), 1);
+    assert!(err
+        .iter()
+        .all(|r| matches!(r, Err(EventStoreError::WrongExpectedVersion { .. }))));
+    assert_eq!(events.len(), expected_events);
+    let versions: Vec<u32> = events.iter().map(|x| x.version.0).unique().collect();
+    assert_eq!(versions, (1..=expected_events as u32).collect::<Vec<u32>>());
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_same_expected_version() {
+    bt::concurrent_writers_with_same_expected_version(
+        &get_store(),
+        EventVersion::new(2_u32),
+        10,
+        |results, events| single_winner(results, events, 4),
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_to_new_stream() {
+    bt::concurrent_writers_to_new_stream(&get_store(), 10, |results, events| {
+        single_winner(results, events, 3)
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_any_version() {
+    bt::concurrent_writers_with_any_version(&get_store(), 10, |results, events| {
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(events.len(), 30);
+        are_ascending(events);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_of_same_events() {
+    bt::concurrent_writers_of_same_events(&get_store(), 10, |results| {
+        let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+        assert_eq!(ok.len(), 1);
+        assert!(err
+            .iter()
+            .all(|r| matches!(r, Err(EventStoreError::DuplicateEvents { .. }))));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_all_events_from_position() {
+    bt::can_read_all_events_from_position(&get_store(), 4, |pages| {
+        assert!(pages.iter().all(|p| p.len() <= 4));
+        let positions: Vec<i64> = pages.iter().flatten().map(|x| x.position).collect();
+        assert_eq!(positions, (1..=27).collect::<Vec<i64>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_stream_events_from_version() {
+    bt::can_stream_events_from_version(&get_store(), EventVersion::new(501), |res| {
+        let versions: Vec<u32> = res.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, (501..=1000).collect::<Vec<u32>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_stream_events_by_correlation_id() {
+    bt::can_stream_events_by_correlation_id(&get_store(), |res| {
+        assert_eq!(res.len(), 15);
+        assert!(res.windows(2).all(|w| w[0].position < w[1].position));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_stream_streams_with_filter() {
+    bt::can_stream_streams_with_filter(&get_store(), |res| {
+        let mut ids: Vec<String> = res.into_iter().map(|x| x.id).collect();
+        ids.sort();
+        assert_eq!(ids.len(), 2);
+        assert!(ids[0].ends_with("_first"));
+        assert!(ids[1].ends_with("_second"));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn stream_filters_match_wildcards_literally() {
+    bt::stream_filters_match_wildcards_literally(&get_store(), |matched| {
+        assert_eq!(matched, vec![vec!["_1"], vec!["%1"], vec!["\\1"]]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_forward_in_pages() {
+    bt::can_read_events_forward_in_pages(&get_store(), 10, |pages| {
+        let sizes: Vec<usize> = pages.iter().map(|p| p.len()).collect();
+        assert_eq!(sizes, vec![10, 10, 5]);
+        let versions: Vec<u32> = pages.iter().flatten().map(|x| x.version.0).collect();
+        assert_eq!(versions, (1..=25).collect::<Vec<u32>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_last_events_backwards() {
+    bt::can_read_events_backwards(&get_store(), None, 10, |res| {
+        let versions: Vec<u32> = res.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, (16..=25).rev().collect::<Vec<u32>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_backwards_from_version() {
+    bt::can_read_events_backwards(&get_store(), Some(EventVersion::new(12)), 5, |res| {
+        let versions: Vec<u32> = res.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, (8..=12).rev().collect::<Vec<u32>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn soft_deleted_stream_can_be_recreated() {
+    bt::soft_deleted_stream_can_be_recreated(&get_store(), |deleted, events| {
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].state, StreamState::Deleted);
+        assert_eq!(deleted[0].last_version.0, 3);
+        let versions: Vec<u32> = events.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, vec![4, 5]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn deleted_events_are_hidden_from_all_reads() {
+    bt::deleted_events_are_hidden_from_all_reads(&get_store(), |correlated, all| {
+        assert_eq!(correlated.len(), 2);
+        assert_eq!(all.len(), 2);
+        let ids = |events: &Vec<EventRead<Payload, Meta, EventVersion>>| {
+            events.iter().map(|x| x.id).collect::<Vec<Uuid>>()
+        };
+        assert_eq!(ids(&correlated), ids(&all));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn hard_deleted_stream_is_removed() {
+    bt::hard_deleted_stream_is_removed(&get_store(), |last_position, recreated| {
+        assert_eq!(recreated[0].version.0, 1);
+        assert!(recreated[0].position > last_position);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn tombstoned_stream_rejects_appends() {
+    bt::tombstoned_stream_rejects_appends(&get_store(), |appended, deleted, listed| {
+        assert!(matches!(
+            appended,
+            Err(EventStoreError::StreamTombstoned(_))
+        ));
+        assert!(matches!(deleted, Err(EventStoreError::StreamTombstoned(_))));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].state, StreamState::Tombstoned);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn deleting_missing_stream_is_typed_error() {
+    let res = EventStore::<Payload, Meta, EventVersion>::delete_stream(
+        &get_store(),
+        "missing",
+        DeleteMode::Soft,
+    )
+    .await;
+    assert!(matches!(res, Err(EventStoreError::StreamNotFound(_))));
+}
+
+#[actix_rt::test]
+async fn max_count_hides_older_events() {
+    bt::max_count_hides_older_events(&get_store(), |stream, events| {
+        assert_eq!(stream.metadata.max_count, Some(3));
+        let versions: Vec<u32> = events.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, vec![8, 9, 10]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn truncate_before_hides_earlier_events() {
+    bt::truncate_before_hides_earlier_events(&get_store(), EventVersion::new(6), |events| {
+        let versions: Vec<u32> = events.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, (6..=10).collect::<Vec<u32>>());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn deleted_events_stay_hidden_after_metadata_change() {
+    bt::deleted_events_stay_hidden_after_metadata_change(
+        &get_store(),
+        EventVersion::new(2),
+        |stream, events| {
+            assert_eq!(stream.metadata.truncate_before, Some(EventVersion::new(2)));
+            let versions: Vec<u32> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![4, 5]);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn max_age_hides_expired_events() {
+    bt::max_age_hides_expired_events(&get_store(), |events| {
+        let versions: Vec<u32> = events.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, vec![4, 5]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn scavenge_removes_hidden_events() {
+    bt::scavenge_removes_hidden_events(&get_store(), |removed, removed_again, remaining| {
+        assert_eq!(removed, 9);
+        assert_eq!(removed_again, 0);
+        let versions: Vec<u32> = remaining.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, vec![8, 9, 10]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn setting_metadata_of_missing_stream_is_typed_error() {
+    let res = EventStore::<Payload, Meta, EventVersion>::set_stream_metadata(
+        &get_store(),
+        "missing",
+        &StreamMetadata::default(),
+    )
+    .await;
+    assert!(matches!(res, Err(EventStoreError::StreamNotFound(_))));
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_position_order() {
+    bt::can_read_events_by_name(&get_store(), EventOrder::Position, |single, multiple| {
+        assert!(single.iter().all(|x| x.name == "Created_2"));
+        let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![2, 5]);
+        let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![1, 3, 4, 6]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_time_order() {
+    bt::can_read_events_by_name(&get_store(), EventOrder::CreatedUtc, |single, multiple| {
+        assert!(single.iter().all(|x| x.name == "Created_2"));
+        let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![2, 5]);
+        let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![1, 3, 4, 6]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn can_read_events_in_time_range() {
+    bt::can_read_events_in_time_range(&get_store(), |all, single| {
+        let positions: Vec<i64> = all.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![3, 4, 5, 6]);
+        let versions: Vec<u32> = single.iter().map(|x| x.version.0).collect();
+        assert_eq!(versions, vec![3, 4]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn appends_to_multiple_streams_atomically() {
+    bt::appends_to_multiple_streams_atomically(
+        &get_store(),
+        EventVersion::new(3),
+        |results, source, target| {
+            let counts: Vec<usize> = results.iter().map(|r| r.len()).collect();
+            assert_eq!(counts, vec![1, 2]);
+            assert!(results[1][0].position > results[0][0].position);
+            let versions: Vec<u32> = source.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2, 3]);
+            let versions: Vec<u32> = target.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2]);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn multi_stream_append_fails_as_a_whole() {
+    bt::multi_stream_append_fails_as_a_whole(
+        &get_store(),
+        EventVersion::new(2),
+        |result, source, target| {
+            assert!(matches!(
+                result,
+                Err(EventStoreError::WrongExpectedVersion { .. })
+            ));
+            assert_eq!(source.len(), 2);
+            assert!(matches!(target, Err(EventStoreError::StreamNotFound(_))));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn concurrent_multi_stream_appends() {
+    bt::concurrent_multi_stream_appends(&get_store(), 10, |results, first, second| {
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(first.len(), 20);
+        assert_eq!(second.len(), 20);
+        are_ascending(first);
+        are_ascending(second);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn retried_append_returns_original_events() {
+    bt::retried_append_returns_original_events(&get_store(), |first, retried, events| {
+        let keys: Vec<(Uuid, i64)> = first.iter().map(|x| (x.id, x.position)).collect();
+        let retried_keys: Vec<(Uuid, i64)> = retried.iter().map(|x| (x.id, x.position)).collect();
+        assert_eq!(retried_keys, keys);
+        assert_eq!(events.len(), 3);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn overlapping_append_is_rejected() {
+    bt::overlapping_append_is_rejected(&get_store(), |result, events| {
+        assert!(matches!(
+            result,
+            Err(EventStoreError::DuplicateEvents { ids, .. }) if ids.len() == 1
+        ));
+        assert_eq!(events.len(), 2);
+    })
+    .await;
+}
@@ -0,0 +1,40 @@
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store_in_memory::key_store::KeyStoreInMemory;
+use cosmo_store_tests::key_store_basic_tests as ks;
+
+#[actix_rt::test]
+async fn create_key() {
+    ks::create_key(&KeyStoreInMemory::new(), |created, res| {
+        assert_eq!(created, vec![1, 2, 3]);
+        assert_eq!(res, Some(vec![1, 2, 3]));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn create_key_keeps_existing_key() {
+    ks::create_key_keeps_existing_key(&KeyStoreInMemory::new(), |res| {
+        assert_eq!(res, vec![1]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn get_key_of_unknown_subject() {
+    ks::get_key_of_unknown_subject(&KeyStoreInMemory::new(), |res| {
+        assert!(res.is_none());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn delete_key_shreds_subject() {
+    ks::delete_key_shreds_subject(&KeyStoreInMemory::new(), |key, recreated| {
+        assert!(key.is_none());
+        assert!(matches!(
+            recreated,
+            Err(EventStoreError::SubjectShredded(_))
+        ));
+    })
+    .await;
+}
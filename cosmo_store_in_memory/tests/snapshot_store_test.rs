@@ -0,0 +1,66 @@
+use cosmo_store::common::u32_event_version::EventVersion;
+use cosmo_store_in_memory::snapshot_store::SnapshotStoreInMemory;
+use cosmo_store_tests::event_store_basic_tests::Payload;
+use cosmo_store_tests::snapshot_store_basic_tests as st;
+
+fn get_store() -> SnapshotStoreInMemory<Payload, EventVersion> {
+    SnapshotStoreInMemory::new()
+}
+
+#[actix_rt::test]
+async fn save_snapshot() {
+    st::save_snapshot(&get_store(), EventVersion::new(3), |res| {
+        assert_eq!(res.version.0, 3);
+        assert_eq!(res.state.name, "State 1");
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot() {
+    st::get_latest_snapshot(
+        &get_store(),
+        vec![
+            EventVersion::new(2),
+            EventVersion::new(7),
+            EventVersion::new(5),
+        ],
+        |res| {
+            let res = res.unwrap();
+            assert_eq!(res.version.0, 7);
+            assert_eq!(res.state.name, "State 1");
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot_of_unknown_stream() {
+    st::get_latest_snapshot_of_unknown_stream(&get_store(), |res| {
+        assert!(res.is_none());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn saving_same_version_replaces_snapshot() {
+    st::saving_same_version_replaces_snapshot(&get_store(), EventVersion::new(4), |res| {
+        let res = res.unwrap();
+        assert_eq!(res.version.0, 4);
+        assert_eq!(res.state.name, "State 2");
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn delete_snapshots() {
+    st::delete_snapshots(
+        &get_store(),
+        vec![EventVersion::new(2), EventVersion::new(4)],
+        |deleted, other| {
+            assert!(deleted.is_none());
+            assert_eq!(other.unwrap().state.name, "State 100");
+        },
+    )
+    .await;
+}
@@ -0,0 +1,46 @@
+use crate::checkpoint_store_sqlx_postgres::CheckpointStoreSQLXPostgres;
+use async_trait::async_trait;
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+
+#[async_trait]
+impl CheckpointStore for CheckpointStoreSQLXPostgres {
+    async fn get_checkpoint(&self, projection: &str) -> Result<Option<i64>> {
+        let select_checkpoint = format!(
+            "select position from {0} where projection = $1",
+            self.table_name()
+        );
+        let res: Option<i64> = sqlx::query_scalar(&select_checkpoint)
+            .bind(projection)
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(res)
+    }
+
+    async fn save_checkpoint(&self, projection: &str, position: i64) -> Result<()> {
+        let save_checkpoint = format!(
+            "insert into {0} (projection, position) values ($1, $2) \
+            on conflict (projection) \
+            do update set position = excluded.position, updated_utc = current_timestamp",
+            self.table_name()
+        );
+        let _ = sqlx::query(&save_checkpoint)
+            .bind(projection)
+            .bind(position)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(())
+    }
+
+    async fn reset_checkpoint(&self, projection: &str) -> Result<()> {
+        let delete_checkpoint = format!("delete from {0} where projection = $1", self.table_name());
+        let _ = sqlx::query(&delete_checkpoint)
+            .bind(projection)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(())
+    }
+}
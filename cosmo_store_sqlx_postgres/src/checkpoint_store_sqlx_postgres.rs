@@ -0,0 +1,48 @@
+use anyhow::Result;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone)]
+pub struct CheckpointStoreSQLXPostgres {
+    pool: PgPool,
+    table_name: String,
+}
+
+impl CheckpointStoreSQLXPostgres {
+    pub fn pool(&self) -> PgPool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_checkpoint_table(pool: &PgPool, table_name: &str) -> Result<PgQueryResult> {
+        // create table if not exists cs_checkpoints_person (
+        //     projection text not null primary key,
+        // position bigint not null,
+        // updated_utc timestamptz default current_timestamp
+        // );
+        let checkpoint_create_table = format!(
+            "create table if not exists {0} (\
+                    projection text not null primary key,\
+                    position bigint not null,\
+                    updated_utc timestamptz default current_timestamp)",
+            table_name
+        );
+
+        let res = sqlx::query(&checkpoint_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &PgPool, name: &str) -> Result<CheckpointStoreSQLXPostgres> {
+        let checkpoint_name = format!("cs_checkpoints_{}", name);
+        let _ =
+            CheckpointStoreSQLXPostgres::create_checkpoint_table(pool, &checkpoint_name).await?;
+
+        Ok(CheckpointStoreSQLXPostgres {
+            pool: pool.clone(),
+            table_name: checkpoint_name,
+        })
+    }
+}
@@ -1,9 +1,36 @@
 use crate::command_store_sqlx_postgres::CommandStoreSQLXPostgres;
-use anyhow::Result;
+use crate::db_types::{command_status_from_db, command_status_to_db, DBCommandData};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use cosmo_store::traits::command_store::CommandStore;
-use cosmo_store::types::command_write::CommandWrite;
+use cosmo_store::types::command_status::CommandStatus;
+use cosmo_store::types::command_write::{CommandRead, CommandWrite};
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+fn db_command_to_command_read<Payload>(d: DBCommandData) -> Result<CommandRead<Payload>>
+where
+    Payload: for<'de> Deserialize<'de>,
+{
+    Ok(CommandRead {
+        id: d.id,
+        correlation_id: d.correlation_id,
+        causation_id: d.causation_id,
+        data: serde_json::from_value(d.data).map_err(EventStoreError::serialization)?,
+        name: d.name,
+        status: command_status_from_db(&d.status, d.failure_reason, d.event_ids)?,
+        created_utc: d.created_utc,
+    })
+}
+
+fn invalid_transition(id: &Uuid, from: &CommandStatus, to: &CommandStatus) -> EventStoreError {
+    EventStoreError::InvalidCommandTransition {
+        id: *id,
+        from: from.name().to_string(),
+        to: to.name().to_string(),
+    }
+}
 
 #[async_trait]
 impl<Payload> CommandStore<Payload> for CommandStoreSQLXPostgres
@@ -21,8 +48,13 @@ where
         values ($1, $2, $3, $4, $5)",
             self.table_name()
         );
-        let data = serde_json::to_value(payload.data.clone())?;
-        let mut tr = self.pool().begin().await?;
+        let data =
+            serde_json::to_value(payload.data.clone()).map_err(EventStoreError::serialization)?;
+        let mut tr = self
+            .pool()
+            .begin()
+            .await
+            .map_err(EventStoreError::backend)?;
         let _ = sqlx::query(&insert_command)
             .bind(payload.id)
             .bind(payload.correlation_id)
@@ -30,9 +62,138 @@ where
             .bind(data)
             .bind(payload.name.clone())
             .execute(&mut *tr)
-            .await?;
+            .await
+            .map_err(EventStoreError::backend)?;
 
-        tr.commit().await?;
+        tr.commit().await.map_err(EventStoreError::backend)?;
         Ok(())
     }
+
+    async fn receive_command(
+        &self,
+        payload: &CommandWrite<Payload>,
+    ) -> Result<CommandRead<Payload>> {
+        let insert_command = format!(
+            "insert into {0} (id, correlation_id, causation_id, data, name) values ($1, $2, $3, $4, $5) \
+            on conflict (id) do nothing",
+            self.table_name()
+        );
+        let data =
+            serde_json::to_value(payload.data.clone()).map_err(EventStoreError::serialization)?;
+        let _ = sqlx::query(&insert_command)
+            .bind(payload.id)
+            .bind(payload.correlation_id)
+            .bind(payload.causation_id)
+            .bind(data)
+            .bind(payload.name.clone())
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        CommandStore::<Payload>::get_command(self, &payload.id).await
+    }
+
+    async fn transition_command(
+        &self,
+        id: &Uuid,
+        status: &CommandStatus,
+    ) -> Result<CommandRead<Payload>> {
+        let current = CommandStore::<Payload>::get_command(self, id).await?;
+        if !current.status.can_transition_to(status) {
+            return Err(invalid_transition(id, &current.status, status));
+        }
+        // Only moves the command on if nobody else did in the meantime
+        let update_command = format!(
+            "update {0} set status = $1, failure_reason = $2, event_ids = $3 \
+            where id = $4 and status = $5 \
+            returning *",
+            self.table_name()
+        );
+        let (failure_reason, event_ids) = command_status_to_db(status);
+        let res = sqlx::query_as::<_, DBCommandData>(&update_command)
+            .bind(status.name())
+            .bind(failure_reason)
+            .bind(event_ids)
+            .bind(id)
+            .bind(current.status.name())
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        match res {
+            Some(d) => db_command_to_command_read(d),
+            None => {
+                let current = CommandStore::<Payload>::get_command(self, id).await?;
+                Err(invalid_transition(id, &current.status, status))
+            }
+        }
+    }
+
+    async fn get_command(&self, id: &Uuid) -> Result<CommandRead<Payload>> {
+        let select_command = format!("select * from {0} where id = $1", self.table_name());
+        let res = sqlx::query_as::<_, DBCommandData>(&select_command)
+            .bind(id)
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        match res {
+            Some(d) => db_command_to_command_read(d),
+            None => Err(EventStoreError::CommandNotFound(*id)),
+        }
+    }
+
+    async fn get_commands_by_correlation_id(
+        &self,
+        correlation_id: &Uuid,
+    ) -> Result<Vec<CommandRead<Payload>>> {
+        self.get_commands_where("correlation_id = $1", *correlation_id)
+            .await
+    }
+
+    async fn get_commands_by_causation_id(
+        &self,
+        causation_id: &Uuid,
+    ) -> Result<Vec<CommandRead<Payload>>> {
+        self.get_commands_where("causation_id = $1", *causation_id)
+            .await
+    }
+
+    async fn get_commands_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> Result<Vec<CommandRead<Payload>>> {
+        let time_query = format!(
+            "select * from {0} where created_utc >= $1 and created_utc < $2 order by created_utc",
+            self.table_name()
+        );
+        let res = sqlx::query_as::<_, DBCommandData>(&time_query)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        res.into_iter().map(db_command_to_command_read).collect()
+    }
+}
+
+impl CommandStoreSQLXPostgres {
+    async fn get_commands_where<Payload>(
+        &self,
+        condition: &str,
+        id: Uuid,
+    ) -> Result<Vec<CommandRead<Payload>>>
+    where
+        Payload: for<'de> Deserialize<'de>,
+    {
+        let select_commands = format!(
+            "select * from {0} where {1} order by created_utc",
+            self.table_name(),
+            condition
+        );
+        let res = sqlx::query_as::<_, DBCommandData>(&select_commands)
+            .bind(id)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        res.into_iter().map(db_command_to_command_read).collect()
+    }
 }
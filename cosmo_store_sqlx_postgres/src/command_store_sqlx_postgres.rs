@@ -23,6 +23,9 @@ impl CommandStoreSQLXPostgres {
         // causation_id uuid not null ,
         // data jsonb not null ,
         // name varchar(255) not null ,
+        // status varchar(16) not null default 'received' ,
+        // failure_reason text ,
+        // event_ids jsonb ,
         // created_utc timestamptz default current_timestamp
         // )
 
@@ -33,6 +36,9 @@ impl CommandStoreSQLXPostgres {
                     causation_id uuid not null , \
                     data jsonb not null , \
                     name varchar(255) not null , \
+                    status varchar(16) not null default 'received' , \
+                    failure_reason text , \
+                    event_ids jsonb , \
                     created_utc timestamptz default current_timestamp)",
             table_name
         );
@@ -41,9 +47,24 @@ impl CommandStoreSQLXPostgres {
         Ok(res)
     }
 
+    async fn upgrade_command_table(pool: &PgPool, table_name: &str) -> Result<PgQueryResult> {
+        // Tables created by earlier versions get the columns added since
+        let upgrade_table = format!(
+            "alter table {0} \
+                    add column if not exists status varchar(16) not null default 'received', \
+                    add column if not exists failure_reason text, \
+                    add column if not exists event_ids jsonb",
+            table_name
+        );
+
+        let res: PgQueryResult = sqlx::query(&upgrade_table).execute(pool).await?;
+        Ok(res)
+    }
+
     pub async fn new(pool: &PgPool, name: &str) -> Result<CommandStoreSQLXPostgres> {
         let command_name = format!("cs_commands_{}", name);
         let _ = CommandStoreSQLXPostgres::create_command_table(pool, &command_name).await?;
+        let _ = CommandStoreSQLXPostgres::upgrade_command_table(pool, &command_name).await?;
 
         Ok(CommandStoreSQLXPostgres {
             pool: pool.clone(),
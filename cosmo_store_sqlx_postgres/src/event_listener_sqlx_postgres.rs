@@ -0,0 +1,52 @@
+use crate::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct EventNotification {
+    pub stream_id: String,
+    pub position: i64,
+}
+
+/**
+Listens on the notification channel of a single store.
+Every appended event produces one `EventNotification`, notifications of other
+stores sharing the same database are never received.
+*/
+pub struct EventListenerSQLXPostgres {
+    listener: PgListener,
+}
+
+impl EventListenerSQLXPostgres {
+    pub async fn new(store: &EventStoreSQLXPostgres) -> Result<EventListenerSQLXPostgres> {
+        let mut listener = PgListener::connect_with(&store.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        listener
+            .listen(&store.notification_channel())
+            .await
+            .map_err(EventStoreError::backend)?;
+
+        Ok(EventListenerSQLXPostgres { listener })
+    }
+
+    pub async fn recv(&mut self) -> Result<EventNotification> {
+        let notification = self
+            .listener
+            .recv()
+            .await
+            .map_err(EventStoreError::backend)?;
+        serde_json::from_str(notification.payload()).map_err(EventStoreError::serialization)
+    }
+
+    /// Wakes every task waiting on `wake` whenever events are appended, until the connection fails
+    pub async fn wake_on_append(mut self, wake: Arc<Notify>) -> Result<()> {
+        loop {
+            let _ = self.recv().await?;
+            wake.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::db_types::DBEventData;
+use crate::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JsonColumn {
+    Data,
+    Metadata,
+}
+
+impl JsonColumn {
+    fn name(&self) -> &'static str {
+        match self {
+            JsonColumn::Data => "data",
+            JsonColumn::Metadata => "metadata",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum JsonQuery {
+    /// Column contains the JSON fragment, e.g. `{"tenant": "x"}`. Served by the GIN index
+    Contains { column: JsonColumn, fragment: Value },
+    /// Value found at `path` equals `value` exactly, array elements are addressed by their index
+    PathEquals {
+        column: JsonColumn,
+        path: Vec<String>,
+        value: Value,
+    },
+}
+
+impl EventStoreSQLXPostgres {
+    /// Reads events of all streams matching the query, ordered by position.
+    /// Only events written with `Codec::Json` can match.
+    pub async fn query_events<Payload, Meta>(
+        &self,
+        query: &JsonQuery,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+    {
+        let select = |condition: String| {
+            format!(
+                "select e.* from {0} where {1} order by e.position",
+                self.visible_events(),
+                condition
+            )
+        };
+        let pool = self.pool();
+        let db_event_data = match query {
+            JsonQuery::Contains { column, fragment } => {
+                let json_query = select(format!("e.{} @> $1", column.name()));
+                sqlx::query_as::<_, DBEventData>(&json_query)
+                    .bind(fragment)
+                    .fetch_all(&pool)
+                    .await
+            }
+            JsonQuery::PathEquals {
+                column,
+                path,
+                value,
+            } => {
+                let json_query = select(format!("e.{} #> $1 = $2", column.name()));
+                sqlx::query_as::<_, DBEventData>(&json_query)
+                    .bind(path)
+                    .bind(value)
+                    .fetch_all(&pool)
+                    .await
+            }
+        }
+        .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
+    }
+}
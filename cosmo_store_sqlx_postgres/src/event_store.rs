@@ -1,121 +1,609 @@
-use crate::db_types::{DBEventData, DBEventStream};
+use crate::db_types::{
+    encoded_from_db, encoded_to_db, stream_state_to_db, DBEventData, DBEventStream,
+};
 use crate::event_store_sqlx_postgres::EventStoreSQLXPostgres;
-use anyhow::Result;
+use async_stream::try_stream;
 use async_trait::async_trait;
-use cosmo_store::common::i64_event_version::{event_writes_to_reads, updated_stream, EventVersion};
+use chrono::{DateTime, Utc};
+use cosmo_store::common::codec::Codec;
+use cosmo_store::common::event_version::{
+    already_appended, event_writes_to_reads, updated_stream, EventVersion,
+};
 use cosmo_store::traits::event_store::EventStore;
 use cosmo_store::traits::version::Version;
+use cosmo_store::types::delete_mode::DeleteMode;
 use cosmo_store::types::event_read::EventRead;
-use cosmo_store::types::event_read_range::EventsReadRange;
-use cosmo_store::types::event_stream::EventStream;
+use cosmo_store::types::event_read_range::{EventOrder, EventsReadRange, ReadDirection};
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::{EventStream, StreamState};
 use cosmo_store::types::event_write::EventWrite;
 use cosmo_store::types::expected_version::ExpectedVersion;
-use cosmo_store::types::stream_read_filter::StreamsReadFilter;
+use cosmo_store::types::stream_append::{ensure_unique_event_ids, StreamAppend};
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store::types::stream_read_filter::{DeletedStreams, StreamsReadFilter};
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use sqlx::types::Uuid;
+use sqlx::{Executor, Postgres, Transaction};
+use std::collections::HashMap;
+
+// Condition on the stream id column with the value bound to `param`, none for all streams
+fn stream_id_condition(
+    filter: &StreamsReadFilter,
+    column: &str,
+    param: &str,
+) -> Option<(String, String)> {
+    let pattern = match filter {
+        StreamsReadFilter::AllStreams => return None,
+        StreamsReadFilter::Equals(s) => {
+            return Some((format!("{} = {}", column, param), s.to_string()))
+        }
+        StreamsReadFilter::StartsWith(s) => format!("{}%", like_escape(s)),
+        StreamsReadFilter::EndsWith(s) => format!("%{}", like_escape(s)),
+        StreamsReadFilter::Contains(s) => format!("%{}%", like_escape(s)),
+    };
+    Some((format!("{} like {} escape '\\'", column, param), pattern))
+}
+
+// Stream ids are matched literally, wildcards in them are escaped with `\`
+fn like_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
 
 impl EventStoreSQLXPostgres {
-    fn db_events_to_event_reads<Payload, Meta>(
+    pub(crate) fn db_event_to_event_read<Payload, Meta>(
+        &self,
+        d: &DBEventData,
+    ) -> Result<EventRead<Payload, Meta, EventVersion>>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+    {
+        // Rows are decoded with the codec they were written with
+        let codec = Codec::from_name(&d.codec)?;
+        let data = encoded_from_db(d.data.clone(), d.data_bin.clone())
+            .ok_or_else(|| EventStoreError::serialization(format!("Event {} has no data", d.id)))?;
+        let data = self.upcasters().upcast(&d.name, d.schema_version, data)?;
+        let metadata = match encoded_from_db(d.metadata.clone(), d.metadata_bin.clone()) {
+            None => None,
+            Some(v) => Some(codec.decode(v)?),
+        };
+        Ok(EventRead {
+            id: d.id,
+            correlation_id: d.correlation_id,
+            causation_id: d.causation_id,
+            stream_id: d.stream_id.clone(),
+            version: EventVersion::new(d.version),
+            position: d.position,
+            name: d.name.clone(),
+            data: codec.decode(data)?,
+            metadata,
+            created_utc: d.created_utc,
+        })
+    }
+
+    pub(crate) fn db_events_to_event_reads<Payload, Meta>(
+        &self,
         events: &[DBEventData],
     ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>>
     where
         Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
         Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
     {
-        let mut event_reads: Vec<EventRead<Payload, Meta, EventVersion>> = Vec::new();
-        for d in events {
-            let metadata = match d.metadata.clone() {
-                None => None,
-                Some(v) => {
-                    let r = serde_json::from_value(v)?;
-                    Some(r)
+        events
+            .iter()
+            .map(|d| self.db_event_to_event_read(d))
+            .collect()
+    }
+
+    // Events of e still visible under deletion and retention settings of their stream s
+    fn visible_condition() -> &'static str {
+        "e.version >= s.deleted_before \
+                and e.version >= s.truncate_before \
+                and (s.max_count is null or e.version > s.last_version - s.max_count) \
+                and (s.max_age is null or e.created_utc >= current_timestamp - s.max_age * interval '1 second')"
+    }
+
+    // Events aliased as e, leaving out the ones hidden by deletion or retention of their stream
+    pub(crate) fn visible_events(&self) -> String {
+        format!(
+            "{0} e join {1} s on s.id = e.stream_id and {2}",
+            self.events_table_name(),
+            self.streams_table_name(),
+            Self::visible_condition()
+        )
+    }
+
+    fn order_by(order: EventOrder) -> &'static str {
+        match order {
+            EventOrder::Position => "order by e.position",
+            EventOrder::CreatedUtc => "order by e.created_utc, e.position",
+        }
+    }
+
+    // Version bounds and page size of the range are bound after the stream id
+    fn events_range_query(&self, range: &EventsReadRange<EventVersion>) -> (String, Vec<i64>) {
+        let ascending = "order by version";
+        let (filter, bounds, order) = match range {
+            EventsReadRange::AllEvents => (String::new(), vec![], ascending.to_string()),
+            EventsReadRange::FromVersion(f) => (
+                " and version >= $2".to_string(),
+                vec![f.0],
+                ascending.to_string(),
+            ),
+            EventsReadRange::ToVersion(t) => (
+                " and version <= $2 and version > 0".to_string(),
+                vec![t.0],
+                ascending.to_string(),
+            ),
+            EventsReadRange::VersionRange {
+                from_version,
+                to_version,
+            } => (
+                " and version >= $2 and version <= $3".to_string(),
+                vec![from_version.0, to_version.0],
+                ascending.to_string(),
+            ),
+            EventsReadRange::Page {
+                from_version,
+                direction,
+                max_count,
+            } => {
+                let (compare, order) = match direction {
+                    ReadDirection::Forward => (">=", ascending),
+                    ReadDirection::Backward => ("<=", "order by version desc"),
+                };
+                match from_version {
+                    Some(v) => (
+                        format!(" and version {} $2", compare),
+                        vec![v.0, *max_count as i64],
+                        format!("{} limit $3", order),
+                    ),
+                    None => (
+                        String::new(),
+                        vec![*max_count as i64],
+                        format!("{} limit $2", order),
+                    ),
                 }
-            };
-            let event_read = EventRead {
-                id: d.id,
-                correlation_id: d.correlation_id,
-                causation_id: d.causation_id,
-                stream_id: d.stream_id.clone(),
-                version: EventVersion::new(d.version),
-                name: d.name.clone(),
-                data: serde_json::from_value(d.data.clone())?,
-                metadata,
-                created_utc: d.created_utc,
-            };
-            event_reads.push(event_read)
+            }
+        };
+        let query = format!(
+            "select e.* from {0} where e.stream_id=$1{1} {2}",
+            self.visible_events(),
+            filter,
+            order
+        );
+        (query, bounds)
+    }
+
+    // Pattern of the filter is bound as the only parameter
+    fn streams_filter_query(
+        &self,
+        filter: &StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> (String, Option<String>) {
+        let mut conditions = Vec::new();
+        let pattern = stream_id_condition(filter, "id", "$1").map(|(condition, pattern)| {
+            conditions.push(condition);
+            pattern
+        });
+        if deleted == DeletedStreams::Exclude {
+            conditions.push(format!(
+                "state = '{}'",
+                stream_state_to_db(StreamState::Active)
+            ));
         }
+        let query = match conditions.is_empty() {
+            true => format!("select * from {0}", self.streams_table_name()),
+            false => format!(
+                "select * from {0} where {1}",
+                self.streams_table_name(),
+                conditions.join(" and ")
+            ),
+        };
+        (query, pattern)
+    }
 
-        Ok(event_reads)
+    // Events already stored under any of the ids of `payload`
+    async fn stored_events<Payload, Meta>(
+        &self,
+        tr: &mut Transaction<'_, Postgres>,
+        payload: &[EventWrite<Payload, Meta>],
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+    {
+        let ids: Vec<Uuid> = payload.iter().map(|e| e.id).collect();
+        let stored_query = format!(
+            "select * from {0} where id = any($1)",
+            self.events_table_name()
+        );
+        let db_event_data = sqlx::query_as::<_, DBEventData>(&stored_query)
+            .bind(ids)
+            .fetch_all(&mut **tr)
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
     }
 
-    async fn process_events<Payload: Clone + Serialize, Meta: Clone + Serialize>(
+    // Last version of the stream as stored, 0 for a stream without row
+    async fn stored_last_version<'e, E>(&self, executor: E, stream_id: &str) -> Result<i64>
+    where
+        E: Executor<'e, Database = Postgres>,
+    {
+        let last_query = format!(
+            "select last_version from {0} where id = $1",
+            self.streams_table_name()
+        );
+        let last: Option<(i64,)> = sqlx::query_as(&last_query)
+            .bind(stream_id)
+            .fetch_optional(executor)
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(last.map_or(0, |(v,)| v))
+    }
+
+    // Unique indexes are the last line of defence against duplicate versions and event ids.
+    // The failed insert aborted the transaction, so the stream is read again outside of it
+    async fn insert_conflict(
         &self,
         stream_id: &str,
-        version: &ExpectedVersion<EventVersion>,
-        payload: Vec<EventWrite<Payload, Meta>>,
-    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        id: Uuid,
+        expected: i64,
+        e: sqlx::Error,
+    ) -> EventStoreError {
+        let constraint = match &e {
+            sqlx::Error::Database(d) if d.is_unique_violation() => d.constraint().map(String::from),
+            _ => None,
+        };
+        let events = self.events_table_name();
+        match constraint {
+            Some(c) if c == format!("ux_{}_stream_version", events) => {
+                match self.stored_last_version(&self.pool(), stream_id).await {
+                    Ok(last_version) => EventStoreError::WrongExpectedVersion {
+                        stream_id: stream_id.to_string(),
+                        expected,
+                        actual: last_version + 1,
+                    },
+                    Err(e) => e,
+                }
+            }
+            Some(c) if c == format!("{}_pkey", events) => EventStoreError::DuplicateEvents {
+                stream_id: stream_id.to_string(),
+                ids: vec![id],
+            },
+            _ => EventStoreError::backend(e),
+        }
+    }
+
+    async fn process_appends<Payload, Meta>(
+        &self,
+        appends: Vec<StreamAppend<Payload, Meta, EventVersion>>,
+    ) -> Result<Vec<Vec<EventRead<Payload, Meta, EventVersion>>>>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+    {
+        if appends.iter().all(|a| a.events.is_empty()) {
+            return Ok(appends.iter().map(|_| Vec::new()).collect());
+        }
+        ensure_unique_event_ids(&appends)?;
         let pool = self.pool();
+
+        // Version checks and writes of all streams happen in single transaction
+        let mut tr = pool.begin().await.map_err(EventStoreError::backend)?;
+
+        // Creating the stream rows up front and locking them makes concurrent writers
+        // to the same streams wait for each other inside the transaction. Rows are locked
+        // in id order, so writers to overlapping streams cannot deadlock
+        let mut stream_ids: Vec<&str> = appends
+            .iter()
+            .filter(|a| !a.events.is_empty())
+            .map(|a| a.stream_id.as_str())
+            .collect();
+        stream_ids.sort();
+        stream_ids.dedup();
+        let ensure_stream = format!(
+            "insert into {0} (id, last_version) values ($1, 0) on conflict (id) do nothing",
+            self.streams_table_name()
+        );
         let exist_query = format!(
-            "select * from {0} where id = $1 limit 1",
+            "select * from {0} where id = $1 limit 1 for update",
             self.streams_table_name()
         );
-        let exist = sqlx::query_as::<_, DBEventStream>(&exist_query)
-            .bind(stream_id)
-            .fetch_one(&pool)
-            .await;
-        let last: (EventVersion, Option<EventStream<EventVersion>>) = match &exist {
-            Ok(r) => (
-                EventVersion::new(r.last_version),
-                Some(EventStream::from(r.clone())),
-            ),
-            Err(_) => (EventVersion::new(0), None),
-        };
+        let mut locked: HashMap<String, DBEventStream> = HashMap::new();
+        for stream_id in stream_ids {
+            let _ = sqlx::query(&ensure_stream)
+                .bind(stream_id)
+                .execute(&mut *tr)
+                .await
+                .map_err(EventStoreError::backend)?;
+            let exist = sqlx::query_as::<_, DBEventStream>(&exist_query)
+                .bind(stream_id)
+                .fetch_one(&mut *tr)
+                .await
+                .map_err(EventStoreError::backend)?;
+            locked.insert(stream_id.to_string(), exist);
+        }
+
+        // Each append is checked against the streams as left by the appends before it
+        let mut updated: HashMap<String, EventStream<EventVersion>> = HashMap::new();
+        let mut first_versions: HashMap<String, i64> = HashMap::new();
+        let mut checked = Vec::new();
+        let mut retried = HashMap::new();
+        for (i, append) in appends.iter().enumerate() {
+            if append.events.is_empty() {
+                checked.push(None);
+                continue;
+            }
+            let stream_id = append.stream_id.as_str();
+            let stored = self.stored_events(&mut tr, &append.events).await?;
+            if let Some(original) =
+                already_appended(stream_id, &append.version, &append.events, stored)?
+            {
+                retried.insert(i, original);
+                checked.push(None);
+                continue;
+            }
+            let current = match updated.get(stream_id) {
+                Some(s) => s.clone(),
+                None => EventStream::from(locked[stream_id].clone()),
+            };
+            if current.state == StreamState::Tombstoned {
+                return Err(EventStoreError::StreamTombstoned(stream_id.to_string()));
+            }
+            let last: (EventVersion, Option<EventStream<EventVersion>>) =
+                match current.last_version.0 {
+                    0 => (EventVersion::new(0), None),
+                    _ => (current.last_version.clone(), Some(current.clone())),
+                };
+            // A soft deleted stream does not exist anymore, but keeps counting its versions
+            let version = match (current.state, &append.version) {
+                (StreamState::Deleted, ExpectedVersion::NoStream) => &ExpectedVersion::Any,
+                (_, version) => version,
+            };
 
-        let next = last.0.next_version(version)?;
+            let next = last.0.next_version(stream_id, version)?;
+            let stream = updated_stream(stream_id, append.events.len(), last)?;
+            updated.insert(stream_id.to_string(), stream);
+            first_versions
+                .entry(stream_id.to_string())
+                .or_insert(next.0);
+            checked.push(Some((next, append.events.len())));
+        }
 
-        let ops: Vec<EventRead<Payload, Meta, EventVersion>> =
-            event_writes_to_reads(stream_id, &next, &payload.clone());
+        let count: usize = checked.iter().flatten().map(|(_, n)| n).sum();
+        if count == 0 {
+            return Ok(appends
+                .iter()
+                .enumerate()
+                .map(|(i, _)| retried.remove(&i).unwrap_or_default())
+                .collect());
+        }
 
-        let updated_stream = updated_stream(stream_id, payload.len() as i64, last);
+        // The position row stays locked until commit, so positions increase in commit order
+        let reserve_positions = format!(
+            "update {0} set last_position = last_position + $1 returning last_position",
+            self.positions_table_name()
+        );
+        let (last_position,): (i64,) = sqlx::query_as(&reserve_positions)
+            .bind(count as i64)
+            .fetch_one(&mut *tr)
+            .await
+            .map_err(EventStoreError::backend)?;
+        let mut position = last_position - count as i64 + 1;
 
-        // Updating all in single transection
-        let mut tr = pool.begin().await?;
+        let mut results: Vec<Vec<EventRead<Payload, Meta, EventVersion>>> = appends
+            .iter()
+            .zip(checked)
+            .map(|(append, next)| match next {
+                Some((next, _)) => {
+                    let ops =
+                        event_writes_to_reads(&append.stream_id, &next, position, &append.events)?;
+                    position += ops.len() as i64;
+                    Ok(ops)
+                }
+                None => Ok(Vec::new()),
+            })
+            .collect::<Result<_>>()?;
 
-        let insert_or_update_stream = format!("insert into {0} (id, last_version) values ($1, $2) on conflict (id) do update set last_version = $2", self.streams_table_name());
-        let _ = sqlx::query(&insert_or_update_stream)
-            .bind(updated_stream.id)
-            .bind(updated_stream.last_version.0)
-            .execute(&mut *tr)
-            .await?;
+        let update_stream = format!(
+            "update {0} set last_version = $2, state = $4 where id = $1 and last_version = $3",
+            self.streams_table_name()
+        );
+        for (stream_id, stream) in &updated {
+            let exist = &locked[stream_id];
+            let res = sqlx::query(&update_stream)
+                .bind(stream_id)
+                .bind(stream.last_version.0)
+                .bind(exist.last_version)
+                .bind(stream_state_to_db(stream.state))
+                .execute(&mut *tr)
+                .await
+                .map_err(EventStoreError::backend)?;
+            if res.rows_affected() != 1 {
+                let last_version = self.stored_last_version(&mut *tr, stream_id).await?;
+                return Err(EventStoreError::WrongExpectedVersion {
+                    stream_id: stream_id.to_string(),
+                    expected: first_versions[stream_id],
+                    actual: last_version + 1,
+                });
+            }
+        }
 
-        let insert_event = format!("insert into {0} (id, correlation_id, causation_id, stream_id, version, name, data, metadata) values ($1, $2, $3, $4, $5, $6, $7, $8)", self.events_table_name());
+        let insert_event = format!("insert into {0} (id, correlation_id, causation_id, stream_id, version, position, name, data, metadata, data_bin, metadata_bin, codec, schema_version) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)", self.events_table_name());
 
-        for op in &ops {
-            let data = serde_json::to_value(op.data.clone())?;
-            let metadata: Option<Value> = match op.metadata.clone() {
-                None => None,
-                Some(v) => {
-                    let r = serde_json::to_value(v)?;
-                    Some(r)
-                }
+        let codec = self.codec();
+        for op in results.iter().flatten() {
+            let (data, data_bin) = encoded_to_db(codec.encode(&op.data)?);
+            let (metadata, metadata_bin) = match &op.metadata {
+                None => (None, None),
+                Some(v) => encoded_to_db(codec.encode(v)?),
             };
-            let _ = sqlx::query(&insert_event)
+            let inserted = sqlx::query(&insert_event)
                 .bind(op.id)
                 .bind(op.correlation_id)
                 .bind(op.causation_id)
                 .bind(op.stream_id.clone())
                 .bind(op.version.0)
+                .bind(op.position)
                 .bind(op.name.clone())
                 .bind(data)
                 .bind(metadata)
+                .bind(data_bin)
+                .bind(metadata_bin)
+                .bind(codec.name())
+                .bind(self.upcasters().current_version(&op.name))
                 .execute(&mut *tr)
-                .await?;
+                .await;
+            if let Err(e) = inserted {
+                tr.rollback().await.map_err(EventStoreError::backend)?;
+                let expected = first_versions[&op.stream_id];
+                return Err(self
+                    .insert_conflict(&op.stream_id, op.id, expected, e)
+                    .await);
+            }
+        }
+
+        if self.outbox() {
+            let insert_outbox = format!(
+                "insert into {0} (position, event_id, stream_id) values ($1, $2, $3)",
+                self.outbox_table_name()
+            );
+            for op in results.iter().flatten() {
+                let _ = sqlx::query(&insert_outbox)
+                    .bind(op.position)
+                    .bind(op.id)
+                    .bind(op.stream_id.clone())
+                    .execute(&mut *tr)
+                    .await
+                    .map_err(EventStoreError::backend)?;
+            }
+        }
+
+        tr.commit().await.map_err(EventStoreError::backend)?;
+
+        for (i, original) in retried {
+            results[i] = original;
+        }
+        Ok(results)
+    }
+
+    async fn update_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<EventVersion>,
+    ) -> Result<EventStream<EventVersion>> {
+        let pool = self.pool();
+        let mut tr = pool.begin().await.map_err(EventStoreError::backend)?;
+
+        let exist_query = format!(
+            "select * from {0} where id = $1 limit 1 for update",
+            self.streams_table_name()
+        );
+        let exist = sqlx::query_as::<_, DBEventStream>(&exist_query)
+            .bind(stream_id)
+            .fetch_optional(&mut *tr)
+            .await
+            .map_err(EventStoreError::backend)?
+            .map(EventStream::from);
+        match exist.map(|s| s.state) {
+            Some(StreamState::Active) => (),
+            Some(StreamState::Tombstoned) => {
+                return Err(EventStoreError::StreamTombstoned(stream_id.to_string()))
+            }
+            _ => return Err(EventStoreError::StreamNotFound(stream_id.to_string())),
         }
 
-        tr.commit().await?;
+        let update_metadata = format!(
+            "update {0} set max_count = $2, max_age = $3, truncate_before = $4 \
+                    where id = $1 returning *",
+            self.streams_table_name()
+        );
+        let updated = sqlx::query_as::<_, DBEventStream>(&update_metadata)
+            .bind(stream_id)
+            .bind(metadata.max_count.map(|c| c as i64))
+            .bind(metadata.max_age.map(|a| a.as_secs() as i64))
+            .bind(metadata.truncate_before.as_ref().map_or(0, |v| v.0))
+            .fetch_one(&mut *tr)
+            .await
+            .map_err(EventStoreError::backend)?;
 
-        Ok(ops)
+        tr.commit().await.map_err(EventStoreError::backend)?;
+
+        Ok(EventStream::from(updated))
+    }
+
+    async fn remove_hidden_events(&self) -> Result<u64> {
+        let scavenge = format!(
+            "delete from {0} e using {1} s where s.id = e.stream_id and not ({2})",
+            self.events_table_name(),
+            self.streams_table_name(),
+            Self::visible_condition()
+        );
+        let removed = sqlx::query(&scavenge)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(removed.rows_affected())
+    }
+
+    async fn remove_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()> {
+        let pool = self.pool();
+        let mut tr = pool.begin().await.map_err(EventStoreError::backend)?;
+
+        let exist_query = format!(
+            "select * from {0} where id = $1 limit 1 for update",
+            self.streams_table_name()
+        );
+        let exist = sqlx::query_as::<_, DBEventStream>(&exist_query)
+            .bind(stream_id)
+            .fetch_optional(&mut *tr)
+            .await
+            .map_err(EventStoreError::backend)?
+            .map(EventStream::from)
+            .ok_or_else(|| EventStoreError::StreamNotFound(stream_id.to_string()))?;
+        if exist.state == StreamState::Tombstoned {
+            return Err(EventStoreError::StreamTombstoned(stream_id.to_string()));
+        }
+
+        let state = match mode {
+            DeleteMode::Soft => StreamState::Deleted,
+            DeleteMode::Tombstone => StreamState::Tombstoned,
+            DeleteMode::Hard => {
+                let delete_events = format!(
+                    "delete from {0} where stream_id = $1",
+                    self.events_table_name()
+                );
+                let delete_stream =
+                    format!("delete from {0} where id = $1", self.streams_table_name());
+                for query in [delete_events, delete_stream] {
+                    let _ = sqlx::query(&query)
+                        .bind(stream_id)
+                        .execute(&mut *tr)
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                }
+                return tr.commit().await.map_err(EventStoreError::backend);
+            }
+        };
+        // Events stay in place, reads skip everything written before the deletion
+        let hide_stream = format!(
+            "update {0} set state = $2, deleted_before = last_version + 1 where id = $1",
+            self.streams_table_name()
+        );
+        let _ = sqlx::query(&hide_stream)
+            .bind(stream_id)
+            .bind(stream_state_to_db(state))
+            .execute(&mut *tr)
+            .await
+            .map_err(EventStoreError::backend)?;
+
+        tr.commit().await.map_err(EventStoreError::backend)
     }
 }
 
@@ -147,7 +635,20 @@ where
             return Ok(Vec::new());
         }
 
-        self.process_events(stream_id, version, payload).await
+        let append = StreamAppend {
+            stream_id: stream_id.to_string(),
+            version: version.clone(),
+            events: payload,
+        };
+        let mut res = self.process_appends(vec![append]).await?;
+        Ok(res.remove(0))
+    }
+
+    async fn append_to_streams(
+        &self,
+        appends: Vec<StreamAppend<Payload, Meta, EventVersion>>,
+    ) -> Result<Vec<Vec<EventRead<Payload, Meta, EventVersion>>>> {
+        self.process_appends(appends).await
     }
 
     async fn get_event(
@@ -157,177 +658,224 @@ where
     ) -> Result<EventRead<Payload, Meta, EventVersion>> {
         let filter = EventsReadRange::VersionRange {
             from_version: version.clone(),
-            to_version: EventVersion(version.0 + 1),
+            to_version: version.add(1)?,
         };
         let events = self.get_events(stream_id, &filter).await?;
-        Ok(events[0].clone())
+        events
+            .into_iter()
+            .find(|e| e.version == *version)
+            .ok_or_else(|| EventStoreError::EventNotFound {
+                stream_id: stream_id.to_string(),
+                version: version.0,
+            })
     }
 
     async fn get_events(
         &self,
         stream_id: &str,
-        version: &EventsReadRange<EventVersion>,
+        range: &EventsReadRange<EventVersion>,
     ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        match version {
-            EventsReadRange::AllEvents => {
-                let all_event = format!(
-                    "select * from {0} where stream_id=$1",
-                    self.events_table_name()
-                );
-                let db_event_data = sqlx::query_as::<_, DBEventData>(&all_event)
-                    .bind(stream_id)
-                    .fetch_all(&self.pool())
-                    .await?;
-                EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
-            }
-            EventsReadRange::FromVersion(f) => {
-                let from_version = format!(
-                    "select * from {0} where stream_id=$1 and version >= $2",
-                    self.events_table_name()
-                );
-                let db_event_data = sqlx::query_as::<_, DBEventData>(&from_version)
-                    .bind(stream_id)
-                    .bind(f.0)
-                    .fetch_all(&self.pool())
-                    .await?;
-                EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
-            }
-            EventsReadRange::ToVersion(t) => {
-                let to_version = format!(
-                    "select * from {0} where stream_id=$1 and version <= $2 and version > 0",
-                    self.events_table_name()
-                );
-                let db_event_data = sqlx::query_as::<_, DBEventData>(&to_version)
-                    .bind(stream_id)
-                    .bind(t.0)
-                    .fetch_all(&self.pool())
-                    .await?;
-                EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
+        self.get_events_stream(stream_id, range).try_collect().await
+    }
+
+    fn get_events_stream<'a>(
+        &'a self,
+        stream_id: &'a str,
+        range: &'a EventsReadRange<EventVersion>,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, EventVersion>>> {
+        let (query, bounds) = self.events_range_query(range);
+        Box::pin(try_stream! {
+            let mut events = sqlx::query_as::<_, DBEventData>(&query).bind(stream_id);
+            for bound in bounds {
+                events = events.bind(bound);
             }
-            EventsReadRange::VersionRange {
-                from_version,
-                to_version,
-            } => {
-                let version_range = format!(
-                    "select * from {0} where stream_id=$1 and version >= $2 and version <= $3",
-                    self.events_table_name()
-                );
-                let db_event_data = sqlx::query_as::<_, DBEventData>(&version_range)
-                    .bind(stream_id)
-                    .bind(from_version.0)
-                    .bind(to_version.0)
-                    .fetch_all(&self.pool())
-                    .await?;
-                EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
+            let pool = self.pool();
+            let mut rows = events.fetch(&pool);
+            while let Some(row) = rows.try_next().await.map_err(EventStoreError::backend)? {
+                yield self.db_event_to_event_read(&row)?;
             }
-        }
+        })
+    }
+
+    async fn get_all_events(
+        &self,
+        from_position: i64,
+        page_size: usize,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        let all_events = format!(
+            "select e.* from {0} where e.position >= $1 order by e.position limit $2",
+            self.visible_events()
+        );
+        let db_event_data = sqlx::query_as::<_, DBEventData>(&all_events)
+            .bind(from_position)
+            .bind(page_size as i64)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
     }
 
     async fn get_events_by_correlation_id(
         &self,
         correlation_id: &Uuid,
     ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        self.get_events_by_correlation_id_stream(correlation_id)
+            .try_collect()
+            .await
+    }
+
+    fn get_events_by_correlation_id_stream<'a>(
+        &'a self,
+        correlation_id: &'a Uuid,
+    ) -> BoxStream<'a, Result<EventRead<Payload, Meta, EventVersion>>> {
         let correlation_query = format!(
-            "select * from {0} where correlation_id=$1",
-            self.events_table_name()
+            "select e.* from {0} where e.correlation_id=$1 order by e.position",
+            self.visible_events()
         );
-        let db_event_data = sqlx::query_as::<_, DBEventData>(&correlation_query)
-            .bind(correlation_id)
-            .fetch_all(&self.pool())
-            .await?;
-        EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
+        Box::pin(try_stream! {
+            let pool = self.pool();
+            let mut rows = sqlx::query_as::<_, DBEventData>(&correlation_query)
+                .bind(correlation_id)
+                .fetch(&pool);
+            while let Some(row) = rows.try_next().await.map_err(EventStoreError::backend)? {
+                yield self.db_event_to_event_read(&row)?;
+            }
+        })
     }
 
     async fn get_events_by_causation_id(
         &self,
         causation_id: &Uuid,
     ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
-        let correlation_query = format!(
-            "select * from {0} where causation_id=$1",
-            self.events_table_name()
+        let causation_query = format!(
+            "select e.* from {0} where e.causation_id=$1 order by e.position",
+            self.visible_events()
         );
-        let db_event_data = sqlx::query_as::<_, DBEventData>(&correlation_query)
+        let db_event_data = sqlx::query_as::<_, DBEventData>(&causation_query)
             .bind(causation_id)
             .fetch_all(&self.pool())
-            .await?;
-        EventStoreSQLXPostgres::db_events_to_event_reads(&db_event_data)
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
+    }
+
+    async fn get_events_by_name(
+        &self,
+        name: &str,
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        self.get_events_by_names(&[name], order).await
+    }
+
+    async fn get_events_by_names(
+        &self,
+        names: &[&str],
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        let name_query = format!(
+            "select e.* from {0} where e.name = any($1) {1}",
+            self.visible_events(),
+            EventStoreSQLXPostgres::order_by(order)
+        );
+        let db_event_data = sqlx::query_as::<_, DBEventData>(&name_query)
+            .bind(names)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
+    }
+
+    async fn get_events_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        filter: &StreamsReadFilter,
+    ) -> Result<Vec<EventRead<Payload, Meta, EventVersion>>> {
+        let mut conditions = vec![
+            "e.created_utc >= $1".to_string(),
+            "e.created_utc < $2".to_string(),
+        ];
+        let stream = stream_id_condition(filter, "e.stream_id", "$3").map(|(condition, stream)| {
+            conditions.push(condition);
+            stream
+        });
+        let time_query = format!(
+            "select e.* from {0} where {1} {2}",
+            self.visible_events(),
+            conditions.join(" and "),
+            EventStoreSQLXPostgres::order_by(EventOrder::CreatedUtc)
+        );
+        let mut events = sqlx::query_as::<_, DBEventData>(&time_query)
+            .bind(from)
+            .bind(to);
+        if let Some(stream) = stream {
+            events = events.bind(stream);
+        }
+        let db_event_data = events
+            .fetch_all(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.db_events_to_event_reads(&db_event_data)
     }
 
     async fn get_streams(
         &self,
         filter: &StreamsReadFilter,
+        deleted: DeletedStreams,
     ) -> Result<Vec<EventStream<EventVersion>>> {
-        match filter {
-            StreamsReadFilter::AllStreams => {
-                let all_stream = format!("select * from {0}", self.streams_table_name());
-                let stream_data = sqlx::query_as::<_, DBEventStream>(&all_stream)
-                    .fetch_all(&self.pool())
-                    .await?;
-                let res: Vec<EventStream<EventVersion>> = stream_data
-                    .iter()
-                    .map(|x| EventStream::from(x.clone()))
-                    .collect();
-                Ok(res)
-            }
-            StreamsReadFilter::StartsWith(s) => {
-                let starts_with_stream = format!(
-                    "select * from {0} where id like $1%",
-                    self.streams_table_name()
-                );
-                let stream_data = sqlx::query_as::<_, DBEventStream>(&starts_with_stream)
-                    .bind(s)
-                    .fetch_all(&self.pool())
-                    .await?;
-                let res: Vec<EventStream<EventVersion>> = stream_data
-                    .iter()
-                    .map(|x| EventStream::from(x.clone()))
-                    .collect();
-                Ok(res)
-            }
-            StreamsReadFilter::EndsWith(s) => {
-                let ends_with_stream = format!(
-                    "select * from {0} where id like %$1",
-                    self.streams_table_name()
-                );
-                let stream_data = sqlx::query_as::<_, DBEventStream>(&ends_with_stream)
-                    .bind(s)
-                    .fetch_all(&self.pool())
-                    .await?;
-                let res: Vec<EventStream<EventVersion>> = stream_data
-                    .iter()
-                    .map(|x| EventStream::from(x.clone()))
-                    .collect();
-                Ok(res)
+        EventStore::<Payload, Meta, EventVersion>::get_streams_stream(self, filter, deleted)
+            .try_collect()
+            .await
+    }
+
+    fn get_streams_stream<'a>(
+        &'a self,
+        filter: &'a StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> BoxStream<'a, Result<EventStream<EventVersion>>> {
+        let (query, pattern) = self.streams_filter_query(filter, deleted);
+        Box::pin(try_stream! {
+            let mut streams = sqlx::query_as::<_, DBEventStream>(&query);
+            if let Some(pattern) = pattern {
+                streams = streams.bind(pattern);
             }
-            StreamsReadFilter::Contains(s) => {
-                let contains_stream = format!(
-                    "select * from {0} where id like %$1%",
-                    self.streams_table_name()
-                );
-                let stream_data = sqlx::query_as::<_, DBEventStream>(&contains_stream)
-                    .bind(s)
-                    .fetch_all(&self.pool())
-                    .await?;
-                let res: Vec<EventStream<EventVersion>> = stream_data
-                    .iter()
-                    .map(|x| EventStream::from(x.clone()))
-                    .collect();
-                Ok(res)
+            let pool = self.pool();
+            let mut rows = streams.fetch(&pool);
+            while let Some(row) = rows.try_next().await.map_err(EventStoreError::backend)? {
+                yield EventStream::from(row);
             }
-        }
+        })
     }
 
     async fn get_stream(&self, stream_id: &str) -> Result<EventStream<EventVersion>> {
-        let stream_by_id = format!("select * from {0} where id=$1", self.streams_table_name());
+        let stream_by_id = format!(
+            "select * from {0} where id=$1 and state=$2",
+            self.streams_table_name()
+        );
         let stream_data = sqlx::query_as::<_, DBEventStream>(&stream_by_id)
             .bind(stream_id)
-            .fetch_one(&self.pool())
-            .await;
-        stream_data.map(EventStream::from).map_err(|_| {
-            anyhow::Error::msg(format!(
-                "StreamID: {} not present in store",
-                stream_id.to_string()
-            ))
-        })
+            .bind(stream_state_to_db(StreamState::Active))
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        stream_data
+            .map(EventStream::from)
+            .ok_or_else(|| EventStoreError::StreamNotFound(stream_id.to_string()))
+    }
+
+    async fn delete_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()> {
+        self.remove_stream(stream_id, mode).await
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<EventVersion>,
+    ) -> Result<EventStream<EventVersion>> {
+        self.update_metadata(stream_id, metadata).await
+    }
+
+    async fn scavenge(&self) -> Result<u64> {
+        self.remove_hidden_events().await
     }
 }
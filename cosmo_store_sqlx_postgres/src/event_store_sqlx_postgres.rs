@@ -1,4 +1,6 @@
 use anyhow::Result;
+use cosmo_store::common::codec::Codec;
+use cosmo_store::common::upcast::Upcasters;
 use sqlx::postgres::PgQueryResult;
 use sqlx::PgPool;
 
@@ -7,6 +9,11 @@ pub struct EventStoreSQLXPostgres {
     pool: PgPool,
     streams_table_name: String,
     events_table_name: String,
+    positions_table_name: String,
+    outbox_table_name: String,
+    codec: Codec,
+    upcasters: Upcasters,
+    outbox: bool,
 }
 
 impl EventStoreSQLXPostgres {
@@ -22,17 +29,66 @@ impl EventStoreSQLXPostgres {
         self.events_table_name.to_string()
     }
 
+    pub fn positions_table_name(&self) -> String {
+        self.positions_table_name.to_string()
+    }
+
+    pub fn outbox_table_name(&self) -> String {
+        self.outbox_table_name.to_string()
+    }
+
+    /// Codec new events are written with
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn upcasters(&self) -> &Upcasters {
+        &self.upcasters
+    }
+
+    /// Upcasts payloads of events stored at older schema versions while reading them
+    pub fn with_upcasters(self, upcasters: Upcasters) -> EventStoreSQLXPostgres {
+        EventStoreSQLXPostgres { upcasters, ..self }
+    }
+
+    pub fn outbox(&self) -> bool {
+        self.outbox
+    }
+
+    /// Writes an outbox row for every appended event, in the same transaction as the event
+    pub fn with_outbox(self) -> EventStoreSQLXPostgres {
+        EventStoreSQLXPostgres {
+            outbox: true,
+            ..self
+        }
+    }
+
+    /// Channel appended events are announced on, one per store name
+    pub fn notification_channel(&self) -> String {
+        self.events_table_name.to_string()
+    }
+
     async fn create_stream_table(pool: &PgPool, streams_name: &str) -> Result<PgQueryResult> {
         // create table if not exists cs_stream_person (
         //     id text primary key,
         // last_version bigint not null ,
-        // last_updated_utc timestamptz default current_timestamp
+        // last_updated_utc timestamptz default current_timestamp,
+        // state varchar(16) not null default 'active',
+        // truncate_before bigint not null default 0,
+        // max_count bigint default null,
+        // max_age bigint default null,
+        // deleted_before bigint not null default 0
         // );
         let streams_create_table = format!(
             "create table if not exists \
                     {} (id text primary key, \
                     last_version bigint not null, \
-                    last_updated_utc timestamptz default current_timestamp )",
+                    last_updated_utc timestamptz default current_timestamp, \
+                    state varchar(16) not null default 'active', \
+                    truncate_before bigint not null default 0, \
+                    max_count bigint default null, \
+                    max_age bigint default null, \
+                    deleted_before bigint not null default 0)",
             streams_name
         );
 
@@ -52,9 +108,14 @@ impl EventStoreSQLXPostgres {
         // stream_id text not null,
         // constraint fk_stream foreign key (stream_id) references cs_stream_person(id) on delete cascade,
         // version bigint not null,
+        // position bigint not null,
         // name varchar(255) not null ,
-        // data jsonb not null ,
-        // metadata jsonb,
+        // data jsonb default null,
+        // metadata jsonb default null,
+        // data_bin bytea default null,
+        // metadata_bin bytea default null,
+        // codec varchar(16) not null default 'json',
+        // schema_version integer not null default 1,
         // created_utc timestamptz default current_timestamp
         // );
         let events_create_table = format!(
@@ -66,9 +127,14 @@ impl EventStoreSQLXPostgres {
                     constraint fk_stream foreign key (stream_id) references {1}(id) \
                     on delete cascade,\
                     version bigint not null,\
+                    position bigint not null,\
                     name varchar(255) not null ,\
-                    data jsonb not null ,\
+                    data jsonb default null,\
                     metadata jsonb default null,\
+                    data_bin bytea default null,\
+                    metadata_bin bytea default null,\
+                    codec varchar(16) not null default 'json',\
+                    schema_version integer not null default 1,\
                     created_utc timestamptz default current_timestamp)",
             events_name, streams_name
         );
@@ -77,6 +143,155 @@ impl EventStoreSQLXPostgres {
         Ok(res)
     }
 
+    async fn upgrade_stream_table(pool: &PgPool, streams_name: &str) -> Result<PgQueryResult> {
+        // Tables created by earlier versions get the columns added since
+        let upgrade_table = format!(
+            "alter table {0} \
+                    add column if not exists state varchar(16) not null default 'active', \
+                    add column if not exists truncate_before bigint not null default 0, \
+                    add column if not exists max_count bigint default null, \
+                    add column if not exists max_age bigint default null, \
+                    add column if not exists deleted_before bigint not null default 0",
+            streams_name
+        );
+
+        let res = sqlx::query(&upgrade_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn upgrade_event_table(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // Tables created by earlier versions get the columns added since, `data` is null
+        // for events written with a binary codec
+        let upgrade_table = format!(
+            "alter table {0} \
+                    add column if not exists position bigint, \
+                    add column if not exists data_bin bytea default null, \
+                    add column if not exists metadata_bin bytea default null, \
+                    add column if not exists codec varchar(16) not null default 'json', \
+                    add column if not exists schema_version integer not null default 1, \
+                    alter column data drop not null",
+            events_name
+        );
+        // Events stored before positions existed get them in the order they were written
+        let backfill_position = format!(
+            "update {0} e set position = p.position from (\
+                    select id, (select coalesce(max(position), 0) from {0}) \
+                    + row_number() over (order by created_utc, stream_id, version) as position \
+                    from {0} where position is null) p \
+                    where e.id = p.id",
+            events_name
+        );
+        let require_position = format!(
+            "alter table {0} alter column position set not null",
+            events_name
+        );
+
+        let _ = sqlx::query(&upgrade_table).execute(pool).await?;
+        let _ = sqlx::query(&backfill_position).execute(pool).await?;
+        let res = sqlx::query(&require_position).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_position_table(
+        pool: &PgPool,
+        positions_name: &str,
+        events_name: &str,
+    ) -> Result<PgQueryResult> {
+        // create table if not exists cs_positions_person (
+        //     id smallint primary key default 1 check (id = 1),
+        // last_position bigint not null
+        // );
+        // Single row holding the last handed out position, so positions of hard deleted
+        // events are never handed out again
+        let positions_create_table = format!(
+            "create table if not exists {0} (\
+                    id smallint primary key default 1 check (id = 1),\
+                    last_position bigint not null)",
+            positions_name
+        );
+        let seed_position = format!(
+            "insert into {0} (id, last_position) \
+                    select 1, coalesce(max(position), 0) from {1} \
+                    on conflict (id) do nothing",
+            positions_name, events_name
+        );
+
+        let _ = sqlx::query(&positions_create_table).execute(pool).await?;
+        let res = sqlx::query(&seed_position).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_stream_version_index(
+        pool: &PgPool,
+        events_name: &str,
+    ) -> Result<PgQueryResult> {
+        // create unique index if not exists ux_cs_events_person_stream_version
+        // on cs_events_person (stream_id, version);
+        let create_index = format!(
+            "create unique index if not exists ux_{0}_stream_version \
+                    on {0} (stream_id, version)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_position_index(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // create unique index if not exists ux_cs_events_person_position
+        // on cs_events_person (position);
+        let create_index = format!(
+            "create unique index if not exists ux_{0}_position on {0} (position)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_name_index(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // create index if not exists ix_cs_events_person_name
+        // on cs_events_person (name, position);
+        let create_index = format!(
+            "create index if not exists ix_{0}_name on {0} (name, position)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_json_index(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // create index if not exists ix_cs_events_person_data
+        // on cs_events_person using gin (data jsonb_path_ops);
+        // create index if not exists ix_cs_events_person_metadata
+        // on cs_events_person using gin (metadata jsonb_path_ops);
+        let create_data_index = format!(
+            "create index if not exists ix_{0}_data on {0} using gin (data jsonb_path_ops)",
+            events_name
+        );
+        let create_metadata_index = format!(
+            "create index if not exists ix_{0}_metadata on {0} using gin (metadata jsonb_path_ops)",
+            events_name
+        );
+
+        let _ = sqlx::query(&create_data_index).execute(pool).await?;
+        let res = sqlx::query(&create_metadata_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_created_utc_index(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // create index if not exists ix_cs_events_person_created_utc
+        // on cs_events_person (created_utc);
+        let create_index = format!(
+            "create index if not exists ix_{0}_created_utc on {0} (created_utc)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
     async fn create_timestamp_trigger(pool: &PgPool, streams_name: &str) -> Result<PgQueryResult> {
         let trigger_function = r#"create or replace function update_modified_column()
             returns trigger as $$
@@ -86,33 +301,133 @@ impl EventStoreSQLXPostgres {
             end;
             $$ language 'plpgsql';"#;
 
-
         let create_trigger = format!(
-            "create trigger if not exists update_{0} before update on {0} \
+            "create or replace trigger update_{0} before update on {0} \
                     for each row execute procedure update_modified_column()",
             streams_name
         );
 
-        let _ = sqlx::query(&trigger_function).execute(pool).await?;
+        let _ = sqlx::query(trigger_function).execute(pool).await?;
+        let res = sqlx::query(&create_trigger).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_notify_trigger(pool: &PgPool, events_name: &str) -> Result<PgQueryResult> {
+        // Notifies the cs_events_person channel with {"stream_id": ..., "position": ...}
+        // for every appended event
+        let notify_function = format!(
+            r#"create or replace function notify_{0}()
+            returns trigger as $$
+            begin
+                perform pg_notify('{0}', json_build_object(
+                    'stream_id', new.stream_id,
+                    'position', new.position)::text);
+                return new;
+            end;
+            $$ language 'plpgsql';"#,
+            events_name
+        );
+
+        let create_trigger = format!(
+            "create or replace trigger notify_{0} after insert on {0} \
+                    for each row execute procedure notify_{0}()",
+            events_name
+        );
+
+        let _ = sqlx::query(&notify_function).execute(pool).await?;
         let res = sqlx::query(&create_trigger).execute(pool).await?;
         Ok(res)
     }
 
+    async fn create_outbox_table(pool: &PgPool, outbox_name: &str) -> Result<PgQueryResult> {
+        // create table if not exists cs_outbox_person (
+        //     position bigint primary key,
+        // event_id uuid not null,
+        // stream_id text not null,
+        // attempts integer not null default 0,
+        // last_error text default null,
+        // next_attempt_utc timestamptz not null default current_timestamp,
+        // claimed_until timestamptz default null,
+        // delivered_utc timestamptz default null,
+        // failed_utc timestamptz default null,
+        // created_utc timestamptz default current_timestamp
+        // );
+        // Rows still to be delivered are found through a partial index
+        let outbox_create_table = format!(
+            "create table if not exists {0} (\
+                    position bigint primary key,\
+                    event_id uuid not null,\
+                    stream_id text not null,\
+                    attempts integer not null default 0,\
+                    last_error text default null,\
+                    next_attempt_utc timestamptz not null default current_timestamp,\
+                    claimed_until timestamptz default null,\
+                    delivered_utc timestamptz default null,\
+                    failed_utc timestamptz default null,\
+                    created_utc timestamptz default current_timestamp)",
+            outbox_name
+        );
+        let create_index = format!(
+            "create index if not exists ix_{0}_pending on {0} (next_attempt_utc) \
+                    where delivered_utc is null and failed_utc is null",
+            outbox_name
+        );
+
+        let _ = sqlx::query(&outbox_create_table).execute(pool).await?;
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    /// Same as `new`, also indexing `data` and `metadata` for `JsonQuery::Contains`
+    pub async fn new_with_json_index(pool: &PgPool, name: &str) -> Result<EventStoreSQLXPostgres> {
+        let store = EventStoreSQLXPostgres::new(pool, name).await?;
+        let _ = EventStoreSQLXPostgres::create_json_index(pool, &store.events_table_name()).await?;
+        Ok(store)
+    }
+
     pub async fn new(pool: &PgPool, name: &str) -> Result<EventStoreSQLXPostgres> {
+        EventStoreSQLXPostgres::new_with_codec(pool, name, Codec::Json).await
+    }
+
+    /// Same as `new`, writing payloads and metadata of new events with `codec`
+    pub async fn new_with_codec(
+        pool: &PgPool,
+        name: &str,
+        codec: Codec,
+    ) -> Result<EventStoreSQLXPostgres> {
         // Generate stream name
         let streams_name = format!("cs_streams_{}", name);
         // Generate name for event table
         let events_name = format!("cs_events_{}", name);
+        // Generate name for position table
+        let positions_name = format!("cs_positions_{}", name);
+        // Generate name for outbox table
+        let outbox_name = format!("cs_outbox_{}", name);
 
         let _ = EventStoreSQLXPostgres::create_stream_table(pool, &streams_name).await?;
+        let _ = EventStoreSQLXPostgres::upgrade_stream_table(pool, &streams_name).await?;
         let _ =
             EventStoreSQLXPostgres::create_event_table(pool, &events_name, &streams_name).await?;
+        let _ = EventStoreSQLXPostgres::upgrade_event_table(pool, &events_name).await?;
+        let _ = EventStoreSQLXPostgres::create_position_table(pool, &positions_name, &events_name)
+            .await?;
+        let _ = EventStoreSQLXPostgres::create_stream_version_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXPostgres::create_position_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXPostgres::create_name_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXPostgres::create_created_utc_index(pool, &events_name).await?;
         let _ = EventStoreSQLXPostgres::create_timestamp_trigger(pool, &streams_name).await?;
+        let _ = EventStoreSQLXPostgres::create_outbox_table(pool, &outbox_name).await?;
+        let _ = EventStoreSQLXPostgres::create_notify_trigger(pool, &events_name).await?;
 
         Ok(EventStoreSQLXPostgres {
             pool: pool.clone(),
             streams_table_name: streams_name,
             events_table_name: events_name,
+            positions_table_name: positions_name,
+            outbox_table_name: outbox_name,
+            codec,
+            upcasters: Upcasters::new(),
+            outbox: false,
         })
     }
 }
@@ -0,0 +1,48 @@
+use crate::key_store_sqlx_postgres::KeyStoreSQLXPostgres;
+use async_trait::async_trait;
+use cosmo_store::traits::key_store::KeyStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+
+#[async_trait]
+impl KeyStore for KeyStoreSQLXPostgres {
+    async fn get_key(&self, subject: &str) -> Result<Option<Vec<u8>>> {
+        let select_key = format!("select key from {0} where subject = $1", self.table_name());
+        let res: Option<Option<Vec<u8>>> = sqlx::query_scalar(&select_key)
+            .bind(subject)
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(res.flatten())
+    }
+
+    async fn create_key(&self, subject: &str, key: &[u8]) -> Result<Vec<u8>> {
+        let insert_key = format!(
+            "insert into {0} (subject, key) values ($1, $2) on conflict (subject) do nothing",
+            self.table_name()
+        );
+        let _ = sqlx::query(&insert_key)
+            .bind(subject)
+            .bind(key)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        self.get_key(subject)
+            .await?
+            .ok_or_else(|| EventStoreError::SubjectShredded(subject.to_string()))
+    }
+
+    async fn delete_key(&self, subject: &str) -> Result<()> {
+        let shred_key = format!(
+            "insert into {0} (subject, key, shredded_utc) values ($1, null, current_timestamp) \
+            on conflict (subject) \
+            do update set key = null, shredded_utc = excluded.shredded_utc",
+            self.table_name()
+        );
+        let _ = sqlx::query(&shred_key)
+            .bind(subject)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(())
+    }
+}
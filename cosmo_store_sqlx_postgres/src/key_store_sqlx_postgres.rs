@@ -0,0 +1,49 @@
+use anyhow::Result;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone)]
+pub struct KeyStoreSQLXPostgres {
+    pool: PgPool,
+    table_name: String,
+}
+
+impl KeyStoreSQLXPostgres {
+    pub fn pool(&self) -> PgPool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_key_table(pool: &PgPool, table_name: &str) -> Result<PgQueryResult> {
+        // create table if not exists cs_keys_person (
+        //     subject text not null primary key,
+        // key bytea,
+        // created_utc timestamptz default current_timestamp,
+        // shredded_utc timestamptz
+        // );
+        let key_create_table = format!(
+            "create table if not exists {0} (\
+                    subject text not null primary key,\
+                    key bytea,\
+                    created_utc timestamptz default current_timestamp,\
+                    shredded_utc timestamptz)",
+            table_name
+        );
+
+        let res = sqlx::query(&key_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &PgPool, name: &str) -> Result<KeyStoreSQLXPostgres> {
+        let key_name = format!("cs_keys_{}", name);
+        let _ = KeyStoreSQLXPostgres::create_key_table(pool, &key_name).await?;
+
+        Ok(KeyStoreSQLXPostgres {
+            pool: pool.clone(),
+            table_name: key_name,
+        })
+    }
+}
@@ -1,6 +1,15 @@
 extern crate serde;
+pub mod checkpoint_store;
+pub mod checkpoint_store_sqlx_postgres;
 pub mod command_store;
 pub mod command_store_sqlx_postgres;
 pub mod db_types;
+pub mod event_listener_sqlx_postgres;
+pub mod event_query_sqlx_postgres;
 pub mod event_store;
 pub mod event_store_sqlx_postgres;
+pub mod key_store;
+pub mod key_store_sqlx_postgres;
+pub mod outbox_dispatcher_sqlx_postgres;
+pub mod snapshot_store;
+pub mod snapshot_store_sqlx_postgres;
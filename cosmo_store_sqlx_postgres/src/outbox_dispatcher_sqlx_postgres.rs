@@ -0,0 +1,174 @@
+use crate::db_types::{DBEventData, DBOutboxFailure};
+use crate::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::outbox_sink::OutboxSink;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::outbox::{OutboxDispatch, OutboxFailure, OutboxSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/**
+Publishes the events a store wrote to its outbox, see `EventStoreSQLXPostgres::with_outbox`.
+Claimed rows are hidden from other dispatchers for `claim_timeout`, so several dispatchers can
+share an outbox without holding locks while publishing. Events are published at least once: a
+batch interrupted before it is marked delivered is published again once its claim timed out.
+Rows whose event was removed before it was published are failed, see `get_failed`.
+*/
+#[derive(Debug, Clone)]
+pub struct OutboxDispatcherSQLXPostgres {
+    store: EventStoreSQLXPostgres,
+    settings: OutboxSettings,
+}
+
+impl OutboxDispatcherSQLXPostgres {
+    pub fn new(store: &EventStoreSQLXPostgres, settings: OutboxSettings) -> Self {
+        OutboxDispatcherSQLXPostgres {
+            store: store.clone(),
+            settings,
+        }
+    }
+
+    /// Claims a batch of pending rows, publishes their events to `sink` and records the outcome
+    pub async fn dispatch<Payload, Meta, S>(&self, sink: &S) -> Result<OutboxDispatch>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        S: OutboxSink<Payload, Meta, EventVersion> + Sync + ?Sized,
+    {
+        // A single statement, committed before publishing, so no row stays locked while
+        // the sink is called and concurrent claims skip the rows claimed here
+        let claim_rows = format!(
+            "update {0} set claimed_until = current_timestamp + $1 * interval '1 millisecond' \
+                    where position in (\
+                    select position from {0} \
+                    where delivered_utc is null and failed_utc is null \
+                    and next_attempt_utc <= current_timestamp \
+                    and (claimed_until is null or claimed_until <= current_timestamp) \
+                    order by position limit $2 \
+                    for update skip locked) \
+                    returning position, attempts",
+            self.store.outbox_table_name()
+        );
+        let mut claimed: Vec<(i64, i32)> = sqlx::query_as(&claim_rows)
+            .bind(self.settings.claim_timeout.as_millis() as i64)
+            .bind(self.settings.batch_size as i64)
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        if claimed.is_empty() {
+            return Ok(OutboxDispatch::default());
+        }
+        claimed.sort_unstable();
+
+        let positions: Vec<i64> = claimed.iter().map(|(p, _)| *p).collect();
+        let events_query = format!(
+            "select * from {0} where position = any($1) order by position",
+            self.store.events_table_name()
+        );
+        let events: HashMap<i64, DBEventData> = sqlx::query_as::<_, DBEventData>(&events_query)
+            .bind(&positions)
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?
+            .into_iter()
+            .map(|e| (e.position, e))
+            .collect();
+
+        // Rows out of attempts are left failed, see `get_failed`
+        let record_failure = format!(
+            "update {0} set attempts = attempts + 1, last_error = $2, \
+                    next_attempt_utc = current_timestamp + $3 * interval '1 millisecond', \
+                    claimed_until = null, \
+                    failed_utc = case when attempts + 1 >= $4 then current_timestamp end \
+                    where position = $1",
+            self.store.outbox_table_name()
+        );
+        let record_removed = format!(
+            "update {0} set attempts = attempts + 1, last_error = $2, claimed_until = null, \
+                    failed_utc = current_timestamp where position = $1",
+            self.store.outbox_table_name()
+        );
+        let mark_delivered = format!(
+            "update {0} set delivered_utc = current_timestamp, claimed_until = null \
+                    where position = $1",
+            self.store.outbox_table_name()
+        );
+        let mut dispatched = OutboxDispatch::default();
+        for (position, attempts) in claimed {
+            // Events removed since they were appended, by a hard delete or a scavenge,
+            // can't be published anymore, their rows are failed right away
+            let published = match events.get(&position) {
+                Some(d) => match self.store.db_event_to_event_read(d) {
+                    Ok(event) => sink.publish(&event).await,
+                    Err(e) => Err(e),
+                },
+                None => {
+                    dispatched.failed += 1;
+                    let _ = sqlx::query(&record_removed)
+                        .bind(position)
+                        .bind(format!(
+                            "Event at position {} was removed before it was published",
+                            position
+                        ))
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                    continue;
+                }
+            };
+            match published {
+                Ok(()) => {
+                    dispatched.delivered += 1;
+                    let _ = sqlx::query(&mark_delivered)
+                        .bind(position)
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                }
+                Err(e) => {
+                    dispatched.failed += 1;
+                    let retry_after = self.settings.retry_after(attempts as u32 + 1);
+                    let _ = sqlx::query(&record_failure)
+                        .bind(position)
+                        .bind(e.to_string())
+                        .bind(retry_after.as_millis() as i64)
+                        .bind(self.settings.max_attempts as i32)
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                }
+            }
+        }
+        Ok(dispatched)
+    }
+
+    /// Rows publishing was given up for, oldest first
+    pub async fn get_failed(&self) -> Result<Vec<OutboxFailure>> {
+        let failed_query = format!(
+            "select position, event_id, stream_id, attempts, last_error, failed_utc \
+                    from {0} where failed_utc is not null order by position",
+            self.store.outbox_table_name()
+        );
+        let failed = sqlx::query_as::<_, DBOutboxFailure>(&failed_query)
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(failed.into_iter().map(OutboxFailure::from).collect())
+    }
+
+    /// Keeps dispatching until the outbox can't be read, waiting `poll_interval` whenever
+    /// nothing is pending
+    pub async fn run<Payload, Meta, S>(&self, sink: &S) -> Result<()>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        S: OutboxSink<Payload, Meta, EventVersion> + Sync + ?Sized,
+    {
+        loop {
+            let dispatched = self.dispatch(sink).await?;
+            if dispatched == OutboxDispatch::default() {
+                tokio::time::sleep(self.settings.poll_interval).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotStoreSQLXPostgres {
+    pool: PgPool,
+    table_name: String,
+}
+
+impl SnapshotStoreSQLXPostgres {
+    pub fn pool(&self) -> PgPool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_snapshot_table(pool: &PgPool, table_name: &str) -> Result<PgQueryResult> {
+        // create table if not exists cs_snapshots_person (
+        //     stream_id text not null,
+        // version bigint not null,
+        // state jsonb not null,
+        // created_utc timestamptz default current_timestamp,
+        // primary key (stream_id, version)
+        // );
+        let snapshot_create_table = format!(
+            "create table if not exists {0} (\
+                    stream_id text not null,\
+                    version bigint not null,\
+                    state jsonb not null,\
+                    created_utc timestamptz default current_timestamp,\
+                    primary key (stream_id, version))",
+            table_name
+        );
+
+        let res = sqlx::query(&snapshot_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &PgPool, name: &str) -> Result<SnapshotStoreSQLXPostgres> {
+        let snapshot_name = format!("cs_snapshots_{}", name);
+        let _ = SnapshotStoreSQLXPostgres::create_snapshot_table(pool, &snapshot_name).await?;
+
+        Ok(SnapshotStoreSQLXPostgres {
+            pool: pool.clone(),
+            table_name: snapshot_name,
+        })
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store_sqlx_postgres::checkpoint_store_sqlx_postgres::CheckpointStoreSQLXPostgres;
+use cosmo_store_tests::checkpoint_store_basic_tests as cs;
+use futures::FutureExt;
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Checkpoint Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Checkpoint Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_store(name: &str) -> CheckpointStoreSQLXPostgres {
+    let conn_str = format!("{}{}", CONN_BASE, name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    CheckpointStoreSQLXPostgres::new(&pool, "person")
+        .await
+        .unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+#[actix_rt::test]
+async fn save_checkpoint() {
+    let name = get_name();
+    setup(&name).await;
+    let result =
+        std::panic::AssertUnwindSafe(cs::save_checkpoint(&get_store(&name).await, |res| {
+            assert_eq!(res, Some(7));
+        }))
+        .catch_unwind()
+        .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_checkpoint_of_unknown_projection() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(cs::get_checkpoint_of_unknown_projection(
+        &get_store(&name).await,
+        |res| {
+            assert!(res.is_none());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn checkpoints_are_kept_per_projection() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(cs::checkpoints_are_kept_per_projection(
+        &get_store(&name).await,
+        |people, orders| {
+            assert_eq!(people, Some(3));
+            assert_eq!(orders, Some(5));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn reset_checkpoint() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(cs::reset_checkpoint(
+        &get_store(&name).await,
+        |people, orders| {
+            assert!(people.is_none());
+            assert_eq!(orders, Some(5));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,172 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::common::codec::Codec;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_tests::event_generator::get_stream_id;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::panic;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: u32,
+    item: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct OrderMeta {
+    user: String,
+}
+
+async fn setup(name: &str) {
+    println!("Event Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Event Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_pool(db_name: &str) -> PgPool {
+    let conn_str = format!("{}{}", CONN_BASE, db_name);
+    PgPoolOptions::new().connect(&conn_str).await.unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+fn get_orders(ids: std::ops::RangeInclusive<u32>) -> Vec<EventWrite<Order, OrderMeta>> {
+    ids.map(|id| EventWrite {
+        id: Uuid::new_v4(),
+        correlation_id: None,
+        causation_id: None,
+        name: String::from("OrderPlaced"),
+        data: Order {
+            id,
+            item: format!("Item {}", id),
+        },
+        metadata: Some(OrderMeta {
+            user: String::from("user"),
+        }),
+    })
+    .collect()
+}
+
+async fn get_codecs(pool: &PgPool) -> Vec<String> {
+    sqlx::query_scalar("select codec from cs_events_person order by position")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn binary_codecs_round_trip() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool(&name).await;
+        for codec in [Codec::MessagePack, Codec::Bincode] {
+            let store = EventStoreSQLXPostgres::new_with_codec(&pool, "person", codec)
+                .await
+                .unwrap();
+            let stream_id = get_stream_id();
+            let written = store
+                .append_events(&stream_id, &ExpectedVersion::Any, get_orders(1..=2))
+                .await
+                .unwrap();
+
+            let events: Vec<_> = EventStore::<Order, OrderMeta, _>::get_events(
+                &store,
+                &stream_id,
+                &EventsReadRange::AllEvents,
+            )
+            .await
+            .unwrap();
+            let orders: Vec<Order> = events.iter().map(|e| e.data.clone()).collect();
+            let written: Vec<Order> = written.iter().map(|e| e.data.clone()).collect();
+            assert_eq!(orders, written);
+            assert!(events
+                .iter()
+                .all(|e| e.metadata.as_ref().unwrap().user == "user"));
+        }
+
+        assert_eq!(
+            get_codecs(&pool).await,
+            vec!["msgpack", "msgpack", "bincode", "bincode"]
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn reads_events_written_with_previous_codec() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool(&name).await;
+        let stream_id = get_stream_id();
+        let json = EventStoreSQLXPostgres::new(&pool, "person").await.unwrap();
+        let _ = json
+            .append_events(&stream_id, &ExpectedVersion::Any, get_orders(1..=2))
+            .await
+            .unwrap();
+        let bincode = EventStoreSQLXPostgres::new_with_codec(&pool, "person", Codec::Bincode)
+            .await
+            .unwrap();
+        let _ = bincode
+            .append_events(&stream_id, &ExpectedVersion::Any, get_orders(3..=4))
+            .await
+            .unwrap();
+
+        let events = EventStore::<Order, OrderMeta, _>::get_events(
+            &bincode,
+            &stream_id,
+            &EventsReadRange::AllEvents,
+        )
+        .await
+        .unwrap();
+        let ids: Vec<u32> = events.iter().map(|e| e.data.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(
+            get_codecs(&pool).await,
+            vec!["json", "json", "bincode", "bincode"]
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_sqlx_postgres::event_listener_sqlx_postgres::{
+    EventListenerSQLXPostgres, EventNotification,
+};
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_tests::event_generator::{get_events, get_stream_id};
+use cosmo_store_tests::event_store_basic_tests::{Meta, Payload};
+use futures::FutureExt;
+use sqlx::postgres::PgPoolOptions;
+use std::panic;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Event Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Event Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_store(db_name: &str, store_name: &str) -> EventStoreSQLXPostgres {
+    let conn_str = format!("{}{}", CONN_BASE, db_name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    EventStoreSQLXPostgres::new(&pool, store_name)
+        .await
+        .unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+#[actix_rt::test]
+async fn notifies_every_appended_event() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let store = get_store(&name, "person").await;
+        let mut listener = EventListenerSQLXPostgres::new(&store).await.unwrap();
+        let stream_id = get_stream_id();
+
+        let _ = EventStore::<Payload, Meta, _>::append_events(
+            &store,
+            &stream_id,
+            &ExpectedVersion::Any,
+            get_events(1..=3),
+        )
+        .await
+        .unwrap();
+
+        for position in 1..=3 {
+            let notification = listener.recv().await.unwrap();
+            assert_eq!(
+                notification,
+                EventNotification {
+                    stream_id: stream_id.clone(),
+                    position,
+                }
+            );
+        }
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn notifications_are_scoped_per_store() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let person = get_store(&name, "person").await;
+        let order = get_store(&name, "order").await;
+        let mut listener = EventListenerSQLXPostgres::new(&person).await.unwrap();
+        let order_stream = get_stream_id();
+        let person_stream = get_stream_id();
+
+        let _ = EventStore::<Payload, Meta, _>::append_events(
+            &order,
+            &order_stream,
+            &ExpectedVersion::Any,
+            get_events(1..=2),
+        )
+        .await
+        .unwrap();
+        let _ = EventStore::<Payload, Meta, _>::append_events(
+            &person,
+            &person_stream,
+            &ExpectedVersion::Any,
+            get_events(1..=1),
+        )
+        .await
+        .unwrap();
+
+        let notification = listener.recv().await.unwrap();
+        assert_eq!(notification.stream_id, person_stream);
+        assert_eq!(notification.position, 1);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,200 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_sqlx_postgres::event_query_sqlx_postgres::{JsonColumn, JsonQuery};
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_tests::event_generator::get_stream_id;
+use futures::FutureExt;
+use serde_json::{json, Value};
+use sqlx::postgres::PgPoolOptions;
+use std::panic;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Event Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Event Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_store(db_name: &str) -> EventStoreSQLXPostgres {
+    let conn_str = format!("{}{}", CONN_BASE, db_name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    EventStoreSQLXPostgres::new_with_json_index(&pool, "person")
+        .await
+        .unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+fn get_event(data: Value, metadata: Value) -> EventWrite<Value, Value> {
+    EventWrite {
+        id: Uuid::new_v4(),
+        correlation_id: None,
+        causation_id: None,
+        name: String::from("OrderPlaced"),
+        data,
+        metadata: Some(metadata),
+    }
+}
+
+async fn append_orders(store: &EventStoreSQLXPostgres) {
+    let events = vec![
+        get_event(
+            json!({"customer": {"id": "c1"}, "items": [{"sku": "a"}]}),
+            json!({"tenant": "x"}),
+        ),
+        get_event(
+            json!({"customer": {"id": "c2"}, "items": [{"sku": "b"}]}),
+            json!({"tenant": "y"}),
+        ),
+        get_event(
+            json!({"customer": {"id": "c1"}, "items": [{"sku": "b"}]}),
+            json!({"tenant": "y"}),
+        ),
+    ];
+    let _ = store
+        .append_events(&get_stream_id(), &ExpectedVersion::Any, events)
+        .await
+        .unwrap();
+}
+
+#[actix_rt::test]
+async fn finds_events_containing_metadata_fragment() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let store = get_store(&name).await;
+        append_orders(&store).await;
+
+        let query = JsonQuery::Contains {
+            column: JsonColumn::Metadata,
+            fragment: json!({"tenant": "y"}),
+        };
+        let events = store.query_events::<Value, Value>(&query).await.unwrap();
+
+        let positions: Vec<i64> = events.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![2, 3]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn finds_events_containing_payload_fragment() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let store = get_store(&name).await;
+        append_orders(&store).await;
+
+        let query = JsonQuery::Contains {
+            column: JsonColumn::Data,
+            fragment: json!({"items": [{"sku": "b"}]}),
+        };
+        let events = store.query_events::<Value, Value>(&query).await.unwrap();
+
+        let positions: Vec<i64> = events.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![2, 3]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn finds_events_by_payload_path() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let store = get_store(&name).await;
+        append_orders(&store).await;
+
+        let query = JsonQuery::PathEquals {
+            column: JsonColumn::Data,
+            path: vec!["customer".to_string(), "id".to_string()],
+            value: json!("c1"),
+        };
+        let events = store.query_events::<Value, Value>(&query).await.unwrap();
+        let positions: Vec<i64> = events.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![1, 3]);
+
+        let query = JsonQuery::PathEquals {
+            column: JsonColumn::Data,
+            path: vec!["items".to_string(), "0".to_string(), "sku".to_string()],
+            value: json!("a"),
+        };
+        let events = store.query_events::<Value, Value>(&query).await.unwrap();
+        let positions: Vec<i64> = events.iter().map(|x| x.position).collect();
+        assert_eq!(positions, vec![1]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn creates_json_indexes() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let store = get_store(&name).await;
+
+        let indexes =
+            "select indexname from pg_indexes where tablename = $1 and indexdef like '%gin%'";
+        let indexes: Vec<(String,)> = sqlx::query_as(indexes)
+            .bind(store.events_table_name())
+            .fetch_all(&store.pool())
+            .await
+            .unwrap();
+        let mut indexes: Vec<String> = indexes.into_iter().map(|x| x.0).collect();
+        indexes.sort();
+        assert_eq!(
+            indexes,
+            vec![
+                "ix_cs_events_person_data".to_string(),
+                "ix_cs_events_person_metadata".to_string()
+            ]
+        );
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -2,14 +2,16 @@
 #[macro_use]
 extern crate claim;
 
-use cosmo_store::common::i64_event_version::EventVersion;
+use cosmo_store::common::event_version::EventVersion;
 use cosmo_store::traits::event_store::EventStore;
 use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::EventOrder;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::StreamState;
 use cosmo_store::types::expected_version::ExpectedVersion;
 use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
 use cosmo_store_tests::event_store_basic_tests as bt;
 use cosmo_store_tests::event_store_basic_tests::{check_position, Meta, Payload};
-use futures;
 use futures::FutureExt;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -21,7 +23,7 @@ const CONN_BASE: &str = "postgresql://localhost:5432/";
 
 async fn setup(name: &str) {
     println!("Event Store will be initialized here...");
-    let conn_str = format!("{}", CONN_BASE);
+    let conn_str = CONN_BASE.to_string();
     let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
     let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
     let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
@@ -30,7 +32,7 @@ async fn setup(name: &str) {
 
 async fn teardown(name: &str) {
     println!("Event Store will be destroyed here...");
-    let conn_str = format!("{}", CONN_BASE);
+    let conn_str = CONN_BASE.to_string();
     let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
     let kill_conn = format!(
         "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
@@ -217,7 +219,14 @@ async fn fails_to_append_to_existing_version() {
         &get_store(&name).await,
         EventVersion::new(1_i64),
         |res| {
-            assert_err!(res);
+            assert!(matches!(
+                res,
+                Err(EventStoreError::WrongExpectedVersion {
+                    expected: 1,
+                    actual: 2,
+                    ..
+                })
+            ));
         },
     ))
     .catch_unwind()
@@ -235,7 +244,10 @@ async fn fails_to_append_to_existing_stream_if_is_not_expected_to_exist() {
         bt::fails_to_append_to_existing_stream_if_is_not_expected_to_exist(
             &get_store(&name).await,
             |res| {
-                assert_err!(res);
+                assert!(matches!(
+                    res,
+                    Err(EventStoreError::WrongExpectedVersion { .. })
+                ));
             },
         ),
     )
@@ -306,3 +318,623 @@ async fn can_read_events_by_correlation_id() {
 
     assert_ok!(result);
 }
+
+#[actix_rt::test]
+async fn can_read_events_by_causation_id() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(
+        bt::can_read_events_by_causation_id::<EventVersion, _>(&get_store(&name).await, |events| {
+            let names: Vec<String> = events.iter().map(|x| x.name.clone()).collect();
+            let expected: Vec<String> = (1..=9).map(|i| format!("Created_{}", i)).collect();
+            assert_eq!(names, expected);
+        }),
+    )
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+fn single_winner(
+    results: Vec<Result<Vec<EventRead<Payload, Meta, EventVersion>>>>,
+    events: Vec<EventRead<Payload, Meta, EventVersion>>,
+    expected_events: usize,
+) {
+    let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+    assert_eq!(ok.len(), 1);
+    assert!(err
+        .iter()
+        .all(|r| matches!(r, Err(EventStoreError::WrongExpectedVersion { .. }))));
+    assert_eq!(events.len(), expected_events);
+    let versions: Vec<i64> = events.iter().map(|x| x.version.0).unique().collect();
+    assert_eq!(versions, (1..=expected_events as i64).collect::<Vec<i64>>());
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_same_expected_version() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_with_same_expected_version(
+        &get_store(&name).await,
+        EventVersion::new(2_i64),
+        10,
+        |results, events| single_winner(results, events, 4),
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_to_new_stream() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_to_new_stream(
+        &get_store(&name).await,
+        10,
+        |results, events| single_winner(results, events, 3),
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_any_version() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_with_any_version(
+        &get_store(&name).await,
+        10,
+        |results, events| {
+            assert!(results.iter().all(|r| r.is_ok()));
+            assert_eq!(events.len(), 30);
+            are_ascending(events);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_of_same_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_of_same_events(
+        &get_store(&name).await,
+        10,
+        |results| {
+            let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+            assert_eq!(ok.len(), 1);
+            assert!(err
+                .iter()
+                .all(|r| matches!(r, Err(EventStoreError::DuplicateEvents { .. }))));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_all_events_from_position() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_all_events_from_position(
+        &get_store(&name).await,
+        4,
+        |pages| {
+            assert!(pages.iter().all(|p| p.len() <= 4));
+            let positions: Vec<i64> = pages.iter().flatten().map(|x| x.position).collect();
+            assert_eq!(positions, (1..=27).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_events_from_version() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_events_from_version(
+        &get_store(&name).await,
+        EventVersion::new(501),
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (501..=1000).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_events_by_correlation_id() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_events_by_correlation_id(
+        &get_store(&name).await,
+        |res| {
+            assert_eq!(res.len(), 15);
+            assert!(res.windows(2).all(|w| w[0].position < w[1].position));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_streams_with_filter() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_streams_with_filter(
+        &get_store(&name).await,
+        |res| {
+            let mut ids: Vec<String> = res.into_iter().map(|x| x.id).collect();
+            ids.sort();
+            assert_eq!(ids.len(), 2);
+            assert!(ids[0].ends_with("_first"));
+            assert!(ids[1].ends_with("_second"));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn stream_filters_match_wildcards_literally() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::stream_filters_match_wildcards_literally(
+        &get_store(&name).await,
+        |matched| {
+            assert_eq!(matched, vec![vec!["_1"], vec!["%1"], vec!["\\1"]]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_forward_in_pages() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_forward_in_pages(
+        &get_store(&name).await,
+        10,
+        |pages| {
+            let sizes: Vec<usize> = pages.iter().map(|p| p.len()).collect();
+            assert_eq!(sizes, vec![10, 10, 5]);
+            let versions: Vec<i64> = pages.iter().flatten().map(|x| x.version.0).collect();
+            assert_eq!(versions, (1..=25).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_last_events_backwards() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_backwards(
+        &get_store(&name).await,
+        None,
+        10,
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (16..=25).rev().collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_backwards_from_version() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_backwards(
+        &get_store(&name).await,
+        Some(EventVersion::new(12)),
+        5,
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (8..=12).rev().collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn soft_deleted_stream_can_be_recreated() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::soft_deleted_stream_can_be_recreated(
+        &get_store(&name).await,
+        |deleted, events| {
+            assert_eq!(deleted.len(), 1);
+            assert_eq!(deleted[0].state, StreamState::Deleted);
+            assert_eq!(deleted[0].last_version.0, 3);
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![4, 5]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn deleted_events_are_hidden_from_all_reads() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::deleted_events_are_hidden_from_all_reads(
+        &get_store(&name).await,
+        |correlated, all| {
+            assert_eq!(correlated.len(), 2);
+            assert_eq!(all.len(), 2);
+            let ids = |events: &Vec<EventRead<Payload, Meta, EventVersion>>| {
+                events.iter().map(|x| x.id).collect::<Vec<Uuid>>()
+            };
+            assert_eq!(ids(&correlated), ids(&all));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn hard_deleted_stream_is_removed() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::hard_deleted_stream_is_removed(
+        &get_store(&name).await,
+        |last_position, recreated| {
+            assert_eq!(recreated[0].version.0, 1);
+            assert!(recreated[0].position > last_position);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn tombstoned_stream_rejects_appends() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::tombstoned_stream_rejects_appends(
+        &get_store(&name).await,
+        |appended, deleted, listed| {
+            assert!(matches!(
+                appended,
+                Err(EventStoreError::StreamTombstoned(_))
+            ));
+            assert!(matches!(deleted, Err(EventStoreError::StreamTombstoned(_))));
+            assert_eq!(listed.len(), 1);
+            assert_eq!(listed[0].state, StreamState::Tombstoned);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn max_count_hides_older_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::max_count_hides_older_events(
+        &get_store(&name).await,
+        |stream, events| {
+            assert_eq!(stream.metadata.max_count, Some(3));
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![8, 9, 10]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn truncate_before_hides_earlier_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::truncate_before_hides_earlier_events(
+        &get_store(&name).await,
+        EventVersion::new(6),
+        |events| {
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (6..=10).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn deleted_events_stay_hidden_after_metadata_change() {
+    let name = get_name();
+    setup(&name).await;
+    let result =
+        std::panic::AssertUnwindSafe(bt::deleted_events_stay_hidden_after_metadata_change(
+            &get_store(&name).await,
+            EventVersion::new(2),
+            |stream, events| {
+                assert_eq!(stream.metadata.truncate_before, Some(EventVersion::new(2)));
+                let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+                assert_eq!(versions, vec![4, 5]);
+            },
+        ))
+        .catch_unwind()
+        .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn max_age_hides_expired_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::max_age_hides_expired_events(
+        &get_store(&name).await,
+        |events| {
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![4, 5]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn scavenge_removes_hidden_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::scavenge_removes_hidden_events(
+        &get_store(&name).await,
+        |removed, removed_again, remaining| {
+            assert_eq!(removed, 9);
+            assert_eq!(removed_again, 0);
+            let versions: Vec<i64> = remaining.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![8, 9, 10]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_position_order() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_by_name(
+        &get_store(&name).await,
+        EventOrder::Position,
+        |single, multiple| {
+            assert!(single.iter().all(|x| x.name == "Created_2"));
+            let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![2, 5]);
+            let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![1, 3, 4, 6]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_time_order() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_by_name(
+        &get_store(&name).await,
+        EventOrder::CreatedUtc,
+        |single, multiple| {
+            assert!(single.iter().all(|x| x.name == "Created_2"));
+            let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![2, 5]);
+            let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![1, 3, 4, 6]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_in_time_range() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_in_time_range(
+        &get_store(&name).await,
+        |all, single| {
+            let positions: Vec<i64> = all.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![3, 4, 5, 6]);
+            let versions: Vec<i64> = single.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![3, 4]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn appends_to_multiple_streams_atomically() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::appends_to_multiple_streams_atomically(
+        &get_store(&name).await,
+        EventVersion::new(3),
+        |results, source, target| {
+            let counts: Vec<usize> = results.iter().map(|r| r.len()).collect();
+            assert_eq!(counts, vec![1, 2]);
+            assert!(results[1][0].position > results[0][0].position);
+            let versions: Vec<i64> = source.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2, 3]);
+            let versions: Vec<i64> = target.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn multi_stream_append_fails_as_a_whole() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::multi_stream_append_fails_as_a_whole(
+        &get_store(&name).await,
+        EventVersion::new(2),
+        |result, source, target| {
+            assert!(matches!(
+                result,
+                Err(EventStoreError::WrongExpectedVersion { .. })
+            ));
+            assert_eq!(source.len(), 2);
+            assert!(matches!(target, Err(EventStoreError::StreamNotFound(_))));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_multi_stream_appends() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_multi_stream_appends(
+        &get_store(&name).await,
+        10,
+        |results, first, second| {
+            assert!(results.iter().all(|r| r.is_ok()));
+            assert_eq!(first.len(), 20);
+            assert_eq!(second.len(), 20);
+            are_ascending(first);
+            are_ascending(second);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn retried_append_returns_original_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::retried_append_returns_original_events(
+        &get_store(&name).await,
+        |first, retried, events| {
+            let keys: Vec<(Uuid, i64)> = first.iter().map(|x| (x.id, x.position)).collect();
+            let retried_keys: Vec<(Uuid, i64)> =
+                retried.iter().map(|x| (x.id, x.position)).collect();
+            assert_eq!(retried_keys, keys);
+            assert_eq!(events.len(), 3);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn overlapping_append_is_rejected() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(bt::overlapping_append_is_rejected(
+        &get_store(&name).await,
+        |result, events| {
+            assert!(matches!(
+                result,
+                Err(EventStoreError::DuplicateEvents { ids, .. }) if ids.len() == 1
+            ));
+            assert_eq!(events.len(), 2);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
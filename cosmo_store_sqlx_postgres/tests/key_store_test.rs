@@ -0,0 +1,116 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store_sqlx_postgres::key_store_sqlx_postgres::KeyStoreSQLXPostgres;
+use cosmo_store_tests::key_store_basic_tests as ks;
+use futures::FutureExt;
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Key Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Key Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_store(name: &str) -> KeyStoreSQLXPostgres {
+    let conn_str = format!("{}{}", CONN_BASE, name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    KeyStoreSQLXPostgres::new(&pool, "person").await.unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+#[actix_rt::test]
+async fn create_key() {
+    let name = get_name();
+    setup(&name).await;
+    let result =
+        std::panic::AssertUnwindSafe(ks::create_key(&get_store(&name).await, |created, res| {
+            assert_eq!(created, vec![1, 2, 3]);
+            assert_eq!(res, Some(vec![1, 2, 3]));
+        }))
+        .catch_unwind()
+        .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn create_key_keeps_existing_key() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(ks::create_key_keeps_existing_key(
+        &get_store(&name).await,
+        |res| {
+            assert_eq!(res, vec![1]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_key_of_unknown_subject() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(ks::get_key_of_unknown_subject(
+        &get_store(&name).await,
+        |res| {
+            assert!(res.is_none());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn delete_key_shreds_subject() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(ks::delete_key_shreds_subject(
+        &get_store(&name).await,
+        |key, recreated| {
+            assert!(key.is_none());
+            assert!(matches!(
+                recreated,
+                Err(EventStoreError::SubjectShredded(_))
+            ));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,243 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::common::codec::Codec;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::command_store::CommandStore;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::command_status::CommandStatus;
+use cosmo_store::types::command_write::{CommandRead, CommandWrite};
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_sqlx_postgres::command_store_sqlx_postgres::CommandStoreSQLXPostgres;
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_tests::event_generator::get_events;
+use cosmo_store_tests::event_store_basic_tests::{Meta, Payload};
+use futures::FutureExt;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::panic;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Event Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Event Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_pool(db_name: &str) -> PgPool {
+    let conn_str = format!("{}{}", CONN_BASE, db_name);
+    PgPoolOptions::new().connect(&conn_str).await.unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+// Tables as created by the first release, holding events of two streams
+async fn create_baseline_tables(pool: &PgPool) {
+    let statements = [
+        "create table cs_streams_person (id text primary key, \
+                last_version bigint not null, \
+                last_updated_utc timestamptz default current_timestamp )",
+        "create table cs_events_person (\
+                id uuid primary key,\
+                correlation_id uuid default null,\
+                causation_id uuid default null,\
+                stream_id text not null,\
+                constraint fk_stream foreign key (stream_id) references cs_streams_person(id) \
+                on delete cascade,\
+                version bigint not null,\
+                name varchar(255) not null ,\
+                data jsonb not null ,\
+                metadata jsonb default null,\
+                created_utc timestamptz default current_timestamp)",
+        "insert into cs_streams_person (id, last_version) values ('person-1', 2), ('person-2', 1)",
+    ];
+    for statement in statements {
+        let _ = sqlx::query(statement).execute(pool).await.unwrap();
+    }
+    let insert_event = "insert into cs_events_person (id, stream_id, version, name, data) \
+            values ($1, $2, $3, $4, $5::jsonb)";
+    for (stream_id, version, name) in [
+        ("person-1", 1, "Created_1"),
+        ("person-2", 1, "Created_2"),
+        ("person-1", 2, "Created_3"),
+    ] {
+        let _ = sqlx::query(insert_event)
+            .bind(Uuid::new_v4())
+            .bind(stream_id)
+            .bind(version as i64)
+            .bind(name)
+            .bind(format!("{{\"name\": \"{}\"}}", name))
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[actix_rt::test]
+async fn opens_tables_created_with_baseline_schema() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool(&name).await;
+        create_baseline_tables(&pool).await;
+
+        // Upgrading again is a no-op
+        let _ = EventStoreSQLXPostgres::new(&pool, "person").await.unwrap();
+        let store = EventStoreSQLXPostgres::new_with_codec(&pool, "person", Codec::Bincode)
+            .await
+            .unwrap();
+
+        let events: Vec<_> = EventStore::<Payload, Meta, _>::get_all_events(&store, 0, 10)
+            .await
+            .unwrap();
+        let stored: Vec<(String, i64)> = events
+            .iter()
+            .map(|e| (e.name.clone(), e.position))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                (String::from("Created_1"), 1),
+                (String::from("Created_2"), 2),
+                (String::from("Created_3"), 3),
+            ]
+        );
+        assert_eq!(events[2].data.name, "Created_3");
+
+        // Binary codecs leave `data` null
+        let appended = store
+            .append_events(
+                "person-1",
+                &ExpectedVersion::Exact(EventVersion::new(3)),
+                get_events(4..=4),
+            )
+            .await
+            .unwrap();
+        assert_eq!(appended[0].position, 4);
+
+        let stream: Vec<_> = EventStore::<Payload, Meta, _>::get_events(
+            &store,
+            "person-1",
+            &EventsReadRange::AllEvents,
+        )
+        .await
+        .unwrap();
+        let names: Vec<&str> = stream.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Created_1", "Created_3", "Created_4"]);
+        assert_eq!(stream[2].version, EventVersion::new(3));
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+// Command table as created by the first release, holding a single command
+async fn create_baseline_command_table(pool: &PgPool, correlation_id: &Uuid) -> Uuid {
+    let _ = sqlx::query(
+        "create table cs_commands_person \
+                (id uuid primary key , \
+                correlation_id uuid not null, \
+                causation_id uuid not null , \
+                data jsonb not null , \
+                name varchar(255) not null , \
+                created_utc timestamptz default current_timestamp)",
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    let id = Uuid::new_v4();
+    let _ = sqlx::query(
+        "insert into cs_commands_person (id, correlation_id, causation_id, data, name) \
+                values ($1, $2, $3, $4::jsonb, $5)",
+    )
+    .bind(id)
+    .bind(correlation_id)
+    .bind(Uuid::new_v4())
+    .bind("{\"name\": \"Stored\"}")
+    .bind("some_command")
+    .execute(pool)
+    .await
+    .unwrap();
+    id
+}
+
+#[actix_rt::test]
+async fn opens_command_tables_created_with_baseline_schema() {
+    let name = get_name();
+    setup(&name).await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool(&name).await;
+        let correlation_id = Uuid::new_v4();
+        let stored_id = create_baseline_command_table(&pool, &correlation_id).await;
+
+        // Upgrading again is a no-op
+        let _ = CommandStoreSQLXPostgres::new(&pool, "person")
+            .await
+            .unwrap();
+        let store = CommandStoreSQLXPostgres::new(&pool, "person")
+            .await
+            .unwrap();
+
+        let stored: CommandRead<Payload> = store.get_command(&stored_id).await.unwrap();
+        assert_eq!(stored.data.name, "Stored");
+        assert_eq!(stored.status, CommandStatus::Received);
+        let stored: CommandRead<Payload> = store
+            .transition_command(&stored_id, &CommandStatus::Processing)
+            .await
+            .unwrap();
+        assert_eq!(stored.status, CommandStatus::Processing);
+
+        let received = store
+            .receive_command(&CommandWrite {
+                id: Uuid::new_v4(),
+                correlation_id,
+                causation_id: Uuid::new_v4(),
+                data: Payload {
+                    name: String::from("Received"),
+                },
+                name: String::from("some_command"),
+            })
+            .await
+            .unwrap();
+        assert_eq!(received.status, CommandStatus::Received);
+
+        let commands: Vec<CommandRead<Payload>> = store
+            .get_commands_by_correlation_id(&correlation_id)
+            .await
+            .unwrap();
+        let names: Vec<&str> = commands.iter().map(|c| c.data.name.as_str()).collect();
+        assert_eq!(names, vec!["Stored", "Received"]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store_sqlx_postgres::snapshot_store_sqlx_postgres::SnapshotStoreSQLXPostgres;
+use cosmo_store_tests::snapshot_store_basic_tests as st;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "postgresql://localhost:5432/";
+
+async fn setup(name: &str) {
+    println!("Snapshot Store will be initialized here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Created {}", name);
+}
+
+async fn teardown(name: &str) {
+    println!("Snapshot Store will be destroyed here...");
+    let conn_str = CONN_BASE.to_string();
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let create_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+    println!("Destroyed {}", name);
+}
+
+async fn get_store<State>(name: &str) -> impl SnapshotStore<State, EventVersion>
+where
+    State: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    let conn_str = format!("{}{}", CONN_BASE, name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    let store = SnapshotStoreSQLXPostgres::new(&pool, "person")
+        .await
+        .unwrap();
+    store
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+#[actix_rt::test]
+async fn save_snapshot() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(st::save_snapshot(
+        &get_store(&name).await,
+        EventVersion::new(3),
+        |res| {
+            assert_eq!(res.version.0, 3);
+            assert_eq!(res.state.name, "State 1");
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(st::get_latest_snapshot(
+        &get_store(&name).await,
+        vec![
+            EventVersion::new(2),
+            EventVersion::new(7),
+            EventVersion::new(5),
+        ],
+        |res| {
+            let res = res.unwrap();
+            assert_eq!(res.version.0, 7);
+            assert_eq!(res.state.name, "State 1");
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot_of_unknown_stream() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(st::get_latest_snapshot_of_unknown_stream(
+        &get_store(&name).await,
+        |res| {
+            assert!(res.is_none());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn saving_same_version_replaces_snapshot() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(st::saving_same_version_replaces_snapshot(
+        &get_store(&name).await,
+        EventVersion::new(4),
+        |res| {
+            let res = res.unwrap();
+            assert_eq!(res.version.0, 4);
+            assert_eq!(res.state.name, "State 2");
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn delete_snapshots() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(st::delete_snapshots(
+        &get_store(&name).await,
+        vec![EventVersion::new(2), EventVersion::new(4)],
+        |deleted, other| {
+            assert!(deleted.is_none());
+            assert_eq!(other.unwrap().state.name, "State 100");
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::SqliteQueryResult;
+
+#[derive(Debug, Clone)]
+pub struct CheckpointStoreSQLXSqlite {
+    pool: SqlitePool,
+    table_name: String,
+}
+
+impl CheckpointStoreSQLXSqlite {
+    pub fn pool(&self) -> SqlitePool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_checkpoint_table(
+        pool: &SqlitePool,
+        table_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create table if not exists cs_checkpoints_person (
+        //     projection text not null primary key,
+        // position integer not null,
+        // updated_utc date default (datetime('now','utc'))
+        // );
+        let checkpoint_create_table = format!(
+            "create table if not exists {0} (\
+                    projection text not null primary key,\
+                    position integer not null,\
+                    updated_utc date default (datetime('now','utc')))",
+            table_name
+        );
+
+        let res = sqlx::query(&checkpoint_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &SqlitePool, name: &str) -> Result<CheckpointStoreSQLXSqlite> {
+        let checkpoint_name = format!("cs_checkpoints_{}", name);
+        let _ = CheckpointStoreSQLXSqlite::create_checkpoint_table(pool, &checkpoint_name).await?;
+
+        Ok(CheckpointStoreSQLXSqlite {
+            pool: pool.clone(),
+            table_name: checkpoint_name,
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use crate::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
 use anyhow::Result;
 use sqlx::sqlite::SqlitePool;
 use sqlx::sqlite::SqliteQueryResult;
@@ -26,6 +27,9 @@ impl CommandStoreSQLXSqlite {
         // causation_id uuid not null ,
         // data jsonb not null ,
         // name varchar(255) not null ,
+        // status varchar(16) not null default 'received' ,
+        // failure_reason text ,
+        // event_ids json ,
         // created_utc timestamptz default current_timestamp
         // )
 
@@ -36,7 +40,10 @@ impl CommandStoreSQLXSqlite {
                     causation_id text not null , \
                     data json not null , \
                     name varchar(255) not null , \
-                    created_utc date default (datetime('now','utc')))",
+                    status varchar(16) not null default 'received' , \
+                    failure_reason text , \
+                    event_ids json , \
+                    created_utc text default (strftime('%Y-%m-%d %H:%M:%f', 'now')))",
             table_name
         );
 
@@ -44,9 +51,24 @@ impl CommandStoreSQLXSqlite {
         Ok(res)
     }
 
+    async fn upgrade_command_table(pool: &SqlitePool, table_name: &str) -> Result<usize> {
+        // Tables created by earlier versions get the columns added since
+        EventStoreSQLXSqlite::add_missing_columns(
+            pool,
+            table_name,
+            &[
+                ("status", "varchar(16) not null default 'received'"),
+                ("failure_reason", "text"),
+                ("event_ids", "json"),
+            ],
+        )
+        .await
+    }
+
     pub async fn new(pool: &SqlitePool, name: &str) -> Result<CommandStoreSQLXSqlite> {
         let command_name = format!("cs_commands_{}", name);
         let _ = CommandStoreSQLXSqlite::create_command_table(pool, &command_name).await?;
+        let _ = CommandStoreSQLXSqlite::upgrade_command_table(pool, &command_name).await?;
 
         Ok(CommandStoreSQLXSqlite {
             pool: pool.clone(),
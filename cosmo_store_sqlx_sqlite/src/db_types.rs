@@ -1,6 +1,12 @@
 use chrono::{DateTime, Utc};
-use cosmo_store::common::i64_event_version::EventVersion;
-use cosmo_store::types::event_stream::EventStream;
+use cosmo_store::common::codec::Encoded;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::types::command_status::CommandStatus;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::{EventStream, StreamState};
+use cosmo_store::types::outbox::OutboxFailure;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -8,6 +14,27 @@ pub struct DBEventStream {
     pub id: String,
     pub last_version: i64,
     pub last_updated_utc: DateTime<Utc>,
+    pub state: String,
+    pub max_count: Option<i64>,
+    pub max_age: Option<i64>,
+    pub truncate_before: i64,
+    pub deleted_before: i64,
+}
+
+pub(crate) fn stream_state_to_db(state: StreamState) -> &'static str {
+    match state {
+        StreamState::Active => "active",
+        StreamState::Deleted => "deleted",
+        StreamState::Tombstoned => "tombstoned",
+    }
+}
+
+fn stream_state_from_db(state: &str) -> StreamState {
+    match state {
+        "deleted" => StreamState::Deleted,
+        "tombstoned" => StreamState::Tombstoned,
+        _ => StreamState::Active,
+    }
 }
 
 impl From<DBEventStream> for EventStream<EventVersion> {
@@ -16,6 +43,19 @@ impl From<DBEventStream> for EventStream<EventVersion> {
             id: s.id,
             last_version: EventVersion::new(s.last_version),
             last_updated_utc: s.last_updated_utc,
+            state: stream_state_from_db(&s.state),
+            metadata: StreamMetadata {
+                max_count: s.max_count.map(|c| c as u64),
+                max_age: s.max_age.map(|a| Duration::from_secs(a as u64)),
+                truncate_before: match s.truncate_before {
+                    0 => None,
+                    v => Some(EventVersion::new(v)),
+                },
+            },
+            deleted_before: match s.deleted_before {
+                0 => None,
+                v => Some(EventVersion::new(v)),
+            },
         }
     }
 }
@@ -26,10 +66,20 @@ impl From<EventStream<EventVersion>> for DBEventStream {
             id: s.id,
             last_version: s.last_version.0,
             last_updated_utc: s.last_updated_utc,
+            state: stream_state_to_db(s.state).to_string(),
+            max_count: s.metadata.max_count.map(|c| c as i64),
+            max_age: s.metadata.max_age.map(|a| a.as_secs() as i64),
+            truncate_before: s.metadata.truncate_before.map_or(0, |v| v.0),
+            deleted_before: s.deleted_before.map_or(0, |v| v.0),
         }
     }
 }
 
+// Fixed width text, so timestamps compare in the same order as they were taken
+pub(crate) fn db_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct DBEventData {
     pub(crate) id: Uuid,
@@ -37,18 +87,121 @@ pub struct DBEventData {
     pub(crate) causation_id: Option<Uuid>,
     pub(crate) stream_id: String,
     pub(crate) version: i64,
+    pub(crate) position: i64,
     pub(crate) name: String,
-    pub(crate) data: serde_json::Value,
+    pub(crate) data: Option<serde_json::Value>,
     pub(crate) metadata: Option<serde_json::Value>,
+    pub(crate) data_bin: Option<Vec<u8>>,
+    pub(crate) metadata_bin: Option<Vec<u8>>,
+    pub(crate) codec: String,
+    pub(crate) schema_version: i32,
+    pub(crate) created_utc: DateTime<Utc>,
+}
+
+// Json and binary column a value is stored in, only one of them is set
+pub(crate) fn encoded_to_db(encoded: Encoded) -> (Option<serde_json::Value>, Option<Vec<u8>>) {
+    match encoded {
+        Encoded::Json(v) => (Some(v), None),
+        Encoded::Binary(b) => (None, Some(b)),
+    }
+}
+
+pub(crate) fn encoded_from_db(
+    json: Option<serde_json::Value>,
+    binary: Option<Vec<u8>>,
+) -> Option<Encoded> {
+    match (json, binary) {
+        (Some(v), _) => Some(Encoded::Json(v)),
+        (None, Some(b)) => Some(Encoded::Binary(b)),
+        (None, None) => None,
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DBCommandData {
+    pub(crate) id: Uuid,
+    pub(crate) correlation_id: Uuid,
+    pub(crate) causation_id: Uuid,
+    pub(crate) data: serde_json::Value,
+    pub(crate) name: String,
+    pub(crate) status: String,
+    pub(crate) failure_reason: Option<String>,
+    pub(crate) event_ids: Option<serde_json::Value>,
     pub(crate) created_utc: DateTime<Utc>,
 }
 
-// #[derive(Debug, Clone, sqlx::FromRow)]
-// pub struct DBCommandData {
-//     pub(crate) id: Uuid,
-//     pub(crate) correlation_id: Uuid,
-//     pub(crate) causation_id: Uuid,
-//     pub(crate) data: serde_json::Value,
-//     pub(crate) name: String,
-//     pub(crate) created_utc: DateTime<Utc>,
-// }
+// Failure reason and produced event ids stored along the status name
+pub(crate) fn command_status_to_db(
+    status: &CommandStatus,
+) -> (Option<String>, Option<serde_json::Value>) {
+    match status {
+        CommandStatus::Succeeded { event_ids } => (
+            None,
+            Some(event_ids.iter().map(|id| id.to_string()).collect()),
+        ),
+        CommandStatus::Failed { reason } => (Some(reason.to_string()), None),
+        _ => (None, None),
+    }
+}
+
+pub(crate) fn command_status_from_db(
+    status: &str,
+    failure_reason: Option<String>,
+    event_ids: Option<serde_json::Value>,
+) -> Result<CommandStatus> {
+    match status {
+        "received" => Ok(CommandStatus::Received),
+        "processing" => Ok(CommandStatus::Processing),
+        "succeeded" => {
+            let event_ids: Vec<String> = event_ids
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(EventStoreError::serialization)?
+                .unwrap_or_default();
+            let event_ids = event_ids
+                .iter()
+                .map(|id| Uuid::parse_str(id))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(EventStoreError::serialization)?;
+            Ok(CommandStatus::Succeeded { event_ids })
+        }
+        "failed" => Ok(CommandStatus::Failed {
+            reason: failure_reason.unwrap_or_default(),
+        }),
+        _ => Err(EventStoreError::serialization(format!(
+            "Unknown command status {}",
+            status
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DBSnapshotData {
+    pub(crate) stream_id: String,
+    pub(crate) version: i64,
+    pub(crate) state: serde_json::Value,
+    pub(crate) created_utc: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DBOutboxFailure {
+    pub(crate) position: i64,
+    pub(crate) event_id: Uuid,
+    pub(crate) stream_id: String,
+    pub(crate) attempts: i32,
+    pub(crate) last_error: Option<String>,
+    pub(crate) failed_utc: DateTime<Utc>,
+}
+
+impl From<DBOutboxFailure> for OutboxFailure {
+    fn from(f: DBOutboxFailure) -> Self {
+        OutboxFailure {
+            position: f.position,
+            event_id: f.event_id,
+            stream_id: f.stream_id,
+            attempts: f.attempts as u32,
+            last_error: f.last_error,
+            failed_utc: f.failed_utc,
+        }
+    }
+}
@@ -1,12 +1,21 @@
 use anyhow::Result;
+use cosmo_store::common::codec::Codec;
+use cosmo_store::common::upcast::Upcasters;
 use sqlx::sqlite::SqlitePool;
 use sqlx::sqlite::SqliteQueryResult;
+use sqlx::{Executor, Sqlite};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct EventStoreSQLXSqlite {
     pool: SqlitePool,
     streams_table_name: String,
     events_table_name: String,
+    positions_table_name: String,
+    outbox_table_name: String,
+    codec: Codec,
+    upcasters: Upcasters,
+    outbox: bool,
 }
 
 impl EventStoreSQLXSqlite {
@@ -22,6 +31,40 @@ impl EventStoreSQLXSqlite {
         self.events_table_name.to_string()
     }
 
+    pub fn positions_table_name(&self) -> String {
+        self.positions_table_name.to_string()
+    }
+
+    pub fn outbox_table_name(&self) -> String {
+        self.outbox_table_name.to_string()
+    }
+
+    /// Codec new events are written with
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn upcasters(&self) -> &Upcasters {
+        &self.upcasters
+    }
+
+    /// Upcasts payloads of events stored at older schema versions while reading them
+    pub fn with_upcasters(self, upcasters: Upcasters) -> EventStoreSQLXSqlite {
+        EventStoreSQLXSqlite { upcasters, ..self }
+    }
+
+    pub fn outbox(&self) -> bool {
+        self.outbox
+    }
+
+    /// Writes an outbox row for every appended event, in the same transaction as the event
+    pub fn with_outbox(self) -> EventStoreSQLXSqlite {
+        EventStoreSQLXSqlite {
+            outbox: true,
+            ..self
+        }
+    }
+
     async fn create_stream_table(
         pool: &SqlitePool,
         streams_name: &str,
@@ -29,13 +72,23 @@ impl EventStoreSQLXSqlite {
         // create table if not exists cs_stream_person (
         //     id text primary key,
         // last_version bigint not null ,
-        // last_updated_utc timestamptz default current_timestamp
+        // last_updated_utc timestamptz default current_timestamp,
+        // state varchar(16) not null default 'active',
+        // truncate_before bigint not null default 0,
+        // max_count bigint default null,
+        // max_age bigint default null,
+        // deleted_before bigint not null default 0
         // );
         let streams_create_table = format!(
             "create table if not exists \
                     {0} (id text primary key, \
                     last_version integer not null, \
-                    last_updated_utc date default (datetime('now','utc')))",
+                    last_updated_utc date default (datetime('now','utc')), \
+                    state varchar(16) not null default 'active', \
+                    truncate_before integer not null default 0, \
+                    max_count integer default null, \
+                    max_age integer default null, \
+                    deleted_before integer not null default 0)",
             streams_name
         );
 
@@ -43,11 +96,14 @@ impl EventStoreSQLXSqlite {
         Ok(res)
     }
 
-    async fn create_event_table(
-        pool: &SqlitePool,
+    async fn create_event_table<'e, E>(
+        executor: E,
         events_name: &str,
         streams_name: &str,
-    ) -> Result<SqliteQueryResult> {
+    ) -> Result<SqliteQueryResult>
+    where
+        E: Executor<'e, Database = Sqlite>,
+    {
         // create table if not exists cs_events_person (
         //     id uuid primary key,
         // correlation_id uuid default null,
@@ -55,9 +111,14 @@ impl EventStoreSQLXSqlite {
         // stream_id text not null,
         // constraint fk_stream foreign key (stream_id) references cs_stream_person(id) on delete cascade,
         // version bigint not null,
+        // position bigint not null,
         // name varchar(255) not null ,
-        // data jsonb not null ,
-        // metadata jsonb,
+        // data json default null,
+        // metadata json default null,
+        // data_bin blob default null,
+        // metadata_bin blob default null,
+        // codec varchar(16) not null default 'json',
+        // schema_version integer not null default 1,
         // created_utc timestamptz default current_timestamp
         // );
         let events_create_table = format!(
@@ -67,17 +128,216 @@ impl EventStoreSQLXSqlite {
                     causation_id text default null,\
                     stream_id text not null,\
                     version integer,\
+                    position integer not null,\
                     name varchar(255) not null ,\
-                    data json not null ,\
+                    data json default null,\
                     metadata json default null,\
-                    created_utc date default (datetime('now','utc')),\
+                    data_bin blob default null,\
+                    metadata_bin blob default null,\
+                    codec varchar(16) not null default 'json',\
+                    schema_version integer not null default 1,\
+                    created_utc text default (strftime('%Y-%m-%d %H:%M:%f', 'now')),\
                     constraint fk_stream foreign key (stream_id) references {1}(id) \
                     on delete cascade)\
                     ",
             events_name, streams_name
         );
 
-        let res = sqlx::query(&events_create_table).execute(pool).await?;
+        let res = sqlx::query(&events_create_table).execute(executor).await?;
+        Ok(res)
+    }
+
+    // Columns of `table`, telling whether they are declared not null
+    pub(crate) async fn table_columns(
+        pool: &SqlitePool,
+        table: &str,
+    ) -> Result<HashMap<String, bool>> {
+        let columns: Vec<(String, bool)> =
+            sqlx::query_as("select name, \"notnull\" from pragma_table_info(?)")
+                .bind(table)
+                .fetch_all(pool)
+                .await?;
+        Ok(columns.into_iter().collect())
+    }
+
+    // Adds the `columns` missing from `table`, returns how many were added
+    pub(crate) async fn add_missing_columns(
+        pool: &SqlitePool,
+        table: &str,
+        columns: &[(&str, &str)],
+    ) -> Result<usize> {
+        let existing = EventStoreSQLXSqlite::table_columns(pool, table).await?;
+        let mut added = 0;
+        for (column, definition) in columns {
+            if !existing.contains_key(*column) {
+                let add_column = format!(
+                    "alter table {0} add column {1} {2}",
+                    table, column, definition
+                );
+                let _ = sqlx::query(&add_column).execute(pool).await?;
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    async fn upgrade_stream_table(pool: &SqlitePool, streams_name: &str) -> Result<usize> {
+        // Tables created by earlier versions get the columns added since
+        EventStoreSQLXSqlite::add_missing_columns(
+            pool,
+            streams_name,
+            &[
+                ("state", "varchar(16) not null default 'active'"),
+                ("truncate_before", "integer not null default 0"),
+                ("max_count", "integer default null"),
+                ("max_age", "integer default null"),
+                ("deleted_before", "integer not null default 0"),
+            ],
+        )
+        .await
+    }
+
+    async fn upgrade_event_table(
+        pool: &SqlitePool,
+        events_name: &str,
+        streams_name: &str,
+    ) -> Result<usize> {
+        // Tables created by earlier versions get the columns added since
+        let added = EventStoreSQLXSqlite::add_missing_columns(
+            pool,
+            events_name,
+            &[
+                ("position", "integer default null"),
+                ("data_bin", "blob default null"),
+                ("metadata_bin", "blob default null"),
+                ("codec", "varchar(16) not null default 'json'"),
+                ("schema_version", "integer not null default 1"),
+            ],
+        )
+        .await?;
+        // Events stored before positions existed get them in the order they were written
+        let backfill_position = format!(
+            "update {0} set position = p.position from (\
+                    select id, (select coalesce(max(position), 0) from {0}) \
+                    + row_number() over (order by rowid) as position \
+                    from {0} where position is null) as p \
+                    where {0}.id = p.id",
+            events_name
+        );
+        let _ = sqlx::query(&backfill_position).execute(pool).await?;
+
+        // SQLite can't change the constraints of a column, so a table with a required `data`,
+        // which binary codecs leave null, or an optional `position` is copied to a new one
+        let columns = EventStoreSQLXSqlite::table_columns(pool, events_name).await?;
+        if !columns["data"] && columns["position"] {
+            return Ok(added);
+        }
+        let upgraded_name = format!("{}_upgraded", events_name);
+        let copy_events = format!(
+            "insert into {1} (id, correlation_id, causation_id, stream_id, version, position, \
+                    name, data, metadata, data_bin, metadata_bin, codec, schema_version, \
+                    created_utc) \
+                    select id, correlation_id, causation_id, stream_id, version, position, \
+                    name, data, metadata, data_bin, metadata_bin, codec, schema_version, \
+                    created_utc from {0}",
+            events_name, upgraded_name
+        );
+        let drop_table = format!("drop table {0}", events_name);
+        let rename_table = format!("alter table {1} rename to {0}", events_name, upgraded_name);
+
+        let mut tr = pool.begin().await?;
+        let _ = EventStoreSQLXSqlite::create_event_table(&mut *tr, &upgraded_name, streams_name)
+            .await?;
+        let _ = sqlx::query(&copy_events).execute(&mut *tr).await?;
+        let _ = sqlx::query(&drop_table).execute(&mut *tr).await?;
+        let _ = sqlx::query(&rename_table).execute(&mut *tr).await?;
+        tr.commit().await?;
+        Ok(added)
+    }
+
+    async fn create_position_table(
+        pool: &SqlitePool,
+        positions_name: &str,
+        events_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create table if not exists cs_positions_person (
+        //     id integer primary key default 1 check (id = 1),
+        // last_position integer not null
+        // );
+        // Single row holding the last handed out position, so positions of hard deleted
+        // events are never handed out again
+        let positions_create_table = format!(
+            "create table if not exists {0} (\
+                    id integer primary key default 1 check (id = 1),\
+                    last_position integer not null)",
+            positions_name
+        );
+        let seed_position = format!(
+            "insert or ignore into {0} (id, last_position) \
+                    select 1, coalesce(max(position), 0) from {1}",
+            positions_name, events_name
+        );
+
+        let _ = sqlx::query(&positions_create_table).execute(pool).await?;
+        let res = sqlx::query(&seed_position).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_stream_version_index(
+        pool: &SqlitePool,
+        events_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create unique index if not exists ux_cs_events_person_stream_version
+        // on cs_events_person (stream_id, version);
+        let create_index = format!(
+            "create unique index if not exists ux_{0}_stream_version \
+                    on {0} (stream_id, version)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_position_index(
+        pool: &SqlitePool,
+        events_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create unique index if not exists ux_cs_events_person_position
+        // on cs_events_person (position);
+        let create_index = format!(
+            "create unique index if not exists ux_{0}_position on {0} (position)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_name_index(pool: &SqlitePool, events_name: &str) -> Result<SqliteQueryResult> {
+        // create index if not exists ix_cs_events_person_name
+        // on cs_events_person (name, position);
+        let create_index = format!(
+            "create index if not exists ix_{0}_name on {0} (name, position)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
+    async fn create_created_utc_index(
+        pool: &SqlitePool,
+        events_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create index if not exists ix_cs_events_person_created_utc
+        // on cs_events_person (created_utc);
+        let create_index = format!(
+            "create index if not exists ix_{0}_created_utc on {0} (created_utc)",
+            events_name
+        );
+
+        let res = sqlx::query(&create_index).execute(pool).await?;
         Ok(res)
     }
 
@@ -93,7 +353,6 @@ impl EventStoreSQLXSqlite {
         //     end;
         //     $$ language 'plpgsql';"#;
 
-
         let create_trigger = format!(
             "create trigger if not exists update_{0} after update on {0} \
              begin \
@@ -107,20 +366,90 @@ impl EventStoreSQLXSqlite {
         Ok(res)
     }
 
+    async fn create_outbox_table(
+        pool: &SqlitePool,
+        outbox_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create table if not exists cs_outbox_person (
+        //     position integer primary key,
+        // event_id text not null,
+        // stream_id text not null,
+        // attempts integer not null default 0,
+        // last_error text default null,
+        // next_attempt_utc text not null,
+        // claimed_until text default null,
+        // delivered_utc text default null,
+        // failed_utc text default null,
+        // created_utc text default current_timestamp
+        // );
+        // Rows still to be delivered are found through a partial index
+        let outbox_create_table = format!(
+            "create table if not exists {0} (\
+                    position integer primary key,\
+                    event_id text not null,\
+                    stream_id text not null,\
+                    attempts integer not null default 0,\
+                    last_error text default null,\
+                    next_attempt_utc text not null,\
+                    claimed_until text default null,\
+                    delivered_utc text default null,\
+                    failed_utc text default null,\
+                    created_utc text default (strftime('%Y-%m-%d %H:%M:%f', 'now')))",
+            outbox_name
+        );
+        let create_index = format!(
+            "create index if not exists ix_{0}_pending on {0} (next_attempt_utc) \
+                    where delivered_utc is null and failed_utc is null",
+            outbox_name
+        );
+
+        let _ = sqlx::query(&outbox_create_table).execute(pool).await?;
+        let res = sqlx::query(&create_index).execute(pool).await?;
+        Ok(res)
+    }
+
     pub async fn new(pool: &SqlitePool, name: &str) -> Result<EventStoreSQLXSqlite> {
+        EventStoreSQLXSqlite::new_with_codec(pool, name, Codec::Json).await
+    }
+
+    /// Same as `new`, writing payloads and metadata of new events with `codec`
+    pub async fn new_with_codec(
+        pool: &SqlitePool,
+        name: &str,
+        codec: Codec,
+    ) -> Result<EventStoreSQLXSqlite> {
         // Generate stream name
         let streams_name = format!("cs_streams_{}", name);
         // Generate name for event table
         let events_name = format!("cs_events_{}", name);
+        // Generate name for position table
+        let positions_name = format!("cs_positions_{}", name);
+        // Generate name for outbox table
+        let outbox_name = format!("cs_outbox_{}", name);
 
         let _ = EventStoreSQLXSqlite::create_stream_table(pool, &streams_name).await?;
+        let _ = EventStoreSQLXSqlite::upgrade_stream_table(pool, &streams_name).await?;
         let _ = EventStoreSQLXSqlite::create_event_table(pool, &events_name, &streams_name).await?;
+        let _ =
+            EventStoreSQLXSqlite::upgrade_event_table(pool, &events_name, &streams_name).await?;
+        let _ = EventStoreSQLXSqlite::create_position_table(pool, &positions_name, &events_name)
+            .await?;
+        let _ = EventStoreSQLXSqlite::create_stream_version_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXSqlite::create_position_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXSqlite::create_name_index(pool, &events_name).await?;
+        let _ = EventStoreSQLXSqlite::create_created_utc_index(pool, &events_name).await?;
         let _ = EventStoreSQLXSqlite::create_timestamp_trigger(pool, &streams_name).await?;
+        let _ = EventStoreSQLXSqlite::create_outbox_table(pool, &outbox_name).await?;
 
         Ok(EventStoreSQLXSqlite {
             pool: pool.clone(),
             streams_table_name: streams_name,
             events_table_name: events_name,
+            positions_table_name: positions_name,
+            outbox_table_name: outbox_name,
+            codec,
+            upcasters: Upcasters::new(),
+            outbox: false,
         })
     }
 }
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::SqliteQueryResult;
+
+#[derive(Debug, Clone)]
+pub struct KeyStoreSQLXSqlite {
+    pool: SqlitePool,
+    table_name: String,
+}
+
+impl KeyStoreSQLXSqlite {
+    pub fn pool(&self) -> SqlitePool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_key_table(pool: &SqlitePool, table_name: &str) -> Result<SqliteQueryResult> {
+        // create table if not exists cs_keys_person (
+        //     subject text not null primary key,
+        // key blob,
+        // created_utc date default (datetime('now','utc')),
+        // shredded_utc date
+        // );
+        let key_create_table = format!(
+            "create table if not exists {0} (\
+                    subject text not null primary key,\
+                    key blob,\
+                    created_utc date default (datetime('now','utc')),\
+                    shredded_utc date)",
+            table_name
+        );
+
+        let res = sqlx::query(&key_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &SqlitePool, name: &str) -> Result<KeyStoreSQLXSqlite> {
+        let key_name = format!("cs_keys_{}", name);
+        let _ = KeyStoreSQLXSqlite::create_key_table(pool, &key_name).await?;
+
+        Ok(KeyStoreSQLXSqlite {
+            pool: pool.clone(),
+            table_name: key_name,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+use crate::db_types::{db_timestamp, DBEventData, DBOutboxFailure};
+use crate::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use chrono::Utc;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::outbox_sink::OutboxSink;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::outbox::{OutboxDispatch, OutboxFailure, OutboxSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/**
+Publishes the events a store wrote to its outbox, see `EventStoreSQLXSqlite::with_outbox`.
+SQLite can't lock single rows, so claimed rows are hidden from other dispatchers for
+`claim_timeout` instead. Events are published at least once: a batch interrupted before
+it is marked delivered is published again once its claim timed out.
+Rows whose event was removed before it was published are failed, see `get_failed`.
+*/
+#[derive(Debug, Clone)]
+pub struct OutboxDispatcherSQLXSqlite {
+    store: EventStoreSQLXSqlite,
+    settings: OutboxSettings,
+}
+
+impl OutboxDispatcherSQLXSqlite {
+    pub fn new(store: &EventStoreSQLXSqlite, settings: OutboxSettings) -> Self {
+        OutboxDispatcherSQLXSqlite {
+            store: store.clone(),
+            settings,
+        }
+    }
+
+    /// Claims a batch of pending rows, publishes their events to `sink` and records the outcome
+    pub async fn dispatch<Payload, Meta, S>(&self, sink: &S) -> Result<OutboxDispatch>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        S: OutboxSink<Payload, Meta, EventVersion> + Sync + ?Sized,
+    {
+        let now = Utc::now();
+        let claim_timeout = chrono::Duration::from_std(self.settings.claim_timeout)
+            .map_err(EventStoreError::backend)?;
+        // A single statement, so no other dispatcher can claim the same rows in between
+        let claim_rows = format!(
+            "update {0} set claimed_until = ? where position in (\
+                    select position from {0} \
+                    where delivered_utc is null and failed_utc is null \
+                    and next_attempt_utc <= ? \
+                    and (claimed_until is null or claimed_until <= ?) \
+                    order by position limit ?) \
+                    returning position, attempts",
+            self.store.outbox_table_name()
+        );
+        let mut claimed: Vec<(i64, i32)> = sqlx::query_as(&claim_rows)
+            .bind(db_timestamp(&(now + claim_timeout)))
+            .bind(db_timestamp(&now))
+            .bind(db_timestamp(&now))
+            .bind(self.settings.batch_size as i64)
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        if claimed.is_empty() {
+            return Ok(OutboxDispatch::default());
+        }
+        claimed.sort_unstable();
+
+        let placeholders = vec!["?"; claimed.len()].join(", ");
+        let events_query = format!(
+            "select * from {0} where position in ({1})",
+            self.store.events_table_name(),
+            placeholders
+        );
+        let mut events = sqlx::query_as::<_, DBEventData>(&events_query);
+        for (position, _) in &claimed {
+            events = events.bind(position);
+        }
+        let events: HashMap<i64, DBEventData> = events
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?
+            .into_iter()
+            .map(|e| (e.position, e))
+            .collect();
+
+        // Rows out of attempts are left failed, see `get_failed`
+        let record_failure = format!(
+            "update {0} set attempts = attempts + 1, last_error = ?, \
+                    next_attempt_utc = ?, claimed_until = null, \
+                    failed_utc = case when attempts + 1 >= ? then ? end \
+                    where position = ?",
+            self.store.outbox_table_name()
+        );
+        let record_removed = format!(
+            "update {0} set attempts = attempts + 1, last_error = ?, claimed_until = null, \
+                    failed_utc = ? where position = ?",
+            self.store.outbox_table_name()
+        );
+        let mark_delivered = format!(
+            "update {0} set delivered_utc = ?, claimed_until = null where position = ?",
+            self.store.outbox_table_name()
+        );
+        let mut dispatched = OutboxDispatch::default();
+        for (position, attempts) in claimed {
+            // Events removed since they were appended, by a hard delete or a scavenge,
+            // can't be published anymore, their rows are failed right away
+            let published = match events.get(&position) {
+                Some(d) => match self.store.db_event_to_event_read(d) {
+                    Ok(event) => sink.publish(&event).await,
+                    Err(e) => Err(e),
+                },
+                None => {
+                    dispatched.failed += 1;
+                    let _ = sqlx::query(&record_removed)
+                        .bind(format!(
+                            "Event at position {} was removed before it was published",
+                            position
+                        ))
+                        .bind(db_timestamp(&Utc::now()))
+                        .bind(position)
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                    continue;
+                }
+            };
+            match published {
+                Ok(()) => {
+                    dispatched.delivered += 1;
+                    let _ = sqlx::query(&mark_delivered)
+                        .bind(db_timestamp(&Utc::now()))
+                        .bind(position)
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                }
+                Err(e) => {
+                    dispatched.failed += 1;
+                    let retry_after =
+                        chrono::Duration::from_std(self.settings.retry_after(attempts as u32 + 1))
+                            .map_err(EventStoreError::backend)?;
+                    let now = Utc::now();
+                    let _ = sqlx::query(&record_failure)
+                        .bind(e.to_string())
+                        .bind(db_timestamp(&(now + retry_after)))
+                        .bind(self.settings.max_attempts as i32)
+                        .bind(db_timestamp(&now))
+                        .bind(position)
+                        .execute(&self.store.pool())
+                        .await
+                        .map_err(EventStoreError::backend)?;
+                }
+            }
+        }
+        Ok(dispatched)
+    }
+
+    /// Rows publishing was given up for, oldest first
+    pub async fn get_failed(&self) -> Result<Vec<OutboxFailure>> {
+        let failed_query = format!(
+            "select position, event_id, stream_id, attempts, last_error, failed_utc \
+                    from {0} where failed_utc is not null order by position",
+            self.store.outbox_table_name()
+        );
+        let failed = sqlx::query_as::<_, DBOutboxFailure>(&failed_query)
+            .fetch_all(&self.store.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(failed.into_iter().map(OutboxFailure::from).collect())
+    }
+
+    /// Keeps dispatching until the outbox can't be read, waiting `poll_interval` whenever
+    /// nothing is pending
+    pub async fn run<Payload, Meta, S>(&self, sink: &S) -> Result<()>
+    where
+        Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+        S: OutboxSink<Payload, Meta, EventVersion> + Sync + ?Sized,
+    {
+        loop {
+            let dispatched = self.dispatch(sink).await?;
+            if dispatched == OutboxDispatch::default() {
+                tokio::time::sleep(self.settings.poll_interval).await;
+            }
+        }
+    }
+}
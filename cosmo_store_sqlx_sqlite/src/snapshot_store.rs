@@ -0,0 +1,76 @@
+use crate::db_types::DBSnapshotData;
+use crate::snapshot_store_sqlx_sqlite::SnapshotStoreSQLXSqlite;
+use async_trait::async_trait;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+
+fn db_snapshot_to_snapshot<State>(d: DBSnapshotData) -> Result<Snapshot<State, EventVersion>>
+where
+    State: for<'de> Deserialize<'de>,
+{
+    Ok(Snapshot {
+        stream_id: d.stream_id,
+        version: EventVersion::new(d.version),
+        state: serde_json::from_value(d.state).map_err(EventStoreError::serialization)?,
+        created_utc: d.created_utc,
+    })
+}
+
+#[async_trait]
+impl<State> SnapshotStore<State, EventVersion> for SnapshotStoreSQLXSqlite
+where
+    State: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    async fn save_snapshot(
+        &self,
+        stream_id: &str,
+        version: &EventVersion,
+        state: &State,
+    ) -> Result<Snapshot<State, EventVersion>> {
+        let upsert_snapshot = format!(
+            "insert into {0} (stream_id, version, state) values (?, ?, ?) \
+            on conflict (stream_id, version) \
+            do update set state = excluded.state, created_utc = datetime('now', 'utc') \
+            returning *",
+            self.table_name()
+        );
+        let data = serde_json::to_value(state).map_err(EventStoreError::serialization)?;
+        let res = sqlx::query_as::<_, DBSnapshotData>(&upsert_snapshot)
+            .bind(stream_id)
+            .bind(version.0)
+            .bind(data)
+            .fetch_one(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        db_snapshot_to_snapshot(res)
+    }
+
+    async fn get_latest_snapshot(
+        &self,
+        stream_id: &str,
+    ) -> Result<Option<Snapshot<State, EventVersion>>> {
+        let latest_snapshot = format!(
+            "select * from {0} where stream_id=? order by version desc limit 1",
+            self.table_name()
+        );
+        let res = sqlx::query_as::<_, DBSnapshotData>(&latest_snapshot)
+            .bind(stream_id)
+            .fetch_optional(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        res.map(db_snapshot_to_snapshot).transpose()
+    }
+
+    async fn delete_snapshots(&self, stream_id: &str) -> Result<()> {
+        let delete_snapshots = format!("delete from {0} where stream_id=?", self.table_name());
+        let _ = sqlx::query(&delete_snapshots)
+            .bind(stream_id)
+            .execute(&self.pool())
+            .await
+            .map_err(EventStoreError::backend)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::SqliteQueryResult;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotStoreSQLXSqlite {
+    pool: SqlitePool,
+    table_name: String,
+}
+
+impl SnapshotStoreSQLXSqlite {
+    pub fn pool(&self) -> SqlitePool {
+        self.pool.clone()
+    }
+
+    pub fn table_name(&self) -> String {
+        self.table_name.to_string()
+    }
+
+    async fn create_snapshot_table(
+        pool: &SqlitePool,
+        table_name: &str,
+    ) -> Result<SqliteQueryResult> {
+        // create table if not exists cs_snapshots_person (
+        //     stream_id text not null,
+        // version integer not null,
+        // state json not null,
+        // created_utc date default (datetime('now','utc')),
+        // primary key (stream_id, version)
+        // );
+        let snapshot_create_table = format!(
+            "create table if not exists {0} (\
+                    stream_id text not null,\
+                    version integer not null,\
+                    state json not null,\
+                    created_utc date default (datetime('now','utc')),\
+                    primary key (stream_id, version))",
+            table_name
+        );
+
+        let res = sqlx::query(&snapshot_create_table).execute(pool).await?;
+        Ok(res)
+    }
+
+    pub async fn new(pool: &SqlitePool, name: &str) -> Result<SnapshotStoreSQLXSqlite> {
+        let snapshot_name = format!("cs_snapshots_{}", name);
+        let _ = SnapshotStoreSQLXSqlite::create_snapshot_table(pool, &snapshot_name).await?;
+
+        Ok(SnapshotStoreSQLXSqlite {
+            pool: pool.clone(),
+            table_name: snapshot_name,
+        })
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store_sqlx_sqlite::checkpoint_store_sqlx_sqlite::CheckpointStoreSQLXSqlite;
+use cosmo_store_tests::checkpoint_store_basic_tests as cs;
+use futures::FutureExt;
+use sqlx::sqlite::SqlitePoolOptions;
+
+const CONN_BASE: &str = "sqlite::memory:";
+
+async fn get_store() -> CheckpointStoreSQLXSqlite {
+    let conn_str = CONN_BASE.to_string();
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&conn_str)
+        .await
+        .unwrap();
+    CheckpointStoreSQLXSqlite::new(&pool, "person")
+        .await
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn save_checkpoint() {
+    let result = std::panic::AssertUnwindSafe(cs::save_checkpoint(&get_store().await, |res| {
+        assert_eq!(res, Some(7));
+    }))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_checkpoint_of_unknown_projection() {
+    let result = std::panic::AssertUnwindSafe(cs::get_checkpoint_of_unknown_projection(
+        &get_store().await,
+        |res| {
+            assert!(res.is_none());
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn checkpoints_are_kept_per_projection() {
+    let result = std::panic::AssertUnwindSafe(cs::checkpoints_are_kept_per_projection(
+        &get_store().await,
+        |people, orders| {
+            assert_eq!(people, Some(3));
+            assert_eq!(orders, Some(5));
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn reset_checkpoint() {
+    let result = std::panic::AssertUnwindSafe(cs::reset_checkpoint(
+        &get_store().await,
+        |people, orders| {
+            assert!(people.is_none());
+            assert_eq!(orders, Some(5));
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
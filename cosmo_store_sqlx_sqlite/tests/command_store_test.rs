@@ -2,12 +2,16 @@
 #[macro_use]
 extern crate claim;
 
+use chrono::Utc;
 use cosmo_store::traits::command_store::CommandStore;
+use cosmo_store::types::command_status::CommandStatus;
 use cosmo_store::types::command_write::CommandWrite;
+use cosmo_store::types::event_store_error::EventStoreError;
 use cosmo_store_sqlx_sqlite::command_store_sqlx_sqlite::CommandStoreSQLXSqlite;
 use futures::FutureExt;
 use serde::{Deserialize, Serialize};
 use sqlx::sqlite::SqlitePoolOptions;
+use std::time::Duration;
 use uuid::Uuid;
 
 const CONN_BASE: &str = "sqlite::memory:";
@@ -39,21 +43,38 @@ async fn get_store<Payload>() -> impl CommandStore<Payload>
 where
     Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
 {
-    let conn_str = format!("{}", CONN_BASE);
-    let pool = SqlitePoolOptions::new().connect(&conn_str).await.unwrap();
+    let conn_str = CONN_BASE.to_string();
+    // In-memory database lives per connection, so the store keeps a single one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&conn_str)
+        .await
+        .unwrap();
     let store = CommandStoreSQLXSqlite::new(&pool, "person").await.unwrap();
     store
 }
 
-fn get_name() -> String {
-    Uuid::new_v4().as_simple().to_string()
-}
+// fn get_name() -> String {
+//     Uuid::new_v4().as_simple().to_string()
+// }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct DummyCommand {
     text: String,
 }
 
+fn get_command(id: Uuid, correlation_id: Uuid, causation_id: Uuid) -> CommandWrite<DummyCommand> {
+    CommandWrite {
+        id,
+        correlation_id,
+        causation_id,
+        data: DummyCommand {
+            text: format!("Do {}", id),
+        },
+        name: "some_command".to_string(),
+    }
+}
+
 #[actix_rt::test]
 async fn append_command() {
     setup().await;
@@ -73,5 +94,204 @@ async fn append_command() {
 
     teardown().await;
 
+    assert_ok!(assert_ok!(result));
+}
+
+#[actix_rt::test]
+async fn get_command_by_id() {
+    setup().await;
+    let store = get_store().await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let id = Uuid::new_v4();
+        let command = get_command(id, id, id);
+        store.append_command(&command).await.unwrap();
+
+        let res = CommandStore::<DummyCommand>::get_command(&store, &id)
+            .await
+            .unwrap();
+        assert_eq!(res.id, id);
+        assert_eq!(res.name, command.name);
+        assert_eq!(res.data.text, command.data.text);
+
+        let unknown = Uuid::new_v4();
+        let res = CommandStore::<DummyCommand>::get_command(&store, &unknown).await;
+        assert!(matches!(res, Err(EventStoreError::CommandNotFound(id)) if id == unknown));
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_commands_by_correlation_and_causation_id() {
+    setup().await;
+    let store = get_store().await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let (first, second, third, other) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        for command in [
+            get_command(first, first, first),
+            get_command(second, first, first),
+            get_command(third, first, second),
+            get_command(other, other, other),
+        ] {
+            store.append_command(&command).await.unwrap();
+            // Keeps creation times apart, commands are ordered by them
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let conversation: Vec<Uuid> = store
+            .get_commands_by_correlation_id(&first)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(conversation, vec![first, second, third]);
+
+        let caused: Vec<Uuid> = store
+            .get_commands_by_causation_id(&first)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(caused, vec![first, second]);
+
+        let caused = store.get_commands_by_causation_id(&second).await.unwrap();
+        assert_eq!(caused.len(), 1);
+        assert_eq!(caused[0].id, third);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_commands_in_time_range() {
+    setup().await;
+    let store = get_store().await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let from = Utc::now();
+        let (before, inside) = (Uuid::new_v4(), Uuid::new_v4());
+        store
+            .append_command(&get_command(before, before, before))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let start = Utc::now();
+        store
+            .append_command(&get_command(inside, inside, inside))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let end = Utc::now();
+
+        let all: Vec<Uuid> =
+            CommandStore::<DummyCommand>::get_commands_in_time_range(&store, &from, &end)
+                .await
+                .unwrap()
+                .iter()
+                .map(|c| c.id)
+                .collect();
+        assert_eq!(all, vec![before, inside]);
+
+        let res = CommandStore::<DummyCommand>::get_commands_in_time_range(&store, &start, &end)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].id, inside);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn receive_command_returns_recorded_outcome() {
+    setup().await;
+    let store = get_store().await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let id = Uuid::new_v4();
+        let command = get_command(id, id, id);
+        let received = store.receive_command(&command).await.unwrap();
+        assert_eq!(received.status, CommandStatus::Received);
+
+        let event_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+        for status in [
+            CommandStatus::Processing,
+            CommandStatus::Succeeded {
+                event_ids: event_ids.clone(),
+            },
+        ] {
+            let res = store.transition_command(&id, &status).await.unwrap();
+            assert_eq!(res.status, status);
+        }
+
+        // Delivered again, the command is answered from its recorded outcome
+        let redelivered = store.receive_command(&command).await.unwrap();
+        assert_eq!(redelivered.status, CommandStatus::Succeeded { event_ids });
+        assert_eq!(redelivered.data.text, command.data.text);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn invalid_command_transitions_are_rejected() {
+    setup().await;
+    let store = get_store().await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let id = Uuid::new_v4();
+        let _ = store
+            .receive_command(&get_command(id, id, id))
+            .await
+            .unwrap();
+        let failed = CommandStatus::Failed {
+            reason: String::from("Out of stock"),
+        };
+        let res = store.transition_command(&id, &failed).await.unwrap();
+        assert_eq!(res.status, failed);
+
+        let res = store
+            .transition_command(&id, &CommandStatus::Processing)
+            .await;
+        assert!(matches!(
+            res,
+            Err(EventStoreError::InvalidCommandTransition { from, to, .. })
+                if from == "failed" && to == "processing"
+        ));
+        let res = CommandStore::<DummyCommand>::get_command(&store, &id)
+            .await
+            .unwrap();
+        assert_eq!(res.status, failed);
+
+        let res = store
+            .transition_command(&Uuid::new_v4(), &CommandStatus::Processing)
+            .await;
+        assert!(matches!(res, Err(EventStoreError::CommandNotFound(_))));
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
     assert_ok!(result);
 }
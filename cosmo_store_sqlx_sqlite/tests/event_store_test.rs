@@ -2,19 +2,23 @@
 #[macro_use]
 extern crate claim;
 
-use cosmo_store::common::i64_event_version::EventVersion;
+use cosmo_store::common::event_version::EventVersion;
 use cosmo_store::traits::event_store::EventStore;
 use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::EventOrder;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::StreamState;
 use cosmo_store::types::expected_version::ExpectedVersion;
 use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
 use cosmo_store_tests::event_store_basic_tests as bt;
 use cosmo_store_tests::event_store_basic_tests::{check_position, Meta, Payload};
 
-use futures::{FutureExt, StreamExt};
+use futures::FutureExt;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::panic;
+use std::path::PathBuf;
 use uuid::Uuid;
 
 const CONN_BASE: &str = "sqlite::memory:";
@@ -47,15 +51,39 @@ where
     Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
     Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
 {
-    let conn_str = format!("{}", CONN_BASE);
+    let conn_str = CONN_BASE.to_string();
     let pool = SqlitePoolOptions::new().connect(&conn_str).await.unwrap();
     let store = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
     store
 }
 
-// fn get_name() -> String {
-//     Uuid::new_v4().as_simple().to_string()
-// }
+// In-memory database lives per connection, concurrent writers need a shared file
+fn get_file_name() -> PathBuf {
+    std::env::temp_dir().join(format!("cosmo_store_{}.db", Uuid::new_v4().as_simple()))
+}
+
+async fn get_file_store<Payload, Meta>(
+    file: &PathBuf,
+) -> impl EventStore<Payload, Meta, EventVersion>
+where
+    Payload: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+    Meta: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    let options = SqliteConnectOptions::new()
+        .filename(file)
+        .create_if_missing(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(8)
+        .connect_with(options)
+        .await
+        .unwrap();
+    let store = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+    store
+}
+
+fn remove_file_store(file: &PathBuf) {
+    let _ = std::fs::remove_file(file);
+}
 
 // TODO: implement to improve test code. not working as of now
 // async fn run_test<T: Sized>(test: Box<T>) -> ()
@@ -84,8 +112,7 @@ fn are_ascending(items: Vec<EventRead<Payload, Meta, EventVersion>>) {
 
 #[actix_rt::test]
 async fn store_setup() {
-    let store = get_store::<Payload, Meta>().await;
-    assert!(true);
+    let _store = get_store::<Payload, Meta>().await;
 }
 
 #[actix_rt::test]
@@ -214,7 +241,14 @@ async fn fails_to_append_to_existing_version() {
         &get_store().await,
         EventVersion::new(1_i64),
         |res| {
-            assert_err!(res);
+            assert!(matches!(
+                res,
+                Err(EventStoreError::WrongExpectedVersion {
+                    expected: 1,
+                    actual: 2,
+                    ..
+                })
+            ));
         },
     ))
     .catch_unwind()
@@ -231,7 +265,10 @@ async fn fails_to_append_to_existing_stream_if_is_not_expected_to_exist() {
         bt::fails_to_append_to_existing_stream_if_is_not_expected_to_exist(
             &get_store().await,
             |res| {
-                assert_err!(res);
+                assert!(matches!(
+                    res,
+                    Err(EventStoreError::WrongExpectedVersion { .. })
+                ));
             },
         ),
     )
@@ -299,3 +336,593 @@ async fn can_read_events_by_correlation_id() {
 
     assert_ok!(result);
 }
+
+fn single_winner(
+    results: Vec<Result<Vec<EventRead<Payload, Meta, EventVersion>>>>,
+    events: Vec<EventRead<Payload, Meta, EventVersion>>,
+    expected_events: usize,
+) {
+    let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+    assert_eq!(ok.len(), 1);
+    assert!(err
+        .iter()
+        .all(|r| matches!(r, Err(EventStoreError::WrongExpectedVersion { .. }))));
+    assert_eq!(events.len(), expected_events);
+    let versions: Vec<i64> = events.iter().map(|x| x.version.0).unique().collect();
+    assert_eq!(versions, (1..=expected_events as i64).collect::<Vec<i64>>());
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_causation_id() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(
+        bt::can_read_events_by_causation_id::<EventVersion, _>(&get_store().await, |events| {
+            let names: Vec<String> = events.iter().map(|x| x.name.clone()).collect();
+            let expected: Vec<String> = (1..=9).map(|i| format!("Created_{}", i)).collect();
+            assert_eq!(names, expected);
+        }),
+    )
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_same_expected_version() {
+    let file = get_file_name();
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_with_same_expected_version(
+        &get_file_store(&file).await,
+        EventVersion::new(2_i64),
+        10,
+        |results, events| single_winner(results, events, 4),
+    ))
+    .catch_unwind()
+    .await;
+    remove_file_store(&file);
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_to_new_stream() {
+    let file = get_file_name();
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_to_new_stream(
+        &get_file_store(&file).await,
+        10,
+        |results, events| single_winner(results, events, 3),
+    ))
+    .catch_unwind()
+    .await;
+    remove_file_store(&file);
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_with_any_version() {
+    let file = get_file_name();
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_with_any_version(
+        &get_file_store(&file).await,
+        10,
+        |results, events| {
+            assert!(results.iter().all(|r| r.is_ok()));
+            assert_eq!(events.len(), 30);
+            are_ascending(events);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    remove_file_store(&file);
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_writers_of_same_events() {
+    let file = get_file_name();
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_writers_of_same_events(
+        &get_file_store(&file).await,
+        10,
+        |results| {
+            let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+            assert_eq!(ok.len(), 1);
+            assert!(err
+                .iter()
+                .all(|r| matches!(r, Err(EventStoreError::DuplicateEvents { .. }))));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    remove_file_store(&file);
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_all_events_from_position() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_all_events_from_position(
+        &get_store().await,
+        4,
+        |pages| {
+            assert!(pages.iter().all(|p| p.len() <= 4));
+            let positions: Vec<i64> = pages.iter().flatten().map(|x| x.position).collect();
+            assert_eq!(positions, (1..=27).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_events_from_version() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_events_from_version(
+        &get_store().await,
+        EventVersion::new(501),
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (501..=1000).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_events_by_correlation_id() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_events_by_correlation_id(
+        &get_store().await,
+        |res| {
+            assert_eq!(res.len(), 15);
+            assert!(res.windows(2).all(|w| w[0].position < w[1].position));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_stream_streams_with_filter() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_stream_streams_with_filter(
+        &get_store().await,
+        |res| {
+            let mut ids: Vec<String> = res.into_iter().map(|x| x.id).collect();
+            ids.sort();
+            assert_eq!(ids.len(), 2);
+            assert!(ids[0].ends_with("_first"));
+            assert!(ids[1].ends_with("_second"));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn stream_filters_match_wildcards_literally() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::stream_filters_match_wildcards_literally(
+        &get_store().await,
+        |matched| {
+            assert_eq!(matched, vec![vec!["_1"], vec!["%1"], vec!["\\1"]]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_forward_in_pages() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_forward_in_pages(
+        &get_store().await,
+        10,
+        |pages| {
+            let sizes: Vec<usize> = pages.iter().map(|p| p.len()).collect();
+            assert_eq!(sizes, vec![10, 10, 5]);
+            let versions: Vec<i64> = pages.iter().flatten().map(|x| x.version.0).collect();
+            assert_eq!(versions, (1..=25).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_last_events_backwards() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_backwards(
+        &get_store().await,
+        None,
+        10,
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (16..=25).rev().collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_backwards_from_version() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_backwards(
+        &get_store().await,
+        Some(EventVersion::new(12)),
+        5,
+        |res| {
+            let versions: Vec<i64> = res.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (8..=12).rev().collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn soft_deleted_stream_can_be_recreated() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::soft_deleted_stream_can_be_recreated(
+        &get_store().await,
+        |deleted, events| {
+            assert_eq!(deleted.len(), 1);
+            assert_eq!(deleted[0].state, StreamState::Deleted);
+            assert_eq!(deleted[0].last_version.0, 3);
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![4, 5]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn deleted_events_are_hidden_from_all_reads() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::deleted_events_are_hidden_from_all_reads(
+        &get_store().await,
+        |correlated, all| {
+            assert_eq!(correlated.len(), 2);
+            assert_eq!(all.len(), 2);
+            let ids = |events: &Vec<EventRead<Payload, Meta, EventVersion>>| {
+                events.iter().map(|x| x.id).collect::<Vec<Uuid>>()
+            };
+            assert_eq!(ids(&correlated), ids(&all));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn hard_deleted_stream_is_removed() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::hard_deleted_stream_is_removed(
+        &get_store().await,
+        |last_position, recreated| {
+            assert_eq!(recreated[0].version.0, 1);
+            assert!(recreated[0].position > last_position);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn tombstoned_stream_rejects_appends() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::tombstoned_stream_rejects_appends(
+        &get_store().await,
+        |appended, deleted, listed| {
+            assert!(matches!(
+                appended,
+                Err(EventStoreError::StreamTombstoned(_))
+            ));
+            assert!(matches!(deleted, Err(EventStoreError::StreamTombstoned(_))));
+            assert_eq!(listed.len(), 1);
+            assert_eq!(listed[0].state, StreamState::Tombstoned);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn max_count_hides_older_events() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::max_count_hides_older_events(
+        &get_store().await,
+        |stream, events| {
+            assert_eq!(stream.metadata.max_count, Some(3));
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![8, 9, 10]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn truncate_before_hides_earlier_events() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::truncate_before_hides_earlier_events(
+        &get_store().await,
+        EventVersion::new(6),
+        |events| {
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, (6..=10).collect::<Vec<i64>>());
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn deleted_events_stay_hidden_after_metadata_change() {
+    setup().await;
+    let result =
+        std::panic::AssertUnwindSafe(bt::deleted_events_stay_hidden_after_metadata_change(
+            &get_store().await,
+            EventVersion::new(2),
+            |stream, events| {
+                assert_eq!(stream.metadata.truncate_before, Some(EventVersion::new(2)));
+                let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+                assert_eq!(versions, vec![4, 5]);
+            },
+        ))
+        .catch_unwind()
+        .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn max_age_hides_expired_events() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::max_age_hides_expired_events(
+        &get_store().await,
+        |events| {
+            let versions: Vec<i64> = events.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![4, 5]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn scavenge_removes_hidden_events() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::scavenge_removes_hidden_events(
+        &get_store().await,
+        |removed, removed_again, remaining| {
+            assert_eq!(removed, 9);
+            assert_eq!(removed_again, 0);
+            let versions: Vec<i64> = remaining.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![8, 9, 10]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_position_order() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_by_name(
+        &get_store().await,
+        EventOrder::Position,
+        |single, multiple| {
+            assert!(single.iter().all(|x| x.name == "Created_2"));
+            let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![2, 5]);
+            let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![1, 3, 4, 6]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_by_name_in_time_order() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_by_name(
+        &get_store().await,
+        EventOrder::CreatedUtc,
+        |single, multiple| {
+            assert!(single.iter().all(|x| x.name == "Created_2"));
+            let positions: Vec<i64> = single.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![2, 5]);
+            let positions: Vec<i64> = multiple.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![1, 3, 4, 6]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn can_read_events_in_time_range() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::can_read_events_in_time_range(
+        &get_store().await,
+        |all, single| {
+            let positions: Vec<i64> = all.iter().map(|x| x.position).collect();
+            assert_eq!(positions, vec![3, 4, 5, 6]);
+            let versions: Vec<i64> = single.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![3, 4]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn appends_to_multiple_streams_atomically() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::appends_to_multiple_streams_atomically(
+        &get_store().await,
+        EventVersion::new(3),
+        |results, source, target| {
+            let counts: Vec<usize> = results.iter().map(|r| r.len()).collect();
+            assert_eq!(counts, vec![1, 2]);
+            assert!(results[1][0].position > results[0][0].position);
+            let versions: Vec<i64> = source.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2, 3]);
+            let versions: Vec<i64> = target.iter().map(|x| x.version.0).collect();
+            assert_eq!(versions, vec![1, 2]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn multi_stream_append_fails_as_a_whole() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::multi_stream_append_fails_as_a_whole(
+        &get_store().await,
+        EventVersion::new(2),
+        |result, source, target| {
+            assert!(matches!(
+                result,
+                Err(EventStoreError::WrongExpectedVersion { .. })
+            ));
+            assert_eq!(source.len(), 2);
+            assert!(matches!(target, Err(EventStoreError::StreamNotFound(_))));
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_multi_stream_appends() {
+    let file = get_file_name();
+    let result = std::panic::AssertUnwindSafe(bt::concurrent_multi_stream_appends(
+        &get_file_store(&file).await,
+        10,
+        |results, first, second| {
+            assert!(results.iter().all(|r| r.is_ok()));
+            assert_eq!(first.len(), 20);
+            assert_eq!(second.len(), 20);
+            are_ascending(first);
+            are_ascending(second);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    remove_file_store(&file);
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn retried_append_returns_original_events() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::retried_append_returns_original_events(
+        &get_store().await,
+        |first, retried, events| {
+            let keys: Vec<(Uuid, i64)> = first.iter().map(|x| (x.id, x.position)).collect();
+            let retried_keys: Vec<(Uuid, i64)> =
+                retried.iter().map(|x| (x.id, x.position)).collect();
+            assert_eq!(retried_keys, keys);
+            assert_eq!(events.len(), 3);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn overlapping_append_is_rejected() {
+    setup().await;
+    let result = std::panic::AssertUnwindSafe(bt::overlapping_append_is_rejected(
+        &get_store().await,
+        |result, events| {
+            assert!(matches!(
+                result,
+                Err(EventStoreError::DuplicateEvents { ids, .. }) if ids.len() == 1
+            ));
+            assert_eq!(events.len(), 2);
+        },
+    ))
+    .catch_unwind()
+    .await;
+    teardown().await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store_sqlx_sqlite::key_store_sqlx_sqlite::KeyStoreSQLXSqlite;
+use cosmo_store_tests::key_store_basic_tests as ks;
+use futures::FutureExt;
+use sqlx::sqlite::SqlitePoolOptions;
+
+const CONN_BASE: &str = "sqlite::memory:";
+
+async fn get_store() -> KeyStoreSQLXSqlite {
+    let conn_str = CONN_BASE.to_string();
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&conn_str)
+        .await
+        .unwrap();
+    KeyStoreSQLXSqlite::new(&pool, "person").await.unwrap()
+}
+
+#[actix_rt::test]
+async fn create_key() {
+    let result =
+        std::panic::AssertUnwindSafe(ks::create_key(&get_store().await, |created, res| {
+            assert_eq!(created, vec![1, 2, 3]);
+            assert_eq!(res, Some(vec![1, 2, 3]));
+        }))
+        .catch_unwind()
+        .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn create_key_keeps_existing_key() {
+    let result = std::panic::AssertUnwindSafe(ks::create_key_keeps_existing_key(
+        &get_store().await,
+        |res| {
+            assert_eq!(res, vec![1]);
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_key_of_unknown_subject() {
+    let result =
+        std::panic::AssertUnwindSafe(ks::get_key_of_unknown_subject(&get_store().await, |res| {
+            assert!(res.is_none());
+        }))
+        .catch_unwind()
+        .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn delete_key_shreds_subject() {
+    let result = std::panic::AssertUnwindSafe(ks::delete_key_shreds_subject(
+        &get_store().await,
+        |key, recreated| {
+            assert!(key.is_none());
+            assert!(matches!(
+                recreated,
+                Err(EventStoreError::SubjectShredded(_))
+            ));
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,348 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use async_trait::async_trait;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::outbox_sink::OutboxSink;
+use cosmo_store::types::delete_mode::DeleteMode;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::outbox::{OutboxDispatch, OutboxSettings};
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_sqlx_sqlite::outbox_dispatcher_sqlx_sqlite::OutboxDispatcherSQLXSqlite;
+use cosmo_store_tests::event_generator::{get_events, get_stream_id};
+use cosmo_store_tests::event_store_basic_tests::{Meta, Payload};
+use futures::FutureExt;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::panic;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "sqlite::memory:";
+
+// Publishes event names, failing the first `failures` publishes
+#[derive(Default)]
+struct RecordingSink {
+    failures: AtomicUsize,
+    published: Mutex<Vec<String>>,
+}
+
+impl RecordingSink {
+    fn failing(failures: usize) -> RecordingSink {
+        RecordingSink {
+            failures: AtomicUsize::new(failures),
+            published: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn published(&self) -> Vec<String> {
+        self.published.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl OutboxSink<Payload, Meta, EventVersion> for RecordingSink {
+    async fn publish(&self, event: &EventRead<Payload, Meta, EventVersion>) -> Result<()> {
+        let failures = self.failures.load(Ordering::SeqCst);
+        if failures > 0 {
+            self.failures.store(failures - 1, Ordering::SeqCst);
+            return Err(EventStoreError::backend("Broker unavailable"));
+        }
+        self.published.lock().unwrap().push(event.name.clone());
+        Ok(())
+    }
+}
+
+async fn setup() {
+    println!("Event Store will be initialized here...");
+}
+
+async fn teardown() {
+    println!("Event Store will be destroyed here...");
+}
+
+// In-memory database lives per connection, so every store shares a single one
+async fn get_pool() -> SqlitePool {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(CONN_BASE)
+        .await
+        .unwrap()
+}
+
+// Concurrent dispatchers need a shared file
+fn get_file_name() -> PathBuf {
+    std::env::temp_dir().join(format!("cosmo_store_{}.db", Uuid::new_v4().as_simple()))
+}
+
+fn get_settings() -> OutboxSettings {
+    OutboxSettings {
+        retry_delay: Duration::ZERO,
+        ..OutboxSettings::default()
+    }
+}
+
+async fn get_outbox_rows(pool: &SqlitePool) -> Vec<(i64, i32, Option<String>, bool)> {
+    sqlx::query_as(
+        "select position, attempts, last_error, delivered_utc is not null \
+        from cs_outbox_person order by position",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+#[actix_rt::test]
+async fn appends_write_outbox_rows() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let store = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+        let outbox = store.clone().with_outbox();
+        let stream_id = get_stream_id();
+        let _ = outbox
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+            .await
+            .unwrap();
+        // Stores without outbox and failed appends leave the outbox alone
+        let _ = store
+            .append_events(&get_stream_id(), &ExpectedVersion::Any, get_events(1..=2))
+            .await
+            .unwrap();
+        let res = outbox
+            .append_events(
+                &stream_id,
+                &ExpectedVersion::Exact(EventVersion::new(1)),
+                get_events(4..=4),
+            )
+            .await;
+        assert!(res.is_err());
+
+        let positions: Vec<i64> = get_outbox_rows(&pool).await.iter().map(|r| r.0).collect();
+        assert_eq!(positions, vec![1, 2, 3]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn dispatcher_retries_failed_events() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let store = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_outbox();
+        let _ = store
+            .append_events(&get_stream_id(), &ExpectedVersion::Any, get_events(1..=2))
+            .await
+            .unwrap();
+        let dispatcher = OutboxDispatcherSQLXSqlite::new(&store, get_settings());
+        let sink = RecordingSink::failing(2);
+
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(
+            dispatched,
+            OutboxDispatch {
+                delivered: 0,
+                failed: 2
+            }
+        );
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(
+            dispatched,
+            OutboxDispatch {
+                delivered: 2,
+                failed: 0
+            }
+        );
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(dispatched, OutboxDispatch::default());
+
+        assert_eq!(sink.published(), vec!["Created_1", "Created_2"]);
+        for (_, attempts, last_error, delivered) in get_outbox_rows(&pool).await {
+            assert_eq!(attempts, 1);
+            assert!(last_error.unwrap().contains("Broker unavailable"));
+            assert!(delivered);
+        }
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn dispatcher_gives_up_after_max_attempts() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let store = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_outbox();
+        let stream_id = get_stream_id();
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=1))
+            .await
+            .unwrap();
+        let settings = OutboxSettings {
+            max_attempts: 2,
+            ..get_settings()
+        };
+        let dispatcher = OutboxDispatcherSQLXSqlite::new(&store, settings);
+        let sink = RecordingSink::failing(usize::MAX);
+
+        for _ in 0..2 {
+            assert!(dispatcher.get_failed().await.unwrap().is_empty());
+            let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+            assert_eq!(
+                dispatched,
+                OutboxDispatch {
+                    delivered: 0,
+                    failed: 1
+                }
+            );
+        }
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(dispatched, OutboxDispatch::default());
+
+        let rows = get_outbox_rows(&pool).await;
+        assert_eq!(rows[0].1, 2);
+        assert!(!rows[0].3);
+        let failed = dispatcher.get_failed().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].position, 1);
+        assert_eq!(failed[0].stream_id, stream_id);
+        assert_eq!(failed[0].attempts, 2);
+        assert!(failed[0]
+            .last_error
+            .as_ref()
+            .unwrap()
+            .contains("Broker unavailable"));
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn dispatcher_fails_rows_of_removed_events() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let store = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_outbox();
+        let stream_id = get_stream_id();
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=2))
+            .await
+            .unwrap();
+        EventStore::<Payload, Meta, EventVersion>::delete_stream(
+            &store,
+            &stream_id,
+            DeleteMode::Hard,
+        )
+        .await
+        .unwrap();
+        let dispatcher = OutboxDispatcherSQLXSqlite::new(&store, get_settings());
+        let sink = RecordingSink::default();
+
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(
+            dispatched,
+            OutboxDispatch {
+                delivered: 0,
+                failed: 2
+            }
+        );
+        let dispatched = dispatcher.dispatch(&sink).await.unwrap();
+        assert_eq!(dispatched, OutboxDispatch::default());
+
+        assert!(sink.published().is_empty());
+        assert!(get_outbox_rows(&pool).await.iter().all(|r| !r.3));
+        let failed = dispatcher.get_failed().await.unwrap();
+        let positions: Vec<i64> = failed.iter().map(|f| f.position).collect();
+        assert_eq!(positions, vec![1, 2]);
+        assert!(failed[0]
+            .last_error
+            .as_ref()
+            .unwrap()
+            .contains("was removed before it was published"));
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn concurrent_dispatchers_deliver_each_event_once() {
+    setup().await;
+    let file = get_file_name();
+    let result = panic::AssertUnwindSafe(async {
+        let options = SqliteConnectOptions::new()
+            .filename(&file)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(8)
+            .connect_with(options)
+            .await
+            .unwrap();
+        let store = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_outbox();
+        let _ = store
+            .append_events(&get_stream_id(), &ExpectedVersion::Any, get_events(1..=20))
+            .await
+            .unwrap();
+        let settings = OutboxSettings {
+            batch_size: 3,
+            ..get_settings()
+        };
+        let sink = RecordingSink::default();
+        let dispatchers =
+            (0..2).map(|_| {
+                let dispatcher = OutboxDispatcherSQLXSqlite::new(&store, settings.clone());
+                let sink = &sink;
+                async move {
+                    while dispatcher.dispatch(sink).await.unwrap() != OutboxDispatch::default() {}
+                }
+            });
+        futures::future::join_all(dispatchers).await;
+
+        let mut published = sink.published();
+        published.sort();
+        let mut expected: Vec<String> = (1..=20).map(|i| format!("Created_{}", i)).collect();
+        expected.sort();
+        assert_eq!(published, expected);
+    })
+    .catch_unwind()
+    .await;
+
+    let _ = std::fs::remove_file(&file);
+    teardown().await;
+
+    assert_ok!(result);
+}
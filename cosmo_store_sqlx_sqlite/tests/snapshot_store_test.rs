@@ -0,0 +1,107 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store_sqlx_sqlite::snapshot_store_sqlx_sqlite::SnapshotStoreSQLXSqlite;
+use cosmo_store_tests::snapshot_store_basic_tests as st;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePoolOptions;
+
+const CONN_BASE: &str = "sqlite::memory:";
+
+async fn get_store<State>() -> impl SnapshotStore<State, EventVersion>
+where
+    State: Send + Sync + 'static + Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    let conn_str = CONN_BASE.to_string();
+    let pool = SqlitePoolOptions::new().connect(&conn_str).await.unwrap();
+    let store = SnapshotStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+    store
+}
+
+#[actix_rt::test]
+async fn save_snapshot() {
+    let result = std::panic::AssertUnwindSafe(st::save_snapshot(
+        &get_store().await,
+        EventVersion::new(3),
+        |res| {
+            assert_eq!(res.version.0, 3);
+            assert_eq!(res.state.name, "State 1");
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot() {
+    let result = std::panic::AssertUnwindSafe(st::get_latest_snapshot(
+        &get_store().await,
+        vec![
+            EventVersion::new(2),
+            EventVersion::new(7),
+            EventVersion::new(5),
+        ],
+        |res| {
+            let res = res.unwrap();
+            assert_eq!(res.version.0, 7);
+            assert_eq!(res.state.name, "State 1");
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn get_latest_snapshot_of_unknown_stream() {
+    let result = std::panic::AssertUnwindSafe(st::get_latest_snapshot_of_unknown_stream(
+        &get_store().await,
+        |res| {
+            assert!(res.is_none());
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn saving_same_version_replaces_snapshot() {
+    let result = std::panic::AssertUnwindSafe(st::saving_same_version_replaces_snapshot(
+        &get_store().await,
+        EventVersion::new(4),
+        |res| {
+            let res = res.unwrap();
+            assert_eq!(res.version.0, 4);
+            assert_eq!(res.state.name, "State 2");
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn delete_snapshots() {
+    let result = std::panic::AssertUnwindSafe(st::delete_snapshots(
+        &get_store().await,
+        vec![EventVersion::new(2), EventVersion::new(4)],
+        |deleted, other| {
+            assert!(deleted.is_none());
+            assert_eq!(other.unwrap().state.name, "State 100");
+        },
+    ))
+    .catch_unwind()
+    .await;
+
+    assert_ok!(result);
+}
@@ -0,0 +1,185 @@
+#[cfg(test)]
+#[macro_use]
+extern crate claim;
+
+use cosmo_store::common::upcast::Upcasters;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_store_error::Result;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_tests::event_generator::get_stream_id;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::panic;
+use uuid::Uuid;
+
+const CONN_BASE: &str = "sqlite::memory:";
+
+// Shape OrderPlaced events were first written in
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct OrderV1 {
+    item: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Order {
+    item: String,
+    quantity: u32,
+    note: String,
+}
+
+async fn setup() {
+    println!("Event Store will be initialized here...");
+}
+
+async fn teardown() {
+    println!("Event Store will be destroyed here...");
+}
+
+// In-memory database lives per connection, so every store shares a single one
+async fn get_pool() -> SqlitePool {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(CONN_BASE)
+        .await
+        .unwrap()
+}
+
+fn order_placed<T>(data: T) -> Vec<EventWrite<T, ()>> {
+    vec![EventWrite {
+        id: Uuid::new_v4(),
+        correlation_id: None,
+        causation_id: None,
+        name: String::from("OrderPlaced"),
+        data,
+        metadata: None,
+    }]
+}
+
+fn add_field(field: &'static str, value: Value) -> impl Fn(Value) -> Result<Value> {
+    move |mut v| {
+        v[field] = value.clone();
+        Ok(v)
+    }
+}
+
+#[actix_rt::test]
+async fn upcasts_events_written_in_older_shapes() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let stream_id = get_stream_id();
+        let v1 = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+        let _ = v1
+            .append_events(
+                &stream_id,
+                &ExpectedVersion::Any,
+                order_placed(OrderV1 {
+                    item: String::from("book"),
+                }),
+            )
+            .await
+            .unwrap();
+
+        let upcasters = Upcasters::new()
+            .register_json("OrderPlaced", 1, add_field("quantity", json!(1)))
+            .register_json("OrderPlaced", 2, add_field("note", json!("")));
+        let current = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_upcasters(upcasters);
+        let _ = current
+            .append_events(
+                &stream_id,
+                &ExpectedVersion::Any,
+                order_placed(Order {
+                    item: String::from("pen"),
+                    quantity: 2,
+                    note: String::from("gift"),
+                }),
+            )
+            .await
+            .unwrap();
+
+        let events = EventStore::<Order, (), _>::get_events(
+            &current,
+            &stream_id,
+            &EventsReadRange::AllEvents,
+        )
+        .await
+        .unwrap();
+        let orders: Vec<Order> = events.into_iter().map(|e| e.data).collect();
+        assert_eq!(
+            orders,
+            vec![
+                Order {
+                    item: String::from("book"),
+                    quantity: 1,
+                    note: String::new(),
+                },
+                Order {
+                    item: String::from("pen"),
+                    quantity: 2,
+                    note: String::from("gift"),
+                },
+            ]
+        );
+
+        let versions: Vec<i32> =
+            sqlx::query_scalar("select schema_version from cs_events_person order by position")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(versions, vec![1, 3]);
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
+
+#[actix_rt::test]
+async fn missing_upcaster_fails_read() {
+    setup().await;
+    let result = panic::AssertUnwindSafe(async {
+        let pool = get_pool().await;
+        let stream_id = get_stream_id();
+        let v1 = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+        let _ = v1
+            .append_events(
+                &stream_id,
+                &ExpectedVersion::Any,
+                order_placed(OrderV1 {
+                    item: String::from("book"),
+                }),
+            )
+            .await
+            .unwrap();
+
+        // Version 1 events have no way up to version 3
+        let upcasters = Upcasters::new().register_json("OrderPlaced", 2, Ok);
+        let current = EventStoreSQLXSqlite::new(&pool, "person")
+            .await
+            .unwrap()
+            .with_upcasters(upcasters);
+        let res = EventStore::<Order, (), _>::get_events(
+            &current,
+            &stream_id,
+            &EventsReadRange::AllEvents,
+        )
+        .await;
+        assert!(res.is_err());
+    })
+    .catch_unwind()
+    .await;
+
+    teardown().await;
+
+    assert_ok!(result);
+}
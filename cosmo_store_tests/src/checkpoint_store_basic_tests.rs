@@ -0,0 +1,42 @@
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+
+pub async fn save_checkpoint<F>(store: &dyn CheckpointStore, assert: F)
+where
+    F: FnOnce(Option<i64>),
+{
+    store.save_checkpoint("people", 3).await.unwrap();
+    store.save_checkpoint("people", 7).await.unwrap();
+    let res = store.get_checkpoint("people").await.unwrap();
+    assert(res)
+}
+
+pub async fn get_checkpoint_of_unknown_projection<F>(store: &dyn CheckpointStore, assert: F)
+where
+    F: FnOnce(Option<i64>),
+{
+    let res = store.get_checkpoint("people").await.unwrap();
+    assert(res)
+}
+
+pub async fn checkpoints_are_kept_per_projection<F>(store: &dyn CheckpointStore, assert: F)
+where
+    F: FnOnce(Option<i64>, Option<i64>),
+{
+    store.save_checkpoint("people", 3).await.unwrap();
+    store.save_checkpoint("orders", 5).await.unwrap();
+    let people = store.get_checkpoint("people").await.unwrap();
+    let orders = store.get_checkpoint("orders").await.unwrap();
+    assert(people, orders)
+}
+
+pub async fn reset_checkpoint<F>(store: &dyn CheckpointStore, assert: F)
+where
+    F: FnOnce(Option<i64>, Option<i64>),
+{
+    store.save_checkpoint("people", 3).await.unwrap();
+    store.save_checkpoint("orders", 5).await.unwrap();
+    store.reset_checkpoint("people").await.unwrap();
+    let people = store.get_checkpoint("people").await.unwrap();
+    let orders = store.get_checkpoint("orders").await.unwrap();
+    assert(people, orders)
+}
@@ -33,5 +33,5 @@ pub fn get_events(x: RangeInclusive<i32>) -> Vec<EventWrite<Payload, Meta>> {
 }
 
 pub fn get_stream_id() -> String {
-    format!("TestStream_{}", Uuid::new_v4().to_string())
+    format!("TestStream_{}", Uuid::new_v4())
 }
@@ -1,13 +1,22 @@
 use crate::event_generator::{get_event, get_events, get_stream_id};
-use anyhow::Result;
+use chrono::Utc;
 use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::version::Version;
+use cosmo_store::types::delete_mode::DeleteMode;
 use cosmo_store::types::event_read::EventRead;
-use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_read_range::{EventOrder, EventsReadRange, ReadDirection};
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
 use cosmo_store::types::event_stream::EventStream;
 use cosmo_store::types::event_write::EventWrite;
 use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_append::StreamAppend;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store::types::stream_read_filter::{DeletedStreams, StreamsReadFilter};
+use futures::future::join_all;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -22,7 +31,7 @@ where
     G: FnOnce() -> bool,
     V: Debug + Eq + PartialEq,
 {
-    assert_eq!(greater_than(), true);
+    assert!(greater_than());
     return_val
 }
 
@@ -348,3 +357,934 @@ pub async fn can_read_events_by_correlation_id<V, F>(
 
     assert(events)
 }
+
+// Events caused by the same command are appended to three streams in turns
+pub async fn can_read_events_by_causation_id<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let caus_id = Uuid::new_v4();
+    let stream_ids: Vec<String> = (1..=3).map(|_| get_stream_id()).collect();
+    for (i, event) in get_events(1..=9).into_iter().enumerate() {
+        let event = EventWrite {
+            causation_id: Some(caus_id),
+            ..event
+        };
+        store
+            .append_event(&stream_ids[i % 3], &ExpectedVersion::Any, &event)
+            .await
+            .unwrap();
+    }
+    store
+        .append_events(&stream_ids[0], &ExpectedVersion::Any, get_events(10..=12))
+        .await
+        .unwrap();
+
+    let events = store.get_events_by_causation_id(&caus_id).await.unwrap();
+
+    assert(events)
+}
+
+pub async fn concurrent_writers_with_same_expected_version<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    expected_version: V,
+    writers: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Result<Vec<EventRead<Payload, Meta, V>>>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq + Clone,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=1))
+        .await
+        .unwrap();
+
+    let expected = ExpectedVersion::Exact(expected_version);
+    let appends = (0..writers).map(|i| {
+        let events = get_events(i as i32 * 10..=(i as i32 * 10) + 2);
+        store.append_events(&stream_id, &expected, events)
+    });
+    let results = join_all(appends).await;
+
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(results, events)
+}
+
+pub async fn concurrent_writers_to_new_stream<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    writers: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Result<Vec<EventRead<Payload, Meta, V>>>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+
+    let appends = (0..writers).map(|i| {
+        let events = get_events(i as i32 * 10..=(i as i32 * 10) + 2);
+        store.append_events(&stream_id, &ExpectedVersion::NoStream, events)
+    });
+    let results = join_all(appends).await;
+
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(results, events)
+}
+
+// Every writer appends the same events, each to a stream of its own
+pub async fn concurrent_writers_of_same_events<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    writers: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Result<Vec<EventRead<Payload, Meta, V>>>>),
+    V: Debug + Eq + PartialEq,
+{
+    let events = get_events(1..=3);
+    let stream_ids: Vec<String> = (0..writers).map(|_| get_stream_id()).collect();
+
+    let appends = stream_ids
+        .iter()
+        .map(|stream_id| store.append_events(stream_id, &ExpectedVersion::Any, events.clone()));
+    let results = join_all(appends).await;
+
+    assert(results)
+}
+
+pub async fn concurrent_writers_with_any_version<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    writers: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Result<Vec<EventRead<Payload, Meta, V>>>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+
+    let appends = (0..writers).map(|i| {
+        let events = get_events(i as i32 * 10..=(i as i32 * 10) + 2);
+        store.append_events(&stream_id, &ExpectedVersion::Any, events)
+    });
+    let results = join_all(appends).await;
+
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(results, events)
+}
+
+pub async fn can_read_all_events_from_position<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    page_size: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Vec<EventRead<Payload, Meta, V>>>),
+    V: Debug + Eq + PartialEq,
+{
+    let streams: Vec<String> = (1..=3).map(|_| get_stream_id()).collect();
+    for i in 0..3 {
+        for stream_id in &streams {
+            let events = get_events(i * 10..=(i * 10) + 2);
+            let _ = store
+                .append_events(stream_id, &ExpectedVersion::Any, events)
+                .await
+                .unwrap();
+        }
+    }
+
+    let mut pages = Vec::new();
+    let mut from_position = 1;
+    loop {
+        let page = store
+            .get_all_events(from_position, page_size)
+            .await
+            .unwrap();
+        match page.last() {
+            None => break,
+            Some(last) => from_position = last.position + 1,
+        }
+        pages.push(page);
+    }
+
+    assert(pages)
+}
+
+pub async fn can_stream_events_from_version<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    from_version: V,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    for i in 0..10 {
+        let events = get_events(i * 100 + 1..=(i + 1) * 100);
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, events)
+            .await
+            .unwrap();
+    }
+
+    let range = EventsReadRange::FromVersion(from_version);
+    let events = store
+        .get_events_stream(&stream_id, &range)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert(events)
+}
+
+pub async fn can_stream_events_by_correlation_id<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let corr_id = Uuid::new_v4();
+    for i in 0..3 {
+        let events = get_events(i * 10..=(i * 10) + 4)
+            .into_iter()
+            .map(|x| EventWrite {
+                correlation_id: Some(corr_id),
+                ..x
+            })
+            .collect();
+        let _ = store
+            .append_events(&get_stream_id(), &ExpectedVersion::Any, events)
+            .await
+            .unwrap();
+    }
+    let _ = store
+        .append_events(&get_stream_id(), &ExpectedVersion::Any, get_events(1..=5))
+        .await
+        .unwrap();
+
+    let events = store
+        .get_events_by_correlation_id_stream(&corr_id)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert(events)
+}
+
+pub async fn can_stream_streams_with_filter<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventStream<V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let prefix = get_stream_id();
+    for stream_id in [
+        format!("{}_first", prefix),
+        format!("{}_second", prefix),
+        get_stream_id(),
+    ] {
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=2))
+            .await
+            .unwrap();
+    }
+
+    let filter = StreamsReadFilter::StartsWith(prefix);
+    let streams = store
+        .get_streams_stream(&filter, DeletedStreams::Exclude)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert(streams)
+}
+
+// Ids containing LIKE wildcards, returns what each filter matched with the prefix cut off
+pub async fn stream_filters_match_wildcards_literally<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<Vec<String>>),
+    V: Debug + Eq + PartialEq,
+{
+    let prefix = Uuid::new_v4().to_string();
+    for suffix in ["_1", "x1", "%1", "\\1"] {
+        let _ = store
+            .append_events(
+                &format!("{}{}", prefix, suffix),
+                &ExpectedVersion::Any,
+                get_events(1..=1),
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut matched = Vec::new();
+    for filter in [
+        StreamsReadFilter::StartsWith(format!("{}_", prefix)),
+        StreamsReadFilter::Contains(format!("{}%", prefix)),
+        StreamsReadFilter::EndsWith(format!("{}\\1", prefix)),
+    ] {
+        let streams = store
+            .get_streams(&filter, DeletedStreams::Exclude)
+            .await
+            .unwrap();
+        let suffixes = streams
+            .into_iter()
+            .map(|s| s.id.trim_start_matches(&prefix).to_string())
+            .collect();
+        matched.push(suffixes);
+    }
+
+    assert(matched)
+}
+
+async fn append_25_events<V: Eq + PartialEq>(store: &dyn EventStore<Payload, Meta, V>) -> String {
+    let stream_id = get_stream_id();
+    for events in [get_events(1..=10), get_events(11..=20), get_events(21..=25)] {
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, events)
+            .await
+            .unwrap();
+    }
+    stream_id
+}
+
+pub async fn can_read_events_forward_in_pages<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    page_size: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<Vec<EventRead<Payload, Meta, V>>>),
+    V: Version<V> + Debug + Eq + PartialEq + Clone,
+{
+    let stream_id = append_25_events(store).await;
+
+    let mut pages = Vec::new();
+    let mut from_version = None;
+    loop {
+        let range = EventsReadRange::Page {
+            from_version: from_version.clone(),
+            direction: ReadDirection::Forward,
+            max_count: page_size,
+        };
+        let page = store.get_events(&stream_id, &range).await.unwrap();
+        match page.last() {
+            None => break,
+            Some(last) => {
+                from_version = Some(
+                    last.version
+                        .next_version(&stream_id, &ExpectedVersion::Any)
+                        .unwrap(),
+                )
+            }
+        }
+        pages.push(page);
+    }
+
+    assert(pages)
+}
+
+pub async fn can_read_events_backwards<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    from_version: Option<V>,
+    max_count: usize,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = append_25_events(store).await;
+
+    let range = EventsReadRange::Page {
+        from_version,
+        direction: ReadDirection::Backward,
+        max_count,
+    };
+    let events = store.get_events(&stream_id, &range).await.unwrap();
+
+    assert(events)
+}
+
+pub async fn soft_deleted_stream_can_be_recreated<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventStream<V>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    store
+        .delete_stream(&stream_id, DeleteMode::Soft)
+        .await
+        .unwrap();
+
+    let hidden = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    assert!(hidden.is_empty());
+    let missing = store.get_stream(&stream_id).await;
+    assert!(matches!(missing, Err(EventStoreError::StreamNotFound(_))));
+    let filter = StreamsReadFilter::StartsWith(stream_id.clone());
+    let listed = store
+        .get_streams(&filter, DeletedStreams::Exclude)
+        .await
+        .unwrap();
+    assert!(listed.is_empty());
+    let deleted = store
+        .get_streams(&filter, DeletedStreams::Include)
+        .await
+        .unwrap();
+
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::NoStream, get_events(4..=5))
+        .await
+        .unwrap();
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(deleted, events)
+}
+
+pub async fn deleted_events_are_hidden_from_all_reads<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let corr_id = Uuid::new_v4();
+    let events = || {
+        get_events(1..=2)
+            .into_iter()
+            .map(|x| EventWrite {
+                correlation_id: Some(corr_id),
+                ..x
+            })
+            .collect::<Vec<_>>()
+    };
+    let deleted = get_stream_id();
+    let kept = get_stream_id();
+    for stream_id in [&deleted, &kept] {
+        let _ = store
+            .append_events(stream_id, &ExpectedVersion::Any, events())
+            .await
+            .unwrap();
+    }
+    store
+        .delete_stream(&deleted, DeleteMode::Soft)
+        .await
+        .unwrap();
+
+    let correlated = store.get_events_by_correlation_id(&corr_id).await.unwrap();
+    let all = store.get_all_events(1, 100).await.unwrap();
+
+    assert(correlated, all)
+}
+
+pub async fn hard_deleted_stream_is_removed<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(i64, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let removed = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    store
+        .delete_stream(&stream_id, DeleteMode::Hard)
+        .await
+        .unwrap();
+
+    let filter = StreamsReadFilter::StartsWith(stream_id.clone());
+    let listed = store
+        .get_streams(&filter, DeletedStreams::Include)
+        .await
+        .unwrap();
+    assert!(listed.is_empty());
+
+    let recreated = store
+        .append_events(&stream_id, &ExpectedVersion::NoStream, get_events(1..=1))
+        .await
+        .unwrap();
+
+    assert(removed[2].position, recreated)
+}
+
+pub async fn tombstoned_stream_rejects_appends<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Result<Vec<EventRead<Payload, Meta, V>>>, Result<()>, Vec<EventStream<V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    store
+        .delete_stream(&stream_id, DeleteMode::Tombstone)
+        .await
+        .unwrap();
+
+    let appended = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(4..=4))
+        .await;
+    let deleted = store.delete_stream(&stream_id, DeleteMode::Hard).await;
+    let filter = StreamsReadFilter::StartsWith(stream_id.clone());
+    let listed = store
+        .get_streams(&filter, DeletedStreams::Include)
+        .await
+        .unwrap();
+
+    assert(appended, deleted, listed)
+}
+
+pub async fn max_count_hides_older_events<V, F>(store: &dyn EventStore<Payload, Meta, V>, assert: F)
+where
+    F: FnOnce(EventStream<V>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=10))
+        .await
+        .unwrap();
+
+    let metadata = StreamMetadata {
+        max_count: Some(3),
+        ..StreamMetadata::default()
+    };
+    let stream = store
+        .set_stream_metadata(&stream_id, &metadata)
+        .await
+        .unwrap();
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(stream, events)
+}
+
+pub async fn truncate_before_hides_earlier_events<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    truncate_before: V,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=10))
+        .await
+        .unwrap();
+
+    let metadata = StreamMetadata {
+        truncate_before: Some(truncate_before),
+        ..StreamMetadata::default()
+    };
+    let _ = store
+        .set_stream_metadata(&stream_id, &metadata)
+        .await
+        .unwrap();
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(events)
+}
+
+// Retention settings are replaced after the deleted stream was written to again
+pub async fn deleted_events_stay_hidden_after_metadata_change<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    truncate_before: V,
+    assert: F,
+) where
+    F: FnOnce(EventStream<V>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    let metadata = StreamMetadata {
+        truncate_before: Some(truncate_before),
+        ..StreamMetadata::default()
+    };
+    let _ = store
+        .set_stream_metadata(&stream_id, &metadata)
+        .await
+        .unwrap();
+    store
+        .delete_stream(&stream_id, DeleteMode::Soft)
+        .await
+        .unwrap();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::NoStream, get_events(4..=5))
+        .await
+        .unwrap();
+
+    let stream = store.get_stream(&stream_id).await.unwrap();
+    let _ = store
+        .set_stream_metadata(&stream_id, &StreamMetadata::default())
+        .await
+        .unwrap();
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(stream, events)
+}
+
+pub async fn max_age_hides_expired_events<V, F>(store: &dyn EventStore<Payload, Meta, V>, assert: F)
+where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(4..=5))
+        .await
+        .unwrap();
+
+    let metadata = StreamMetadata {
+        max_age: Some(Duration::from_secs(1)),
+        ..StreamMetadata::default()
+    };
+    let _ = store
+        .set_stream_metadata(&stream_id, &metadata)
+        .await
+        .unwrap();
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(events)
+}
+
+pub async fn scavenge_removes_hidden_events<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(u64, u64, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let capped = get_stream_id();
+    let deleted = get_stream_id();
+    let _ = store
+        .append_events(&capped, &ExpectedVersion::Any, get_events(1..=10))
+        .await
+        .unwrap();
+    let _ = store
+        .append_events(&deleted, &ExpectedVersion::Any, get_events(1..=2))
+        .await
+        .unwrap();
+    let metadata = StreamMetadata {
+        max_count: Some(3),
+        ..StreamMetadata::default()
+    };
+    let _ = store.set_stream_metadata(&capped, &metadata).await.unwrap();
+    store
+        .delete_stream(&deleted, DeleteMode::Soft)
+        .await
+        .unwrap();
+
+    let removed = store.scavenge().await.unwrap();
+    let removed_again = store.scavenge().await.unwrap();
+    let remaining = store.get_all_events(1, 100).await.unwrap();
+
+    assert(removed, removed_again, remaining)
+}
+
+pub async fn can_read_events_by_name<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    order: EventOrder,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    for stream_id in [get_stream_id(), get_stream_id()] {
+        let _ = store
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+            .await
+            .unwrap();
+    }
+
+    let single = store.get_events_by_name("Created_2", order).await.unwrap();
+    let multiple = store
+        .get_events_by_names(&["Created_1", "Created_3"], order)
+        .await
+        .unwrap();
+
+    assert(single, multiple)
+}
+
+pub async fn can_read_events_in_time_range<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Vec<EventRead<Payload, Meta, V>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let pause = || tokio::time::sleep(Duration::from_millis(20));
+    let stream_id = get_stream_id();
+    let other = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=2))
+        .await
+        .unwrap();
+    pause().await;
+    let from = Utc::now();
+    pause().await;
+    for id in [&stream_id, &other] {
+        let _ = store
+            .append_events(id, &ExpectedVersion::Any, get_events(3..=4))
+            .await
+            .unwrap();
+    }
+    pause().await;
+    let to = Utc::now();
+    pause().await;
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(5..=6))
+        .await
+        .unwrap();
+
+    let all = store
+        .get_events_in_time_range(&from, &to, &StreamsReadFilter::AllStreams)
+        .await
+        .unwrap();
+    let single = store
+        .get_events_in_time_range(&from, &to, &StreamsReadFilter::Equals(stream_id))
+        .await
+        .unwrap();
+
+    assert(all, single)
+}
+
+pub async fn appends_to_multiple_streams_atomically<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    source_next: V,
+    assert: F,
+) where
+    F: FnOnce(
+        Vec<Vec<EventRead<Payload, Meta, V>>>,
+        Vec<EventRead<Payload, Meta, V>>,
+        Vec<EventRead<Payload, Meta, V>>,
+    ),
+    V: Debug + Eq + PartialEq,
+{
+    let source = get_stream_id();
+    let target = get_stream_id();
+    let _ = store
+        .append_events(&source, &ExpectedVersion::Any, get_events(1..=2))
+        .await
+        .unwrap();
+
+    let results = store
+        .append_to_streams(vec![
+            StreamAppend {
+                stream_id: source.clone(),
+                version: ExpectedVersion::Exact(source_next),
+                events: get_events(3..=3),
+            },
+            StreamAppend {
+                stream_id: target.clone(),
+                version: ExpectedVersion::NoStream,
+                events: get_events(1..=2),
+            },
+        ])
+        .await
+        .unwrap();
+
+    let source_events = store
+        .get_events(&source, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    let target_events = store
+        .get_events(&target, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(results, source_events, target_events)
+}
+
+pub async fn multi_stream_append_fails_as_a_whole<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    wrong_version: V,
+    assert: F,
+) where
+    F: FnOnce(
+        Result<Vec<Vec<EventRead<Payload, Meta, V>>>>,
+        Vec<EventRead<Payload, Meta, V>>,
+        Result<EventStream<V>>,
+    ),
+    V: Debug + Eq + PartialEq,
+{
+    let source = get_stream_id();
+    let target = get_stream_id();
+    let _ = store
+        .append_events(&source, &ExpectedVersion::Any, get_events(1..=2))
+        .await
+        .unwrap();
+
+    let result = store
+        .append_to_streams(vec![
+            StreamAppend {
+                stream_id: target.clone(),
+                version: ExpectedVersion::NoStream,
+                events: get_events(1..=2),
+            },
+            StreamAppend {
+                stream_id: source.clone(),
+                version: ExpectedVersion::Exact(wrong_version),
+                events: get_events(3..=3),
+            },
+        ])
+        .await;
+
+    let source_events = store
+        .get_events(&source, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    let target_stream = store.get_stream(&target).await;
+
+    assert(result, source_events, target_stream)
+}
+
+pub async fn concurrent_multi_stream_appends<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    writers: usize,
+    assert: F,
+) where
+    F: FnOnce(
+        Vec<Result<Vec<Vec<EventRead<Payload, Meta, V>>>>>,
+        Vec<EventRead<Payload, Meta, V>>,
+        Vec<EventRead<Payload, Meta, V>>,
+    ),
+    V: Debug + Eq + PartialEq,
+{
+    let first = get_stream_id();
+    let second = get_stream_id();
+
+    // Every other writer lists the streams in reverse order
+    let appends = (0..writers).map(|i| {
+        let mut streams = vec![first.clone(), second.clone()];
+        if i % 2 == 1 {
+            streams.reverse();
+        }
+        let appends = streams
+            .into_iter()
+            .map(|stream_id| StreamAppend {
+                stream_id,
+                version: ExpectedVersion::Any,
+                events: get_events(i as i32 * 10..=(i as i32 * 10) + 1),
+            })
+            .collect();
+        store.append_to_streams(appends)
+    });
+    let results = join_all(appends).await;
+
+    let first_events = store
+        .get_events(&first, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    let second_events = store
+        .get_events(&second, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(results, first_events, second_events)
+}
+
+pub async fn retried_append_returns_original_events<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(
+        Vec<EventRead<Payload, Meta, V>>,
+        Vec<EventRead<Payload, Meta, V>>,
+        Vec<EventRead<Payload, Meta, V>>,
+    ),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let events = get_events(1..=3);
+    let first = store
+        .append_events(&stream_id, &ExpectedVersion::NoStream, events.clone())
+        .await
+        .unwrap();
+    let retried = store
+        .append_events(&stream_id, &ExpectedVersion::NoStream, events)
+        .await
+        .unwrap();
+
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(first, retried, events)
+}
+
+pub async fn overlapping_append_is_rejected<V, F>(
+    store: &dyn EventStore<Payload, Meta, V>,
+    assert: F,
+) where
+    F: FnOnce(Result<Vec<EventRead<Payload, Meta, V>>>, Vec<EventRead<Payload, Meta, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let events = get_events(1..=3);
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, events[..2].to_vec())
+        .await
+        .unwrap();
+    let result = store
+        .append_events(&stream_id, &ExpectedVersion::Any, events[1..].to_vec())
+        .await;
+
+    let events = store
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    assert(result, events)
+}
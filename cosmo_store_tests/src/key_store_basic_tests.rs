@@ -0,0 +1,44 @@
+use crate::event_generator::get_stream_id;
+use cosmo_store::traits::key_store::KeyStore;
+use cosmo_store::types::event_store_error::Result;
+
+pub async fn create_key<F>(store: &dyn KeyStore, assert: F)
+where
+    F: FnOnce(Vec<u8>, Option<Vec<u8>>),
+{
+    let subject = get_stream_id();
+    let created = store.create_key(&subject, &[1, 2, 3]).await.unwrap();
+    let res = store.get_key(&subject).await.unwrap();
+    assert(created, res)
+}
+
+pub async fn create_key_keeps_existing_key<F>(store: &dyn KeyStore, assert: F)
+where
+    F: FnOnce(Vec<u8>),
+{
+    let subject = get_stream_id();
+    let _ = store.create_key(&subject, &[1]).await.unwrap();
+    let res = store.create_key(&subject, &[2]).await.unwrap();
+    assert(res)
+}
+
+pub async fn get_key_of_unknown_subject<F>(store: &dyn KeyStore, assert: F)
+where
+    F: FnOnce(Option<Vec<u8>>),
+{
+    let res = store.get_key(&get_stream_id()).await.unwrap();
+    assert(res)
+}
+
+pub async fn delete_key_shreds_subject<F>(store: &dyn KeyStore, assert: F)
+where
+    F: FnOnce(Option<Vec<u8>>, Result<Vec<u8>>),
+{
+    let subject = get_stream_id();
+    let _ = store.create_key(&subject, &[1]).await.unwrap();
+    store.delete_key(&subject).await.unwrap();
+
+    let key = store.get_key(&subject).await.unwrap();
+    let recreated = store.create_key(&subject, &[2]).await;
+    assert(key, recreated)
+}
@@ -1,2 +1,5 @@
+pub mod checkpoint_store_basic_tests;
 pub mod event_generator;
 pub mod event_store_basic_tests;
+pub mod key_store_basic_tests;
+pub mod snapshot_store_basic_tests;
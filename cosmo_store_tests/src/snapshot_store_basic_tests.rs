@@ -0,0 +1,108 @@
+use crate::event_generator::get_stream_id;
+use crate::event_store_basic_tests::Payload;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store::types::snapshot::Snapshot;
+use std::fmt::Debug;
+
+fn get_state(i: i32) -> Payload {
+    Payload {
+        name: format!("State {}", i),
+    }
+}
+
+pub async fn save_snapshot<V, F>(store: &dyn SnapshotStore<Payload, V>, version: V, assert: F)
+where
+    F: FnOnce(Snapshot<Payload, V>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let res = store
+        .save_snapshot(&stream_id, &version, &get_state(1))
+        .await
+        .unwrap();
+    assert(res)
+}
+
+pub async fn get_latest_snapshot<V, F>(
+    store: &dyn SnapshotStore<Payload, V>,
+    versions: Vec<V>,
+    assert: F,
+) where
+    F: FnOnce(Option<Snapshot<Payload, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    for (i, version) in versions.iter().enumerate() {
+        let _ = store
+            .save_snapshot(&stream_id, version, &get_state(i as i32))
+            .await
+            .unwrap();
+    }
+    // Snapshots of other streams are never returned
+    let _ = store
+        .save_snapshot(&get_stream_id(), &versions[0], &get_state(100))
+        .await
+        .unwrap();
+
+    let res = store.get_latest_snapshot(&stream_id).await.unwrap();
+    assert(res)
+}
+
+pub async fn get_latest_snapshot_of_unknown_stream<V, F>(
+    store: &dyn SnapshotStore<Payload, V>,
+    assert: F,
+) where
+    F: FnOnce(Option<Snapshot<Payload, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let res = store.get_latest_snapshot(&get_stream_id()).await.unwrap();
+    assert(res)
+}
+
+pub async fn saving_same_version_replaces_snapshot<V, F>(
+    store: &dyn SnapshotStore<Payload, V>,
+    version: V,
+    assert: F,
+) where
+    F: FnOnce(Option<Snapshot<Payload, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    for i in 1..=2 {
+        let _ = store
+            .save_snapshot(&stream_id, &version, &get_state(i))
+            .await
+            .unwrap();
+    }
+
+    let res = store.get_latest_snapshot(&stream_id).await.unwrap();
+    assert(res)
+}
+
+pub async fn delete_snapshots<V, F>(
+    store: &dyn SnapshotStore<Payload, V>,
+    versions: Vec<V>,
+    assert: F,
+) where
+    F: FnOnce(Option<Snapshot<Payload, V>>, Option<Snapshot<Payload, V>>),
+    V: Debug + Eq + PartialEq,
+{
+    let stream_id = get_stream_id();
+    let other_id = get_stream_id();
+    for (i, version) in versions.iter().enumerate() {
+        let _ = store
+            .save_snapshot(&stream_id, version, &get_state(i as i32))
+            .await
+            .unwrap();
+    }
+    let _ = store
+        .save_snapshot(&other_id, &versions[0], &get_state(100))
+        .await
+        .unwrap();
+
+    store.delete_snapshots(&stream_id).await.unwrap();
+
+    let deleted = store.get_latest_snapshot(&stream_id).await.unwrap();
+    let other = store.get_latest_snapshot(&other_id).await.unwrap();
+    assert(deleted, other)
+}
@@ -1,10 +1,18 @@
 use anyhow::Result;
 use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store::traits::version::Version as StoreVersion;
+use cosmo_store::types::delete_mode::DeleteMode;
 use cosmo_store::types::event_read::EventRead;
 use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store::types::event_stream::StreamState;
 use cosmo_store::types::event_write::EventWrite;
 use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::snapshot::Snapshot;
+use futures::{future, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 pub trait Aggregate<State, Command, Event> {
     fn init(&self) -> State;
@@ -26,16 +34,292 @@ where
     Event: Into<EventWrite<Event, Meta>> + Clone + Serialize + for<'de> Deserialize<'de>,
     Meta: Clone + Serialize + for<'de> Deserialize<'de>,
 {
-    let events = store.get_events(stream_id, range).await?;
-    let state = events
+    let state = store
+        .get_events_stream(stream_id, range)
+        .try_fold(aggregate.init(), |a, b| {
+            future::ready(Ok(aggregate.apply(a, &b.data)))
+        })
+        .await?;
+    let new_events = aggregate
+        .execute(&state, command)?
         .iter()
-        .fold(aggregate.init(), |a, b| aggregate.apply(a, &b.data));
+        .map(|x| x.clone().into())
+        .collect();
+    let res = store
+        .append_events(stream_id, expected_version, new_events)
+        .await?;
+    Ok(res)
+}
+
+// Latest snapshot of the stream, unless it holds events deleted or truncated since, or was
+// taken of a stream that no longer exists or was recreated with fewer events
+async fn latest_snapshot<State, Event, Meta, Version>(
+    store: &impl EventStore<Event, Meta, Version>,
+    snapshots: &impl SnapshotStore<State, Version>,
+    stream_id: &str,
+) -> Result<Option<Snapshot<State, Version>>>
+where
+    Version: Eq + PartialOrd,
+{
+    let stream = match store.get_stream(stream_id).await {
+        Ok(s) if s.state == StreamState::Active => s,
+        Ok(_) | Err(EventStoreError::StreamNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let res = snapshots.get_latest_snapshot(stream_id).await?.filter(|s| {
+        s.version <= stream.last_version
+            && stream
+                .deleted_before
+                .as_ref()
+                .is_none_or(|d| s.version >= *d)
+            && stream
+                .metadata
+                .truncate_before
+                .as_ref()
+                .is_none_or(|t| s.version >= *t)
+    });
+    Ok(res)
+}
+
+// Folds the events written after the latest snapshot on top of its state.
+// Returns the state, the version of the last event applied and how many events were folded.
+async fn rehydrate<State, Command, Event, Meta, Version>(
+    aggregate: &impl Aggregate<State, Command, Event>,
+    store: &impl EventStore<Event, Meta, Version>,
+    snapshots: &impl SnapshotStore<State, Version>,
+    stream_id: &str,
+) -> Result<(State, Option<Version>, usize)>
+where
+    Version: StoreVersion<Version> + Eq + PartialOrd + Clone,
+{
+    let (state, range, last_version) = match latest_snapshot(store, snapshots, stream_id).await? {
+        None => (aggregate.init(), EventsReadRange::AllEvents, None),
+        Some(s) => {
+            let next = s.version.next_version(stream_id, &ExpectedVersion::Any)?;
+            (s.state, EventsReadRange::FromVersion(next), Some(s.version))
+        }
+    };
+    let res = store
+        .get_events_stream(stream_id, &range)
+        .try_fold((state, last_version, 0), |(state, _, folded), e| {
+            future::ready(Ok((
+                aggregate.apply(state, &e.data),
+                Some(e.version),
+                folded + 1,
+            )))
+        })
+        .await?;
+    Ok(res)
+}
+
+// Rehydrates the state of a stream from its latest snapshot and the events written after it.
+pub async fn load_state<State, Command, Event, Meta, Version>(
+    aggregate: &impl Aggregate<State, Command, Event>,
+    store: &impl EventStore<Event, Meta, Version>,
+    snapshots: &impl SnapshotStore<State, Version>,
+    stream_id: &str,
+) -> Result<(State, Option<Version>)>
+where
+    Version: StoreVersion<Version> + Eq + PartialOrd + Clone,
+{
+    let (state, version, _) = rehydrate(aggregate, store, snapshots, stream_id).await?;
+    Ok((state, version))
+}
+
+// Same as make_handler, but rehydrates from the latest snapshot and takes a new one
+// once `snapshot_every` events were written since the previous snapshot.
+pub async fn make_snapshot_handler<State, Command, Event, Meta, Version>(
+    aggregate: &impl Aggregate<State, Command, Event>,
+    store: &impl EventStore<Event, Meta, Version>,
+    snapshots: &impl SnapshotStore<State, Version>,
+    command: &Command,
+    stream_id: &str,
+    expected_version: &ExpectedVersion<Version>,
+    snapshot_every: usize,
+) -> Result<Vec<EventRead<Event, Meta, Version>>>
+where
+    Version: StoreVersion<Version> + Eq + PartialOrd + Clone,
+    Event: Into<EventWrite<Event, Meta>> + Clone + Serialize + for<'de> Deserialize<'de>,
+    Meta: Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    let (state, _, folded) = rehydrate(aggregate, store, snapshots, stream_id).await?;
     let new_events = aggregate
         .execute(&state, command)?
         .iter()
         .map(|x| x.clone().into())
         .collect();
-    store
+    let res = store
         .append_events(stream_id, expected_version, new_events)
-        .await
+        .await?;
+
+    if let Some(last) = res.last() {
+        if folded + res.len() >= snapshot_every {
+            let state = res.iter().fold(state, |a, b| aggregate.apply(a, &b.data));
+            let _ = snapshots
+                .save_snapshot(stream_id, &last.version, &state)
+                .await?;
+        }
+    }
+    Ok(res)
+}
+
+// Deletes the stream along with its snapshots, a stream recreated after a hard delete would
+// otherwise be rehydrated from the snapshots of the deleted one.
+// Snapshots go first, so a failed delete leaves at worst a stream without snapshots.
+pub async fn delete_stream<State, Event, Meta, Version>(
+    store: &impl EventStore<Event, Meta, Version>,
+    snapshots: &impl SnapshotStore<State, Version>,
+    stream_id: &str,
+    mode: DeleteMode,
+) -> Result<()>
+where
+    Version: Eq + PartialEq,
+{
+    snapshots.delete_snapshots(stream_id).await?;
+    store.delete_stream(stream_id, mode).await?;
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// How often a command is executed again after a concurrency conflict
+    pub max_retries: u32,
+    /// Delay before the first retry, every further retry waits one more delay
+    pub retry_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            retry_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn retry_after(&self, retries: u32) -> Duration {
+        self.retry_delay * retries
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Handled<State, Event, Meta, Version> {
+    pub state: State,
+    /// Last version of the stream, none while the stream doesn't exist or is deleted
+    pub version: Option<Version>,
+    pub events: Vec<EventRead<Event, Meta, Version>>,
+}
+
+/**
+Handles commands of an aggregate with optimistic concurrency.
+Events are appended at the version following the last one the command was executed against.
+When another writer got there first, the stream is loaded again and the command executed again
+on the new state, as often as the retry policy allows.
+*/
+pub struct AggregateRepository<'a, State, Command, Event, Meta, Version> {
+    aggregate: &'a (dyn Aggregate<State, Command, Event> + Sync),
+    store: &'a (dyn EventStore<Event, Meta, Version> + Sync),
+    retry_policy: RetryPolicy,
+}
+
+impl<'a, State, Command, Event, Meta, Version>
+    AggregateRepository<'a, State, Command, Event, Meta, Version>
+where
+    Version: StoreVersion<Version> + Eq + PartialEq + Clone,
+    Event: Into<EventWrite<Event, Meta>> + Clone,
+{
+    pub fn new(
+        aggregate: &'a (dyn Aggregate<State, Command, Event> + Sync),
+        store: &'a (dyn EventStore<Event, Meta, Version> + Sync),
+    ) -> Self {
+        AggregateRepository {
+            aggregate,
+            store,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    // Folds the visible events of the stream, returns the state and the last version of the
+    // stream, which is kept when retention settings hide its last events
+    pub async fn load(&self, stream_id: &str) -> Result<(State, Option<Version>)> {
+        // Read before the events, events appended in between make the next append conflict
+        let version = match self.store.get_stream(stream_id).await {
+            Ok(s) if s.state == StreamState::Deleted => None,
+            Ok(s) => Some(s.last_version),
+            Err(EventStoreError::StreamNotFound(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let state = self
+            .store
+            .get_events_stream(stream_id, &EventsReadRange::AllEvents)
+            .try_fold(self.aggregate.init(), |state, e| {
+                future::ready(Ok(self.aggregate.apply(state, &e.data)))
+            })
+            .await?;
+        Ok((state, version))
+    }
+
+    pub async fn handle(
+        &self,
+        stream_id: &str,
+        command: &Command,
+    ) -> Result<Handled<State, Event, Meta, Version>> {
+        let mut retries = 0;
+        loop {
+            let (state, version) = self.load(stream_id).await?;
+            let new_events: Vec<EventWrite<Event, Meta>> = self
+                .aggregate
+                .execute(&state, command)?
+                .iter()
+                .map(|x| x.clone().into())
+                .collect();
+            if new_events.is_empty() {
+                return Ok(Handled {
+                    state,
+                    version,
+                    events: vec![],
+                });
+            }
+            let expected_version = match &version {
+                Some(v) => {
+                    ExpectedVersion::Exact(v.next_version(stream_id, &ExpectedVersion::Any)?)
+                }
+                None => ExpectedVersion::NoStream,
+            };
+            match self
+                .store
+                .append_events(stream_id, &expected_version, new_events)
+                .await
+            {
+                Ok(events) => {
+                    let state = events
+                        .iter()
+                        .fold(state, |a, b| self.aggregate.apply(a, &b.data));
+                    let version = events.last().map(|e| e.version.clone()).or(version);
+                    return Ok(Handled {
+                        state,
+                        version,
+                        events,
+                    });
+                }
+                Err(e)
+                    if e.is_concurrency_conflict() && retries < self.retry_policy.max_retries =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(self.retry_policy.retry_after(retries)).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 }
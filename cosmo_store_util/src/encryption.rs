@@ -0,0 +1,444 @@
+use aes_gcm::aead::{self, Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::key_store::KeyStore;
+use cosmo_store::types::delete_mode::DeleteMode;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::{EventOrder, EventsReadRange};
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_stream::EventStream;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_append::StreamAppend;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store::types::stream_read_filter::{DeletedStreams, StreamsReadFilter};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Payload or metadata of an event read through an `EncryptedEventStore`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Shreddable<T> {
+    Readable(T),
+    /// The key it was encrypted with was deleted
+    Shredded,
+}
+
+impl<T> Shreddable<T> {
+    pub fn readable(self) -> Option<T> {
+        match self {
+            Shreddable::Readable(v) => Some(v),
+            Shreddable::Shredded => None,
+        }
+    }
+
+    pub fn is_shredded(&self) -> bool {
+        matches!(self, Shreddable::Shredded)
+    }
+}
+
+/// Encrypted payload or metadata as stored by the wrapped store
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sealed {
+    pub subject: String,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+type Subject = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/**
+Event store encrypting payload and metadata of events with the key of their subject.
+The subject of an event is its stream unless `with_subject` maps streams to something else,
+e.g. the person several streams are about. Shredding a subject deletes its key:
+its events stay in the store, but read back as `Shreddable::Shredded` and no more
+events can be appended for it. Names, ids and other event fields are not encrypted,
+but sealed values are bound to the id and stream of their event.
+*/
+pub struct EncryptedEventStore<S, K> {
+    store: S,
+    keys: K,
+    subject: Subject,
+}
+
+impl<S, K: KeyStore> EncryptedEventStore<S, K> {
+    pub fn new(store: S, keys: K) -> EncryptedEventStore<S, K> {
+        EncryptedEventStore {
+            store,
+            keys,
+            subject: Arc::new(|stream_id| stream_id.to_string()),
+        }
+    }
+
+    /// Encrypts events with the key of the subject `subject` maps their stream id to
+    pub fn with_subject<F>(self, subject: F) -> EncryptedEventStore<S, K>
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        EncryptedEventStore {
+            subject: Arc::new(subject),
+            ..self
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn keys(&self) -> &K {
+        &self.keys
+    }
+
+    /// Makes all events of `subject` unreadable for good
+    pub async fn shred(&self, subject: &str) -> Result<()> {
+        self.keys.delete_key(subject).await
+    }
+
+    async fn write_key(&self, subject: &str) -> Result<Vec<u8>> {
+        match self.keys.get_key(subject).await? {
+            Some(key) => Ok(key),
+            None => {
+                let key = Aes256Gcm::generate_key(OsRng);
+                self.keys.create_key(subject, &key).await
+            }
+        }
+    }
+
+    async fn seal_events<Payload, Meta>(
+        &self,
+        stream_id: &str,
+        events: Vec<EventWrite<Shreddable<Payload>, Shreddable<Meta>>>,
+    ) -> Result<Vec<EventWrite<Sealed, Sealed>>>
+    where
+        Payload: Serialize,
+        Meta: Serialize,
+    {
+        let subject = (self.subject)(stream_id);
+        let key = self.write_key(&subject).await?;
+        events
+            .into_iter()
+            .map(|e| {
+                let aad = associated_data(&e.id, stream_id);
+                Ok(EventWrite {
+                    id: e.id,
+                    correlation_id: e.correlation_id,
+                    causation_id: e.causation_id,
+                    name: e.name,
+                    data: seal(&subject, &key, &aad, e.data)?,
+                    metadata: e
+                        .metadata
+                        .map(|m| seal(&subject, &key, &aad, m))
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn open_events<Payload, Meta, V>(
+        &self,
+        events: Vec<EventRead<Sealed, Sealed, V>>,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>>
+    where
+        Payload: DeserializeOwned,
+        Meta: DeserializeOwned,
+    {
+        let mut keys: HashMap<String, Option<Vec<u8>>> = HashMap::new();
+        let mut opened = Vec::with_capacity(events.len());
+        for e in events {
+            if !keys.contains_key(&e.data.subject) {
+                let key = self.keys.get_key(&e.data.subject).await?;
+                keys.insert(e.data.subject.clone(), key);
+            }
+            let key = keys[&e.data.subject].as_deref();
+            let aad = associated_data(&e.id, &e.stream_id);
+            opened.push(EventRead {
+                id: e.id,
+                correlation_id: e.correlation_id,
+                causation_id: e.causation_id,
+                stream_id: e.stream_id,
+                version: e.version,
+                position: e.position,
+                name: e.name,
+                data: open(key, &aad, e.data)?,
+                metadata: e.metadata.map(|m| open(key, &aad, m)).transpose()?,
+                created_utc: e.created_utc,
+            });
+        }
+        Ok(opened)
+    }
+
+    async fn open_event<Payload, Meta, V>(
+        &self,
+        event: EventRead<Sealed, Sealed, V>,
+    ) -> Result<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>
+    where
+        Payload: DeserializeOwned,
+        Meta: DeserializeOwned,
+    {
+        let mut opened = self.open_events(vec![event]).await?;
+        Ok(opened.remove(0))
+    }
+}
+
+// Ties a sealed value to its event, so it can't be opened as part of another event or stream.
+// Ids have a fixed length, the stream id takes the rest
+fn associated_data(id: &Uuid, stream_id: &str) -> Vec<u8> {
+    let mut aad = id.as_bytes().to_vec();
+    aad.extend_from_slice(stream_id.as_bytes());
+    aad
+}
+
+fn seal<T: Serialize>(
+    subject: &str,
+    key: &[u8],
+    aad: &[u8],
+    value: Shreddable<T>,
+) -> Result<Sealed> {
+    let value = value.readable().ok_or_else(|| {
+        EventStoreError::serialization(format!("Shredded value of {} can't be written", subject))
+    })?;
+    let plaintext = serde_json::to_vec(&value).map_err(EventStoreError::serialization)?;
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| {
+        EventStoreError::serialization(format!("Invalid key of {}: {}", subject, e))
+    })?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            aead::Payload {
+                msg: &plaintext,
+                aad,
+            },
+        )
+        .map_err(|e| EventStoreError::serialization(format!("Failed to encrypt: {}", e)))?;
+    Ok(Sealed {
+        subject: subject.to_string(),
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+fn open<T: DeserializeOwned>(
+    key: Option<&[u8]>,
+    aad: &[u8],
+    sealed: Sealed,
+) -> Result<Shreddable<T>> {
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(Shreddable::Shredded),
+    };
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| {
+        EventStoreError::serialization(format!("Invalid key of {}: {}", sealed.subject, e))
+    })?;
+    if sealed.nonce.len() != 12 {
+        return Err(EventStoreError::serialization(format!(
+            "Invalid nonce of {}",
+            sealed.subject
+        )));
+    }
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&sealed.nonce),
+            aead::Payload {
+                msg: &sealed.ciphertext,
+                aad,
+            },
+        )
+        .map_err(|e| EventStoreError::serialization(format!("Failed to decrypt: {}", e)))?;
+    let value = serde_json::from_slice(&plaintext).map_err(EventStoreError::serialization)?;
+    Ok(Shreddable::Readable(value))
+}
+
+#[async_trait]
+impl<Payload, Meta, V, S, K> EventStore<Shreddable<Payload>, Shreddable<Meta>, V>
+    for EncryptedEventStore<S, K>
+where
+    Payload: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    Meta: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    V: Eq + PartialEq + Send + Sync + 'static,
+    S: EventStore<Sealed, Sealed, V> + Send + Sync,
+    K: KeyStore + Send + Sync,
+{
+    async fn append_event(
+        &self,
+        stream_id: &str,
+        version: &ExpectedVersion<V>,
+        payload: &EventWrite<Shreddable<Payload>, Shreddable<Meta>>,
+    ) -> Result<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>> {
+        let mut res = self
+            .append_events(stream_id, version, vec![payload.clone()])
+            .await?;
+        Ok(res.remove(0))
+    }
+
+    async fn append_events(
+        &self,
+        stream_id: &str,
+        version: &ExpectedVersion<V>,
+        payload: Vec<EventWrite<Shreddable<Payload>, Shreddable<Meta>>>,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let sealed = self.seal_events(stream_id, payload).await?;
+        let res = self.store.append_events(stream_id, version, sealed).await?;
+        self.open_events(res).await
+    }
+
+    async fn append_to_streams(
+        &self,
+        appends: Vec<StreamAppend<Shreddable<Payload>, Shreddable<Meta>, V>>,
+    ) -> Result<Vec<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>>> {
+        let mut sealed = Vec::with_capacity(appends.len());
+        for append in appends {
+            sealed.push(StreamAppend {
+                events: self.seal_events(&append.stream_id, append.events).await?,
+                stream_id: append.stream_id,
+                version: append.version,
+            });
+        }
+        let res = self.store.append_to_streams(sealed).await?;
+        let mut opened = Vec::with_capacity(res.len());
+        for events in res {
+            opened.push(self.open_events(events).await?);
+        }
+        Ok(opened)
+    }
+
+    async fn get_event(
+        &self,
+        stream_id: &str,
+        version: &V,
+    ) -> Result<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>> {
+        let res = self.store.get_event(stream_id, version).await?;
+        self.open_event(res).await
+    }
+
+    async fn get_events(
+        &self,
+        stream_id: &str,
+        range: &EventsReadRange<V>,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self.store.get_events(stream_id, range).await?;
+        self.open_events(res).await
+    }
+
+    fn get_events_stream<'a>(
+        &'a self,
+        stream_id: &'a str,
+        range: &'a EventsReadRange<V>,
+    ) -> BoxStream<'a, Result<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        self.store
+            .get_events_stream(stream_id, range)
+            .and_then(move |e| self.open_event(e))
+            .boxed()
+    }
+
+    async fn get_all_events(
+        &self,
+        from_position: i64,
+        page_size: usize,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self.store.get_all_events(from_position, page_size).await?;
+        self.open_events(res).await
+    }
+
+    async fn get_events_by_correlation_id(
+        &self,
+        correlation_id: &Uuid,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self
+            .store
+            .get_events_by_correlation_id(correlation_id)
+            .await?;
+        self.open_events(res).await
+    }
+
+    fn get_events_by_correlation_id_stream<'a>(
+        &'a self,
+        correlation_id: &'a Uuid,
+    ) -> BoxStream<'a, Result<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        self.store
+            .get_events_by_correlation_id_stream(correlation_id)
+            .and_then(move |e| self.open_event(e))
+            .boxed()
+    }
+
+    async fn get_events_by_causation_id(
+        &self,
+        causation_id: &Uuid,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self.store.get_events_by_causation_id(causation_id).await?;
+        self.open_events(res).await
+    }
+
+    async fn get_events_by_name(
+        &self,
+        name: &str,
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self.store.get_events_by_name(name, order).await?;
+        self.open_events(res).await
+    }
+
+    async fn get_events_by_names(
+        &self,
+        names: &[&str],
+        order: EventOrder,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self.store.get_events_by_names(names, order).await?;
+        self.open_events(res).await
+    }
+
+    async fn get_events_in_time_range(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        filter: &StreamsReadFilter,
+    ) -> Result<Vec<EventRead<Shreddable<Payload>, Shreddable<Meta>, V>>> {
+        let res = self
+            .store
+            .get_events_in_time_range(from, to, filter)
+            .await?;
+        self.open_events(res).await
+    }
+
+    async fn get_streams(
+        &self,
+        filter: &StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> Result<Vec<EventStream<V>>> {
+        self.store.get_streams(filter, deleted).await
+    }
+
+    fn get_streams_stream<'a>(
+        &'a self,
+        filter: &'a StreamsReadFilter,
+        deleted: DeletedStreams,
+    ) -> BoxStream<'a, Result<EventStream<V>>> {
+        self.store.get_streams_stream(filter, deleted)
+    }
+
+    async fn get_stream(&self, stream_id: &str) -> Result<EventStream<V>> {
+        self.store.get_stream(stream_id).await
+    }
+
+    async fn delete_stream(&self, stream_id: &str, mode: DeleteMode) -> Result<()> {
+        self.store.delete_stream(stream_id, mode).await
+    }
+
+    async fn set_stream_metadata(
+        &self,
+        stream_id: &str,
+        metadata: &StreamMetadata<V>,
+    ) -> Result<EventStream<V>> {
+        self.store.set_stream_metadata(stream_id, metadata).await
+    }
+
+    async fn scavenge(&self) -> Result<u64> {
+        self.store.scavenge().await
+    }
+}
@@ -1 +1,5 @@
 pub mod aggregate;
+pub mod encryption;
+pub mod process_manager;
+pub mod projection;
+pub mod subscription;
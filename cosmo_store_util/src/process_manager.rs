@@ -0,0 +1,197 @@
+use crate::projection::Projection;
+use async_trait::async_trait;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::version::Version as StoreVersion;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// What a process did about an event: changes to its state and commands to dispatch
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reaction<Change, Command> {
+    pub changes: Vec<Change>,
+    pub commands: Vec<Command>,
+}
+
+impl<Change, Command> Default for Reaction<Change, Command> {
+    fn default() -> Self {
+        Reaction {
+            changes: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+}
+
+/**
+Long-running workflow reacting to events of several streams.
+Every correlation id runs its own process, whose state is folded from the reactions
+recorded in the process stream. Failing to react stops the `ProjectionRunner` with
+`EventStoreError::ReactionFailed`, whose source is the process' `Error`.
+*/
+pub trait ProcessManager<State, Event, Change, Command> {
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Prefix of the process streams, also identifies the checkpoint
+    fn name(&self) -> &str;
+    /// Names of the events the process reacts to, none reacts to all events
+    fn event_names(&self) -> Option<Vec<&str>> {
+        None
+    }
+    fn init(&self) -> State;
+    fn apply(&self, state: State, change: &Change) -> State;
+    fn react(&self, state: &State, event: &Event)
+        -> Result<Reaction<Change, Command>, Self::Error>;
+}
+
+#[async_trait]
+pub trait CommandDispatcher<Command> {
+    async fn dispatch(&self, command: &Command) -> Result<()>;
+}
+
+/**
+Runs a process manager as a projection, so a `ProjectionRunner` feeds it the events of a store.
+Each event is reacted to once: the reaction is recorded in the process stream before its commands
+are dispatched, a redelivered event dispatches the recorded commands again instead.
+Commands are therefore dispatched at least once, unless the reaction was hidden by the retention
+settings of the process stream before the event was redelivered.
+*/
+pub struct ProcessHandler<'a, State, Event, Change, Command, Error, V> {
+    manager: &'a (dyn ProcessManager<State, Event, Change, Command, Error = Error> + Sync),
+    reactions: &'a (dyn EventStore<Reaction<Change, Command>, (), V> + Sync),
+    dispatcher: &'a (dyn CommandDispatcher<Command> + Sync),
+}
+
+impl<'a, State, Event, Change, Command, Error, V>
+    ProcessHandler<'a, State, Event, Change, Command, Error, V>
+where
+    Error: StdError + Send + Sync + 'static,
+    V: Eq,
+{
+    pub fn new(
+        manager: &'a (dyn ProcessManager<State, Event, Change, Command, Error = Error> + Sync),
+        reactions: &'a (dyn EventStore<Reaction<Change, Command>, (), V> + Sync),
+        dispatcher: &'a (dyn CommandDispatcher<Command> + Sync),
+    ) -> Self {
+        ProcessHandler {
+            manager,
+            reactions,
+            dispatcher,
+        }
+    }
+
+    pub fn stream_id(&self, correlation_id: &Uuid) -> String {
+        format!("{}-{}", self.manager.name(), correlation_id)
+    }
+
+    fn fold(&self, reactions: &[EventRead<Reaction<Change, Command>, (), V>]) -> State {
+        reactions
+            .iter()
+            .flat_map(|r| r.data.changes.iter())
+            .fold(self.manager.init(), |state, change| {
+                self.manager.apply(state, change)
+            })
+    }
+
+    async fn get_reactions(
+        &self,
+        correlation_id: &Uuid,
+    ) -> Result<Vec<EventRead<Reaction<Change, Command>, (), V>>> {
+        self.reactions
+            .get_events(&self.stream_id(correlation_id), &EventsReadRange::AllEvents)
+            .await
+    }
+
+    async fn dispatch(&self, commands: &[Command]) -> Result<()> {
+        for command in commands {
+            self.dispatcher.dispatch(command).await?;
+        }
+        Ok(())
+    }
+
+    /// State of the process correlated by `correlation_id`
+    pub async fn get_state(&self, correlation_id: &Uuid) -> Result<State> {
+        let reactions = self.get_reactions(correlation_id).await?;
+        Ok(self.fold(&reactions))
+    }
+}
+
+#[async_trait]
+impl<State, Event, Change, Command, Error, Meta, V> Projection<Event, Meta, V>
+    for ProcessHandler<'_, State, Event, Change, Command, Error, V>
+where
+    State: Send,
+    Event: Sync,
+    Change: Send + Sync,
+    Command: Send + Sync,
+    Error: StdError + Send + Sync + 'static,
+    Meta: Sync,
+    V: StoreVersion<V> + Eq + Send + Sync,
+{
+    fn name(&self) -> &str {
+        self.manager.name()
+    }
+
+    fn event_names(&self) -> Option<Vec<&str>> {
+        self.manager.event_names()
+    }
+
+    // Events without a correlation id don't belong to any process
+    async fn handle(&self, event: &EventRead<Event, Meta, V>) -> Result<()> {
+        let correlation_id = match event.correlation_id {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        let stream_id = self.stream_id(&correlation_id);
+        // Read before the reactions, reactions recorded in between make the append conflict
+        let last_version = match self.reactions.get_stream(&stream_id).await {
+            Ok(s) => Some(s.last_version),
+            Err(EventStoreError::StreamNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let reactions = self.get_reactions(&correlation_id).await?;
+        if let Some(r) = reactions.iter().find(|r| r.causation_id == Some(event.id)) {
+            return self.dispatch(&r.data.commands).await;
+        }
+
+        let state = self.fold(&reactions);
+        let reaction = self.manager.react(&state, &event.data).map_err(|e| {
+            EventStoreError::ReactionFailed {
+                process: self.manager.name().to_string(),
+                event_id: event.id,
+                source: Box::new(e),
+            }
+        })?;
+        let expected_version = match last_version {
+            Some(v) => ExpectedVersion::Exact(v.next_version(&stream_id, &ExpectedVersion::Any)?),
+            None => ExpectedVersion::NoStream,
+        };
+        // Reactions are identified by their event, so the store still refuses to record an event
+        // again while its reaction is hidden by the retention settings of the process stream
+        let recorded = match self
+            .reactions
+            .append_event(
+                &stream_id,
+                &expected_version,
+                &EventWrite {
+                    id: Uuid::new_v5(&event.id, stream_id.as_bytes()),
+                    correlation_id: Some(correlation_id),
+                    causation_id: Some(event.id),
+                    name: String::from("Reacted"),
+                    data: reaction,
+                    metadata: None,
+                },
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(EventStoreError::DuplicateEvents { .. }) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        self.dispatch(&recorded.data.commands).await
+    }
+}
@@ -0,0 +1,137 @@
+use crate::subscription::SubscriptionSettings;
+use async_trait::async_trait;
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_store_error::Result;
+use futures::future;
+
+/**
+Read model built from the events of all streams.
+Events are handed over in position order and at least once: events handled after the last
+saved checkpoint are handled again when the runner restarts.
+*/
+#[async_trait]
+pub trait Projection<Payload, Meta, V> {
+    /// Identifies the checkpoint of the projection
+    fn name(&self) -> &str;
+    /// Names of the events the projection handles, none handles all events
+    fn event_names(&self) -> Option<Vec<&str>> {
+        None
+    }
+    async fn handle(&self, event: &EventRead<Payload, Meta, V>) -> Result<()>;
+    /// Drops the read model before it is rebuilt from scratch
+    async fn reset(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/**
+Feeds a projection the events of a store, starting after its checkpoint.
+The checkpoint is saved after every page of events, and before giving up on a failed event,
+so the failed event is the first one handled by the next run.
+*/
+pub struct ProjectionRunner<'a, Payload, Meta, V> {
+    store: &'a (dyn EventStore<Payload, Meta, V> + Sync),
+    checkpoints: &'a (dyn CheckpointStore + Sync),
+    projection: &'a (dyn Projection<Payload, Meta, V> + Sync),
+    settings: SubscriptionSettings,
+}
+
+impl<'a, Payload, Meta, V> ProjectionRunner<'a, Payload, Meta, V>
+where
+    V: Eq,
+{
+    pub fn new(
+        store: &'a (dyn EventStore<Payload, Meta, V> + Sync),
+        checkpoints: &'a (dyn CheckpointStore + Sync),
+        projection: &'a (dyn Projection<Payload, Meta, V> + Sync),
+        settings: SubscriptionSettings,
+    ) -> Self {
+        ProjectionRunner {
+            store,
+            checkpoints,
+            projection,
+            settings,
+        }
+    }
+
+    async fn save_checkpoint(&self, saved: Option<i64>, done: Option<i64>) -> Result<()> {
+        match done {
+            Some(position) if done != saved => {
+                self.checkpoints
+                    .save_checkpoint(self.projection.name(), position)
+                    .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Handles the events written after the checkpoint, returns how many were handled
+    pub async fn catch_up(&self) -> Result<usize> {
+        let names = self.projection.event_names();
+        let mut checkpoint = self
+            .checkpoints
+            .get_checkpoint(self.projection.name())
+            .await?;
+        let mut handled = 0;
+        loop {
+            let from_position = checkpoint.map_or(0, |c| c + 1);
+            let page = self
+                .store
+                .get_all_events(from_position, self.settings.page_size)
+                .await?;
+            if page.is_empty() {
+                return Ok(handled);
+            }
+            let mut done = checkpoint;
+            for event in &page {
+                let wanted = names
+                    .as_ref()
+                    .is_none_or(|n| n.contains(&event.name.as_str()));
+                if wanted {
+                    if let Err(e) = self.projection.handle(event).await {
+                        self.save_checkpoint(checkpoint, done).await?;
+                        return Err(e);
+                    }
+                    handled += 1;
+                }
+                done = Some(event.position);
+            }
+            self.save_checkpoint(checkpoint, done).await?;
+            checkpoint = done;
+        }
+    }
+
+    /// Resets the checkpoint and the projection, then handles every event again
+    pub async fn rebuild(&self) -> Result<usize> {
+        // Checkpoint goes first, a rebuild interrupted in between starts over instead of
+        // resuming on top of an empty read model
+        self.checkpoints
+            .reset_checkpoint(self.projection.name())
+            .await?;
+        self.projection.reset().await?;
+        self.catch_up().await
+    }
+
+    /// Keeps catching up until an event can't be handled, waiting `poll_interval` or until
+    /// woken after each catch up
+    pub async fn run(&self) -> Result<()> {
+        loop {
+            // Registered before catching up, so events appended in between still wake us up
+            let wake = self.settings.wake.clone();
+            let mut notified = wake.as_ref().map(|w| Box::pin(w.notified()));
+            if let Some(n) = notified.as_mut() {
+                n.as_mut().enable();
+            }
+            let _ = self.catch_up().await?;
+            let sleep = Box::pin(tokio::time::sleep(self.settings.poll_interval));
+            match notified {
+                Some(n) => {
+                    let _ = future::select(n, sleep).await;
+                }
+                None => sleep.await,
+            }
+        }
+    }
+}
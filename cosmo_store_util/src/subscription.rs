@@ -0,0 +1,189 @@
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::version::Version;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::{EventsReadRange, ReadDirection};
+use cosmo_store::types::event_store_error::Result;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use futures::stream::{self, AbortHandle, Abortable, BoxStream};
+use futures::{future, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+#[derive(Clone, Debug)]
+pub enum SubscriptionStart<V> {
+    /// Events of a single stream, starting at `from_version`
+    Stream { stream_id: String, from_version: V },
+    /// Events of all streams, starting at `from_position`
+    AllStreams { from_position: i64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct SubscriptionSettings {
+    /// Number of events fetched from the store at once
+    pub page_size: usize,
+    /// Delay before asking the store again once the subscription caught up
+    pub poll_interval: Duration,
+    /// Wakes the subscription before `poll_interval` elapsed, e.g. when the store announces new events
+    pub wake: Option<Arc<Notify>>,
+}
+
+impl Default for SubscriptionSettings {
+    fn default() -> Self {
+        SubscriptionSettings {
+            page_size: 100,
+            poll_interval: Duration::from_millis(500),
+            wake: None,
+        }
+    }
+}
+
+struct Cursor<Payload, Meta, V> {
+    start: SubscriptionStart<V>,
+    buffer: VecDeque<EventRead<Payload, Meta, V>>,
+    failed: bool,
+}
+
+impl<Payload, Meta, V: Version<V>> Cursor<Payload, Meta, V> {
+    fn advance(&mut self, last: &EventRead<Payload, Meta, V>) -> Result<()> {
+        match &mut self.start {
+            SubscriptionStart::Stream {
+                stream_id,
+                from_version,
+            } => {
+                *from_version = last
+                    .version
+                    .next_version(stream_id, &ExpectedVersion::Any)?
+            }
+            SubscriptionStart::AllStreams { from_position } => *from_position = last.position + 1,
+        }
+        Ok(())
+    }
+}
+
+async fn fetch_page<Payload, Meta, V, S>(
+    store: &S,
+    start: &SubscriptionStart<V>,
+    page_size: usize,
+) -> Result<Vec<EventRead<Payload, Meta, V>>>
+where
+    S: EventStore<Payload, Meta, V> + ?Sized,
+    V: Eq + Clone,
+{
+    match start {
+        SubscriptionStart::Stream {
+            stream_id,
+            from_version,
+        } => {
+            let page = EventsReadRange::Page {
+                from_version: Some(from_version.clone()),
+                direction: ReadDirection::Forward,
+                max_count: page_size,
+            };
+            store.get_events(stream_id, &page).await
+        }
+        SubscriptionStart::AllStreams { from_position } => {
+            store.get_all_events(*from_position, page_size).await
+        }
+    }
+}
+
+/**
+Catch-up subscription over an event store.
+Replays the events already in the store and then keeps polling for new ones.
+Events are only fetched when the consumer asks for them, dropping the subscription
+or aborting it through its `AbortHandle` stops it.
+*/
+pub struct Subscription<'a, Payload, Meta, V> {
+    inner: Abortable<BoxStream<'a, Result<EventRead<Payload, Meta, V>>>>,
+    handle: AbortHandle,
+}
+
+impl<Payload, Meta, V> Subscription<'_, Payload, Meta, V> {
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.handle.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.handle.abort()
+    }
+}
+
+impl<Payload, Meta, V> Stream for Subscription<'_, Payload, Meta, V> {
+    type Item = Result<EventRead<Payload, Meta, V>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+pub fn subscribe<'a, Payload, Meta, V, S>(
+    store: &'a S,
+    start: SubscriptionStart<V>,
+    settings: SubscriptionSettings,
+) -> Subscription<'a, Payload, Meta, V>
+where
+    S: EventStore<Payload, Meta, V> + Sync + ?Sized,
+    Payload: Send + 'a,
+    Meta: Send + 'a,
+    V: Version<V> + Eq + Clone + Send + Sync + 'a,
+{
+    let cursor = Cursor {
+        start,
+        buffer: VecDeque::new(),
+        failed: false,
+    };
+    let events = stream::unfold(cursor, move |mut cursor| {
+        let settings = settings.clone();
+        async move {
+            if cursor.failed {
+                return None;
+            }
+            loop {
+                if let Some(event) = cursor.buffer.pop_front() {
+                    return Some((Ok(event), cursor));
+                }
+                // Registered before fetching, so events appended in between still wake us up
+                let wake = settings.wake.clone();
+                let mut notified = wake.as_ref().map(|w| Box::pin(w.notified()));
+                if let Some(n) = notified.as_mut() {
+                    n.as_mut().enable();
+                }
+                let page = fetch_page(store, &cursor.start, settings.page_size)
+                    .await
+                    .and_then(|page| {
+                        if let Some(last) = page.last() {
+                            cursor.advance(last)?;
+                        }
+                        Ok(page)
+                    });
+                match page {
+                    Ok(page) if page.is_empty() => {
+                        let sleep = Box::pin(tokio::time::sleep(settings.poll_interval));
+                        match notified {
+                            Some(n) => {
+                                let _ = future::select(n, sleep).await;
+                            }
+                            None => sleep.await,
+                        }
+                    }
+                    Ok(page) => cursor.buffer.extend(page),
+                    Err(e) => {
+                        cursor.failed = true;
+                        return Some((Err(e), cursor));
+                    }
+                }
+            }
+        }
+    })
+    .boxed();
+    let (handle, registration) = AbortHandle::new_pair();
+
+    Subscription {
+        inner: Abortable::new(events, registration),
+        handle,
+    }
+}
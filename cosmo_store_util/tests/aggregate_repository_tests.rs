@@ -0,0 +1,278 @@
+use anyhow::{bail, Result};
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::delete_mode::DeleteMode;
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_tests::event_generator::get_stream_id;
+use cosmo_store_util::aggregate::{Aggregate, AggregateRepository, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+type Store = EventStoreInMemory<CounterEvent, (), EventVersion>;
+
+#[derive(Clone, Debug)]
+enum CounterCommand {
+    Increment { max: i32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum CounterEvent {
+    Incremented,
+}
+
+impl From<CounterEvent> for EventWrite<CounterEvent, ()> {
+    fn from(e: CounterEvent) -> Self {
+        EventWrite {
+            id: Uuid::new_v4(),
+            correlation_id: None,
+            causation_id: None,
+            name: String::from("Incremented"),
+            data: e,
+            metadata: None,
+        }
+    }
+}
+
+// Counts executions, a concurrent writer increments the stream during the first `interferences`
+#[derive(Default)]
+struct CounterAggregate<'a> {
+    rival: Option<(&'a Store, String)>,
+    interferences: AtomicUsize,
+    executed: AtomicUsize,
+}
+
+impl<'a> CounterAggregate<'a> {
+    fn contended(store: &'a Store, stream_id: &str, interferences: usize) -> Self {
+        CounterAggregate {
+            rival: Some((store, stream_id.to_string())),
+            interferences: AtomicUsize::new(interferences),
+            executed: AtomicUsize::new(0),
+        }
+    }
+
+    fn executed(&self) -> usize {
+        self.executed.load(Ordering::SeqCst)
+    }
+}
+
+impl Aggregate<i32, CounterCommand, CounterEvent> for CounterAggregate<'_> {
+    fn init(&self) -> i32 {
+        0
+    }
+
+    fn apply(&self, state: i32, event: &CounterEvent) -> i32 {
+        match event {
+            CounterEvent::Incremented => state + 1,
+        }
+    }
+
+    fn execute(&self, state: &i32, command: &CounterCommand) -> Result<Vec<CounterEvent>> {
+        let _ = self.executed.fetch_add(1, Ordering::SeqCst);
+        if let Some((store, stream_id)) = &self.rival {
+            let interferences = self.interferences.load(Ordering::SeqCst);
+            if interferences > 0 {
+                self.interferences
+                    .store(interferences - 1, Ordering::SeqCst);
+                let _ = futures::executor::block_on(store.append_event(
+                    stream_id,
+                    &ExpectedVersion::Any,
+                    &CounterEvent::Incremented.into(),
+                ))?;
+            }
+        }
+        match command {
+            CounterCommand::Increment { max } if state >= max => bail!("Counter is at {}", max),
+            CounterCommand::Increment { .. } => Ok(vec![CounterEvent::Incremented]),
+        }
+    }
+}
+
+fn retry_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        retry_delay: Duration::ZERO,
+    }
+}
+
+#[actix_rt::test]
+async fn handles_commands_at_the_loaded_version() {
+    let store = Store::new();
+    let aggregate = CounterAggregate::default();
+    let repository = AggregateRepository::new(&aggregate, &store);
+    let stream_id = get_stream_id();
+
+    let first = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await
+        .unwrap();
+    assert_eq!(first.state, 1);
+    assert_eq!(first.version, Some(EventVersion::new(1)));
+    assert_eq!(first.events.len(), 1);
+
+    let second = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await
+        .unwrap();
+    assert_eq!(second.state, 2);
+    assert_eq!(second.version, Some(EventVersion::new(2)));
+    assert_eq!(second.events[0].version, EventVersion::new(2));
+    assert_eq!(
+        repository.load(&stream_id).await.unwrap(),
+        (2, Some(EventVersion::new(2)))
+    );
+}
+
+#[actix_rt::test]
+async fn handles_commands_after_retention_hid_events() {
+    let store = Store::new();
+    let aggregate = CounterAggregate::default();
+    let repository =
+        AggregateRepository::new(&aggregate, &store).with_retry_policy(retry_policy(0));
+    let stream_id = get_stream_id();
+
+    for _ in 0..3 {
+        let _ = repository
+            .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+            .await
+            .unwrap();
+    }
+    store
+        .set_stream_metadata(
+            &stream_id,
+            &StreamMetadata {
+                truncate_before: Some(EventVersion::new(4)),
+                ..StreamMetadata::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        repository.load(&stream_id).await.unwrap(),
+        (0, Some(EventVersion::new(3)))
+    );
+
+    let handled = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await
+        .unwrap();
+    assert_eq!(handled.state, 1);
+    assert_eq!(handled.version, Some(EventVersion::new(4)));
+    assert_eq!(handled.events[0].version, EventVersion::new(4));
+}
+
+#[actix_rt::test]
+async fn handles_commands_on_deleted_streams() {
+    let store = Store::new();
+    let aggregate = CounterAggregate::default();
+    let repository =
+        AggregateRepository::new(&aggregate, &store).with_retry_policy(retry_policy(0));
+    let stream_id = get_stream_id();
+
+    for _ in 0..2 {
+        let _ = repository
+            .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+            .await
+            .unwrap();
+    }
+    store
+        .delete_stream(&stream_id, DeleteMode::Soft)
+        .await
+        .unwrap();
+    assert_eq!(repository.load(&stream_id).await.unwrap(), (0, None));
+
+    let handled = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await
+        .unwrap();
+    assert_eq!(handled.state, 1);
+    assert_eq!(handled.events.len(), 1);
+}
+
+#[actix_rt::test]
+async fn retries_command_after_conflict() {
+    let store = Store::new();
+    let stream_id = get_stream_id();
+    let aggregate = CounterAggregate::contended(&store, &stream_id, 2);
+    let repository =
+        AggregateRepository::new(&aggregate, &store).with_retry_policy(retry_policy(2));
+
+    let res = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await
+        .unwrap();
+    assert_eq!(aggregate.executed(), 3);
+    assert_eq!(res.state, 3);
+    assert_eq!(res.version, Some(EventVersion::new(3)));
+    assert_eq!(res.events.len(), 1);
+}
+
+#[actix_rt::test]
+async fn retried_command_sees_the_new_state() {
+    let store = Store::new();
+    let stream_id = get_stream_id();
+    let aggregate = CounterAggregate::contended(&store, &stream_id, 1);
+    let repository =
+        AggregateRepository::new(&aggregate, &store).with_retry_policy(retry_policy(2));
+
+    let res = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 1 })
+        .await;
+    assert!(res.is_err());
+    assert_eq!(aggregate.executed(), 2);
+    assert_eq!(repository.load(&stream_id).await.unwrap().0, 1);
+}
+
+#[actix_rt::test]
+async fn gives_up_after_retry_policy() {
+    let store = Store::new();
+    let stream_id = get_stream_id();
+    let aggregate = CounterAggregate::contended(&store, &stream_id, usize::MAX);
+    let repository =
+        AggregateRepository::new(&aggregate, &store).with_retry_policy(retry_policy(2));
+
+    let res = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 5 })
+        .await;
+    let err = res.err().unwrap();
+    assert!(err
+        .downcast_ref::<EventStoreError>()
+        .unwrap()
+        .is_concurrency_conflict());
+    assert_eq!(aggregate.executed(), 3);
+}
+
+#[actix_rt::test]
+async fn handles_commands_in_sqlite() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let store = EventStoreSQLXSqlite::new(&pool, "counter").await.unwrap();
+    let aggregate = CounterAggregate::default();
+    let repository = AggregateRepository::new(&aggregate, &store);
+    let stream_id = get_stream_id();
+
+    for _ in 0..3 {
+        let _ = repository
+            .handle(&stream_id, &CounterCommand::Increment { max: 3 })
+            .await
+            .unwrap();
+    }
+    let res = repository
+        .handle(&stream_id, &CounterCommand::Increment { max: 3 })
+        .await;
+    assert!(res.is_err());
+    assert_eq!(
+        repository.load(&stream_id).await.unwrap(),
+        (3, Some(EventVersion::new(3)))
+    );
+}
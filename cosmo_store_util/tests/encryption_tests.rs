@@ -0,0 +1,266 @@
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_store_error::EventStoreError;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_in_memory::key_store::KeyStoreInMemory;
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_sqlx_postgres::key_store_sqlx_postgres::KeyStoreSQLXPostgres;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_sqlx_sqlite::key_store_sqlx_sqlite::KeyStoreSQLXSqlite;
+use cosmo_store_tests::event_generator::get_stream_id;
+use cosmo_store_util::encryption::{EncryptedEventStore, Sealed, Shreddable};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::SqlitePoolOptions;
+use uuid::Uuid;
+
+const PG_CONN_BASE: &str = "postgresql://localhost:5432/";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Person {
+    name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Audit {
+    user: String,
+}
+
+type Event = EventRead<Shreddable<Person>, Shreddable<Audit>, EventVersion>;
+
+fn get_people(names: &[&str]) -> Vec<EventWrite<Shreddable<Person>, Shreddable<Audit>>> {
+    names
+        .iter()
+        .map(|name| EventWrite {
+            id: Uuid::new_v4(),
+            correlation_id: None,
+            causation_id: None,
+            name: String::from("PersonRegistered"),
+            data: Shreddable::Readable(Person {
+                name: name.to_string(),
+            }),
+            metadata: Some(Shreddable::Readable(Audit {
+                user: String::from("admin"),
+            })),
+        })
+        .collect()
+}
+
+fn get_in_memory_store(
+) -> EncryptedEventStore<EventStoreInMemory<Sealed, Sealed, EventVersion>, KeyStoreInMemory> {
+    EncryptedEventStore::new(EventStoreInMemory::new(), KeyStoreInMemory::new())
+}
+
+async fn read(
+    store: &(dyn EventStore<Shreddable<Person>, Shreddable<Audit>, EventVersion> + Sync),
+    stream_id: &str,
+) -> Vec<Event> {
+    store
+        .get_events(stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap()
+}
+
+fn names(events: &[Event]) -> Vec<Option<String>> {
+    events
+        .iter()
+        .map(|e| e.data.clone().readable().map(|p| p.name))
+        .collect()
+}
+
+#[actix_rt::test]
+async fn encrypts_payload_and_metadata() {
+    let store = get_in_memory_store();
+    let stream_id = get_stream_id();
+    let written = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_people(&["Ann"]))
+        .await
+        .unwrap();
+    assert_eq!(names(&written), vec![Some(String::from("Ann"))]);
+
+    let events = read(&store, &stream_id).await;
+    assert_eq!(names(&events), vec![Some(String::from("Ann"))]);
+    assert_eq!(
+        events[0].metadata,
+        Some(Shreddable::Readable(Audit {
+            user: String::from("admin")
+        }))
+    );
+
+    let stored = store
+        .store()
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    assert_eq!(stored[0].data.subject, stream_id);
+    assert!(!String::from_utf8_lossy(&stored[0].data.ciphertext).contains("Ann"));
+}
+
+#[actix_rt::test]
+async fn shredded_events_read_as_marker() {
+    let store = get_in_memory_store();
+    let shredded = get_stream_id();
+    let kept = get_stream_id();
+    for (stream_id, name) in [(&shredded, "Ann"), (&kept, "Bob")] {
+        let _ = store
+            .append_events(stream_id, &ExpectedVersion::Any, get_people(&[name]))
+            .await
+            .unwrap();
+    }
+
+    store.shred(&shredded).await.unwrap();
+
+    let events = read(&store, &shredded).await;
+    assert_eq!(events.len(), 1);
+    assert!(events[0].data.is_shredded());
+    assert_eq!(events[0].metadata, Some(Shreddable::Shredded));
+    assert_eq!(
+        names(&read(&store, &kept).await),
+        vec![Some(String::from("Bob"))]
+    );
+
+    let res = store
+        .append_events(&shredded, &ExpectedVersion::Any, get_people(&["Ann"]))
+        .await;
+    assert!(matches!(res, Err(EventStoreError::SubjectShredded(_))));
+}
+
+#[actix_rt::test]
+async fn shreds_all_streams_of_subject() {
+    let store = get_in_memory_store()
+        .with_subject(|stream_id| stream_id.split('/').next().unwrap().to_string());
+    let person = get_stream_id();
+    let profile = format!("{}/profile", person);
+    let orders = format!("{}/orders", person);
+    for stream_id in [&profile, &orders] {
+        let _ = store
+            .append_events(stream_id, &ExpectedVersion::Any, get_people(&["Ann"]))
+            .await
+            .unwrap();
+    }
+
+    store.shred(&person).await.unwrap();
+
+    for stream_id in [&profile, &orders] {
+        assert_eq!(names(&read(&store, stream_id).await), vec![None]);
+    }
+}
+
+#[actix_rt::test]
+async fn sealed_values_only_open_in_their_event() {
+    // Every stream shares the subject, so the copy is encrypted with the right key
+    let store = get_in_memory_store().with_subject(|_| String::from("person"));
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_people(&["Ann"]))
+        .await
+        .unwrap();
+    let stored = store
+        .store()
+        .get_events(&stream_id, &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+
+    let copied_id = get_stream_id();
+    let copy = EventWrite {
+        id: Uuid::new_v4(),
+        correlation_id: None,
+        causation_id: None,
+        name: stored[0].name.clone(),
+        data: stored[0].data.clone(),
+        metadata: stored[0].metadata.clone(),
+    };
+    let _ = store
+        .store()
+        .append_event(&copied_id, &ExpectedVersion::Any, &copy)
+        .await
+        .unwrap();
+
+    let res: Result<Vec<Event>, _> = store
+        .get_events(&copied_id, &EventsReadRange::AllEvents)
+        .await;
+    assert!(matches!(res, Err(EventStoreError::Serialization(_))));
+    assert_eq!(
+        names(&read(&store, &stream_id).await),
+        vec![Some(String::from("Ann"))]
+    );
+}
+
+#[actix_rt::test]
+async fn shreds_events_in_sqlite() {
+    // In-memory database lives per connection, both stores have to share a single one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let store = EncryptedEventStore::new(
+        EventStoreSQLXSqlite::new(&pool, "person").await.unwrap(),
+        KeyStoreSQLXSqlite::new(&pool, "person").await.unwrap(),
+    );
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(
+            &stream_id,
+            &ExpectedVersion::Any,
+            get_people(&["Ann", "Bob"]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        names(&read(&store, &stream_id).await),
+        vec![Some(String::from("Ann")), Some(String::from("Bob"))]
+    );
+
+    store.shred(&stream_id).await.unwrap();
+
+    assert_eq!(names(&read(&store, &stream_id).await), vec![None, None]);
+}
+
+#[actix_rt::test]
+async fn shreds_events_in_postgres() {
+    let name = Uuid::new_v4().as_simple().to_string();
+    let server = PgPoolOptions::new().connect(PG_CONN_BASE).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&server).await.unwrap();
+
+    let pool = PgPoolOptions::new()
+        .connect(&format!("{}{}", PG_CONN_BASE, name))
+        .await
+        .unwrap();
+    let store = EncryptedEventStore::new(
+        EventStoreSQLXPostgres::new(&pool, "person").await.unwrap(),
+        KeyStoreSQLXPostgres::new(&pool, "person").await.unwrap(),
+    );
+    let stream_id = get_stream_id();
+    let _ = store
+        .append_events(
+            &stream_id,
+            &ExpectedVersion::Any,
+            get_people(&["Ann", "Bob"]),
+        )
+        .await
+        .unwrap();
+    let before = names(&read(&store, &stream_id).await);
+    store.shred(&stream_id).await.unwrap();
+    let after = names(&read(&store, &stream_id).await);
+    pool.close().await;
+
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let drop_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&server).await.unwrap();
+    let _ = sqlx::query(&drop_db).execute(&server).await.unwrap();
+
+    assert_eq!(
+        before,
+        vec![Some(String::from("Ann")), Some(String::from("Bob"))]
+    );
+    assert_eq!(after, vec![None, None]);
+}
@@ -1,10 +1,8 @@
-#[cfg(test)]
-#[macro_use]
-extern crate claim;
-
-use cosmo_store::common::i64_event_version::EventVersion;
+use anyhow::Result;
+use cosmo_store::common::event_version::EventVersion;
 use cosmo_store::traits::event_store::EventStore;
 use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_write::EventWrite;
 use cosmo_store::types::expected_version::ExpectedVersion;
 use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
 use cosmo_store_tests::event_generator::get_stream_id;
@@ -12,16 +10,12 @@ use cosmo_store_util::aggregate::{make_handler, Aggregate};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPoolOptions;
 use uuid::Uuid;
-use anyhow::Result;
-use futures;
-use sqlx::PgPool;
-use cosmo_store::types::event_write::EventWrite;
 
 const CONN_BASE: &str = "postgresql://localhost:5432/";
 
 async fn setup(name: &str) {
     println!("Event Store will be initialized here...");
-    let conn_str = format!("{}", CONN_BASE);
+    let conn_str = CONN_BASE.to_string();
     let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
     let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
     let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
@@ -30,7 +24,7 @@ async fn setup(name: &str) {
 
 async fn teardown(name: &str) {
     println!("Event Store will be destroyed here...");
-    let conn_str = format!("{}", CONN_BASE);
+    let conn_str = CONN_BASE.to_string();
     let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
     let kill_conn = format!(
         "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
@@ -99,6 +93,7 @@ impl From<TodoEvent> for EventWrite<TodoEvent, TodoEvent> {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct Todo {
     id: Uuid,
@@ -175,7 +170,7 @@ impl Aggregate<TodoState, TodoCommand, TodoEvent> for TodoAggregate {
         }
     }
 
-    fn execute(&self, state: &TodoState, command: &TodoCommand) -> Result<Vec<TodoEvent>> {
+    fn execute(&self, _state: &TodoState, command: &TodoCommand) -> Result<Vec<TodoEvent>> {
         let res = match command {
             TodoCommand::AddTodo(t) => vec![TodoEvent::TodoAdded(t.clone())],
             TodoCommand::RemoveTodo(t) => vec![TodoEvent::TodoRemoved(t.clone())],
@@ -214,14 +209,14 @@ async fn add_state() {
         &EventsReadRange::AllEvents,
         &ExpectedVersion::Any,
     )
-        .await
-        .unwrap();
+    .await
+    .unwrap();
 
     assert_eq!(res.len(), 1);
 
-    let state = res
-        .iter()
-        .fold(TODO_AGGREGATE.init(), |a, b| TODO_AGGREGATE.apply(a, &b.data));
+    let state = res.iter().fold(TODO_AGGREGATE.init(), |a, b| {
+        TODO_AGGREGATE.apply(a, &b.data)
+    });
 
     for r in &res {
         println!("{:?} - {:?}", r.stream_id, r.data);
@@ -229,5 +224,4 @@ async fn add_state() {
     assert_eq!(state.todos.len(), 1);
 
     teardown(&name).await;
-
 }
@@ -0,0 +1,443 @@
+use async_trait::async_trait;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read_range::EventsReadRange;
+use cosmo_store::types::event_store_error::{EventStoreError, Result as StoreResult};
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store_in_memory::checkpoint_store::CheckpointStoreInMemory;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_sqlx_sqlite::checkpoint_store_sqlx_sqlite::CheckpointStoreSQLXSqlite;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_util::process_manager::{
+    CommandDispatcher, ProcessHandler, ProcessManager, Reaction,
+};
+use cosmo_store_util::projection::{Projection, ProjectionRunner};
+use cosmo_store_util::subscription::SubscriptionSettings;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePoolOptions;
+use std::fmt;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ShopEvent {
+    OrderPlaced { amount: u32 },
+    PaymentReceived,
+    OrderShipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Fulfillment {
+    New,
+    AwaitingPayment,
+    AwaitingShipment,
+    Done,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ShopCommand {
+    RequestPayment { amount: u32 },
+    ShipOrder,
+}
+
+type Reactions = Reaction<Fulfillment, ShopCommand>;
+
+#[derive(Debug, PartialEq)]
+enum FulfillmentError {
+    EmptyOrder,
+}
+
+impl fmt::Display for FulfillmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Order has nothing to pay for")
+    }
+}
+
+impl std::error::Error for FulfillmentError {}
+
+type Handler<'a> = ProcessHandler<
+    'a,
+    Fulfillment,
+    ShopEvent,
+    Fulfillment,
+    ShopCommand,
+    FulfillmentError,
+    EventVersion,
+>;
+
+// Order -> payment -> shipping, the state changes are the states themselves
+struct FulfillmentProcess;
+
+impl ProcessManager<Fulfillment, ShopEvent, Fulfillment, ShopCommand> for FulfillmentProcess {
+    type Error = FulfillmentError;
+
+    fn name(&self) -> &str {
+        "fulfillment"
+    }
+
+    fn init(&self) -> Fulfillment {
+        Fulfillment::New
+    }
+
+    fn apply(&self, _state: Fulfillment, change: &Fulfillment) -> Fulfillment {
+        change.clone()
+    }
+
+    fn react(&self, state: &Fulfillment, event: &ShopEvent) -> Result<Reactions, FulfillmentError> {
+        let reaction = match (state, event) {
+            (Fulfillment::New, ShopEvent::OrderPlaced { amount: 0 }) => {
+                return Err(FulfillmentError::EmptyOrder)
+            }
+            (Fulfillment::New, ShopEvent::OrderPlaced { amount }) => Reaction {
+                changes: vec![Fulfillment::AwaitingPayment],
+                commands: vec![ShopCommand::RequestPayment { amount: *amount }],
+            },
+            (Fulfillment::AwaitingPayment, ShopEvent::PaymentReceived) => Reaction {
+                changes: vec![Fulfillment::AwaitingShipment],
+                commands: vec![ShopCommand::ShipOrder],
+            },
+            (Fulfillment::AwaitingShipment, ShopEvent::OrderShipped) => Reaction {
+                changes: vec![Fulfillment::Done],
+                commands: vec![],
+            },
+            _ => Reaction::default(),
+        };
+        Ok(reaction)
+    }
+}
+
+#[derive(Default)]
+struct RecordingDispatcher {
+    commands: Mutex<Vec<ShopCommand>>,
+}
+
+impl RecordingDispatcher {
+    fn commands(&self) -> Vec<ShopCommand> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl CommandDispatcher<ShopCommand> for RecordingDispatcher {
+    async fn dispatch(&self, command: &ShopCommand) -> StoreResult<()> {
+        self.commands.lock().unwrap().push(command.clone());
+        Ok(())
+    }
+}
+
+fn shop_event(event: ShopEvent, correlation_id: Option<Uuid>) -> EventWrite<ShopEvent, ()> {
+    EventWrite {
+        id: Uuid::new_v4(),
+        correlation_id,
+        causation_id: None,
+        name: String::from("ShopEvent"),
+        data: event,
+        metadata: None,
+    }
+}
+
+async fn append(
+    store: &(dyn EventStore<ShopEvent, (), EventVersion> + Sync),
+    stream_id: &str,
+    event: ShopEvent,
+    correlation_id: Option<Uuid>,
+) {
+    let _ = store
+        .append_event(
+            stream_id,
+            &ExpectedVersion::Any,
+            &shop_event(event, correlation_id),
+        )
+        .await
+        .unwrap();
+}
+
+async fn catch_up(
+    store: &(dyn EventStore<ShopEvent, (), EventVersion> + Sync),
+    checkpoints: &(dyn CheckpointStore + Sync),
+    handler: &Handler<'_>,
+) -> usize {
+    try_catch_up(store, checkpoints, handler).await.unwrap()
+}
+
+async fn try_catch_up(
+    store: &(dyn EventStore<ShopEvent, (), EventVersion> + Sync),
+    checkpoints: &(dyn CheckpointStore + Sync),
+    handler: &Handler<'_>,
+) -> StoreResult<usize> {
+    ProjectionRunner::new(store, checkpoints, handler, SubscriptionSettings::default())
+        .catch_up()
+        .await
+}
+
+#[actix_rt::test]
+async fn reacts_to_events_of_several_streams() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    let other = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        Some(order),
+    )
+    .await;
+    append(
+        &store,
+        "order-2",
+        ShopEvent::OrderPlaced { amount: 20 },
+        Some(other),
+    )
+    .await;
+    append(&store, "payment-1", ShopEvent::PaymentReceived, Some(order)).await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+
+    assert_eq!(
+        dispatcher.commands(),
+        vec![
+            ShopCommand::RequestPayment { amount: 10 },
+            ShopCommand::RequestPayment { amount: 20 },
+            ShopCommand::ShipOrder,
+        ]
+    );
+    assert_eq!(
+        handler.get_state(&order).await.unwrap(),
+        Fulfillment::AwaitingShipment
+    );
+    assert_eq!(
+        handler.get_state(&other).await.unwrap(),
+        Fulfillment::AwaitingPayment
+    );
+}
+
+#[actix_rt::test]
+async fn redelivered_events_are_reacted_to_once() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        Some(order),
+    )
+    .await;
+    append(&store, "payment-1", ShopEvent::PaymentReceived, Some(order)).await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+
+    // Lost checkpoint, every event is delivered again
+    checkpoints.reset_checkpoint("fulfillment").await.unwrap();
+    let handled = catch_up(&store, &checkpoints, &handler).await;
+    assert_eq!(handled, 2);
+
+    let recorded = reactions
+        .get_events(&handler.stream_id(&order), &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    assert_eq!(recorded.len(), 2);
+    assert_eq!(
+        handler.get_state(&order).await.unwrap(),
+        Fulfillment::AwaitingShipment
+    );
+    // Recorded commands are dispatched again, they may not have made it the first time
+    assert_eq!(
+        dispatcher.commands(),
+        vec![
+            ShopCommand::RequestPayment { amount: 10 },
+            ShopCommand::ShipOrder,
+            ShopCommand::RequestPayment { amount: 10 },
+            ShopCommand::ShipOrder,
+        ]
+    );
+}
+
+async fn truncate_process(
+    reactions: &EventStoreInMemory<Reactions, (), EventVersion>,
+    stream_id: &str,
+    truncate_before: i64,
+) {
+    let _ = reactions
+        .set_stream_metadata(
+            stream_id,
+            &StreamMetadata {
+                truncate_before: Some(EventVersion::new(truncate_before)),
+                ..StreamMetadata::default()
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[actix_rt::test]
+async fn records_reactions_after_hidden_ones() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        Some(order),
+    )
+    .await;
+    append(&store, "payment-1", ShopEvent::PaymentReceived, Some(order)).await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+    truncate_process(&reactions, &handler.stream_id(&order), 3).await;
+
+    append(&store, "shipping-1", ShopEvent::OrderShipped, Some(order)).await;
+    let handled = catch_up(&store, &checkpoints, &handler).await;
+    assert_eq!(handled, 1);
+
+    let recorded = reactions
+        .get_events(&handler.stream_id(&order), &EventsReadRange::AllEvents)
+        .await
+        .unwrap();
+    let versions: Vec<EventVersion> = recorded.iter().map(|r| r.version.clone()).collect();
+    assert_eq!(versions, vec![EventVersion::new(3)]);
+}
+
+#[actix_rt::test]
+async fn redelivered_events_with_hidden_reactions_are_not_reacted_to_again() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        Some(order),
+    )
+    .await;
+    let placed = store
+        .get_events("order-1", &EventsReadRange::AllEvents)
+        .await
+        .unwrap()
+        .remove(0);
+    handler.handle(&placed).await.unwrap();
+    truncate_process(&reactions, &handler.stream_id(&order), 2).await;
+
+    handler.handle(&placed).await.unwrap();
+
+    assert_eq!(
+        dispatcher.commands(),
+        vec![ShopCommand::RequestPayment { amount: 10 }]
+    );
+    let stream = reactions
+        .get_stream(&handler.stream_id(&order))
+        .await
+        .unwrap();
+    assert_eq!(stream.last_version, EventVersion::new(1));
+}
+
+#[actix_rt::test]
+async fn uncorrelated_events_are_ignored() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        None,
+    )
+    .await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+
+    assert!(dispatcher.commands().is_empty());
+    assert!(reactions.get_all_events(0, 10).await.unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn failed_reactions_surface_the_process_error() {
+    let store = EventStoreInMemory::new();
+    let reactions = EventStoreInMemory::<Reactions, (), EventVersion>::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 0 },
+        Some(order),
+    )
+    .await;
+    let res = try_catch_up(&store, &checkpoints, &handler).await;
+
+    match res {
+        Err(EventStoreError::ReactionFailed {
+            process, source, ..
+        }) => {
+            assert_eq!(process, "fulfillment");
+            assert_eq!(
+                source.downcast_ref::<FulfillmentError>(),
+                Some(&FulfillmentError::EmptyOrder)
+            );
+        }
+        other => panic!("Expected a failed reaction, got {:?}", other),
+    }
+    assert!(dispatcher.commands().is_empty());
+    assert!(reactions.get_all_events(0, 10).await.unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn keeps_process_state_in_sqlite() {
+    // In-memory database lives per connection, every store has to share a single one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let store = EventStoreSQLXSqlite::new(&pool, "shop").await.unwrap();
+    let reactions = EventStoreSQLXSqlite::new(&pool, "fulfillment")
+        .await
+        .unwrap();
+    let checkpoints = CheckpointStoreSQLXSqlite::new(&pool, "shop").await.unwrap();
+    let dispatcher = RecordingDispatcher::default();
+    let handler = ProcessHandler::new(&FulfillmentProcess, &reactions, &dispatcher);
+
+    let order = Uuid::new_v4();
+    append(
+        &store,
+        "order-1",
+        ShopEvent::OrderPlaced { amount: 10 },
+        Some(order),
+    )
+    .await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+    append(&store, "payment-1", ShopEvent::PaymentReceived, Some(order)).await;
+    append(&store, "shipping-1", ShopEvent::OrderShipped, Some(order)).await;
+    let _ = catch_up(&store, &checkpoints, &handler).await;
+
+    assert_eq!(handler.get_state(&order).await.unwrap(), Fulfillment::Done);
+    assert_eq!(
+        dispatcher.commands(),
+        vec![
+            ShopCommand::RequestPayment { amount: 10 },
+            ShopCommand::ShipOrder,
+        ]
+    );
+}
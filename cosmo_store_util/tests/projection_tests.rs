@@ -0,0 +1,245 @@
+use async_trait::async_trait;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::checkpoint_store::CheckpointStore;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::types::event_read::EventRead;
+use cosmo_store::types::event_store_error::{EventStoreError, Result};
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_in_memory::checkpoint_store::CheckpointStoreInMemory;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_sqlx_postgres::checkpoint_store_sqlx_postgres::CheckpointStoreSQLXPostgres;
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_sqlx_sqlite::checkpoint_store_sqlx_sqlite::CheckpointStoreSQLXSqlite;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_tests::event_generator::{get_events, get_stream_id};
+use cosmo_store_tests::event_store_basic_tests::{Meta, Payload};
+use cosmo_store_util::projection::{Projection, ProjectionRunner};
+use cosmo_store_util::subscription::SubscriptionSettings;
+use futures::{future, FutureExt};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+const PG_CONN_BASE: &str = "postgresql://localhost:5432/";
+
+// Remembers the names of the events it handled
+#[derive(Default)]
+struct NamesProjection {
+    names: Mutex<Vec<String>>,
+    only: Option<Vec<&'static str>>,
+    fail_on: Mutex<Option<String>>,
+}
+
+impl NamesProjection {
+    fn names(&self) -> Vec<String> {
+        self.names.lock().unwrap().clone()
+    }
+
+    // Fails the first time it is handed the event named `name`
+    fn fail_once_on(&self, name: &str) {
+        *self.fail_on.lock().unwrap() = Some(name.to_string());
+    }
+}
+
+#[async_trait]
+impl Projection<Payload, Meta, EventVersion> for NamesProjection {
+    fn name(&self) -> &str {
+        "names"
+    }
+
+    fn event_names(&self) -> Option<Vec<&str>> {
+        self.only.clone()
+    }
+
+    async fn handle(&self, event: &EventRead<Payload, Meta, EventVersion>) -> Result<()> {
+        let mut fail_on = self.fail_on.lock().unwrap();
+        if fail_on.as_deref() == Some(event.name.as_str()) {
+            *fail_on = None;
+            return Err(EventStoreError::backend("Read model unavailable"));
+        }
+        self.names.lock().unwrap().push(event.name.clone());
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<()> {
+        self.names.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+fn settings() -> SubscriptionSettings {
+    SubscriptionSettings {
+        page_size: 2,
+        poll_interval: Duration::from_millis(10),
+        wake: None,
+    }
+}
+
+fn created(range: std::ops::RangeInclusive<i32>) -> Vec<String> {
+    range.map(|i| format!("Created_{}", i)).collect()
+}
+
+async fn append(
+    store: &(dyn EventStore<Payload, Meta, EventVersion> + Sync),
+    range: std::ops::RangeInclusive<i32>,
+) {
+    let _ = store
+        .append_events(&get_stream_id(), &ExpectedVersion::Any, get_events(range))
+        .await
+        .unwrap();
+}
+
+// Runs a projection, restarts it with a fresh runner and checks it resumed at its checkpoint
+async fn resumes_from_checkpoint(
+    store: &(dyn EventStore<Payload, Meta, EventVersion> + Sync),
+    checkpoints: &(dyn CheckpointStore + Sync),
+    restarted: &(dyn CheckpointStore + Sync),
+) {
+    let projection = NamesProjection::default();
+    append(store, 1..=3).await;
+    let handled = ProjectionRunner::new(store, checkpoints, &projection, settings())
+        .catch_up()
+        .await
+        .unwrap();
+    assert_eq!(handled, 3);
+
+    append(store, 4..=5).await;
+    let handled = ProjectionRunner::new(store, restarted, &projection, settings())
+        .catch_up()
+        .await
+        .unwrap();
+    assert_eq!(handled, 2);
+    assert_eq!(projection.names(), created(1..=5));
+    assert_eq!(restarted.get_checkpoint("names").await.unwrap(), Some(5));
+}
+
+#[actix_rt::test]
+async fn catches_up_and_resumes_from_checkpoint() {
+    let store = EventStoreInMemory::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    resumes_from_checkpoint(&store, &checkpoints, &checkpoints).await;
+}
+
+#[actix_rt::test]
+async fn handles_only_named_events() {
+    let store = EventStoreInMemory::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let projection = NamesProjection {
+        only: Some(vec!["Created_2", "Created_5"]),
+        ..NamesProjection::default()
+    };
+    append(&store, 1..=5).await;
+
+    let runner = ProjectionRunner::new(&store, &checkpoints, &projection, settings());
+    let handled = runner.catch_up().await.unwrap();
+    assert_eq!(handled, 2);
+    assert_eq!(projection.names(), vec!["Created_2", "Created_5"]);
+    // Skipped events move the checkpoint too
+    assert_eq!(checkpoints.get_checkpoint("names").await.unwrap(), Some(5));
+}
+
+#[actix_rt::test]
+async fn failed_event_is_handled_again() {
+    let store = EventStoreInMemory::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let projection = NamesProjection::default();
+    projection.fail_once_on("Created_4");
+    append(&store, 1..=5).await;
+
+    let runner = ProjectionRunner::new(&store, &checkpoints, &projection, settings());
+    let res = runner.catch_up().await;
+    assert!(res.is_err());
+    assert_eq!(checkpoints.get_checkpoint("names").await.unwrap(), Some(3));
+
+    let handled = runner.catch_up().await.unwrap();
+    assert_eq!(handled, 2);
+    assert_eq!(projection.names(), created(1..=5));
+}
+
+#[actix_rt::test]
+async fn rebuild_starts_from_scratch() {
+    let store = EventStoreInMemory::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let projection = NamesProjection::default();
+    append(&store, 1..=3).await;
+
+    let runner = ProjectionRunner::new(&store, &checkpoints, &projection, settings());
+    let _ = runner.catch_up().await.unwrap();
+    let handled = runner.rebuild().await.unwrap();
+    assert_eq!(handled, 3);
+    assert_eq!(projection.names(), created(1..=3));
+}
+
+#[actix_rt::test]
+async fn run_keeps_handling_new_events() {
+    let store = EventStoreInMemory::new();
+    let checkpoints = CheckpointStoreInMemory::new();
+    let projection = NamesProjection::default();
+    append(&store, 1..=2).await;
+
+    let runner = ProjectionRunner::new(&store, &checkpoints, &projection, settings());
+    let appended = async {
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        append(&store, 3..=4).await;
+        while projection.names().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let _ = future::select(Box::pin(runner.run()), Box::pin(appended)).await;
+    assert_eq!(projection.names(), created(1..=4));
+}
+
+#[actix_rt::test]
+async fn resumes_from_sqlite_checkpoint() {
+    // In-memory database lives per connection, every store has to share a single one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let store = EventStoreSQLXSqlite::new(&pool, "person").await.unwrap();
+    let checkpoints = CheckpointStoreSQLXSqlite::new(&pool, "person")
+        .await
+        .unwrap();
+    let restarted = CheckpointStoreSQLXSqlite::new(&pool, "person")
+        .await
+        .unwrap();
+    resumes_from_checkpoint(&store, &checkpoints, &restarted).await;
+}
+
+#[actix_rt::test]
+async fn resumes_from_postgres_checkpoint() {
+    let name = Uuid::new_v4().as_simple().to_string();
+    let server = PgPoolOptions::new().connect(PG_CONN_BASE).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&server).await.unwrap();
+
+    let pool = PgPoolOptions::new()
+        .connect(&format!("{}{}", PG_CONN_BASE, name))
+        .await
+        .unwrap();
+    let store = EventStoreSQLXPostgres::new(&pool, "person").await.unwrap();
+    let checkpoints = CheckpointStoreSQLXPostgres::new(&pool, "person")
+        .await
+        .unwrap();
+    let restarted = CheckpointStoreSQLXPostgres::new(&pool, "person")
+        .await
+        .unwrap();
+    let result =
+        std::panic::AssertUnwindSafe(resumes_from_checkpoint(&store, &checkpoints, &restarted))
+            .catch_unwind()
+            .await;
+    pool.close().await;
+
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let drop_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&server).await.unwrap();
+    let _ = sqlx::query(&drop_db).execute(&server).await.unwrap();
+
+    assert!(result.is_ok());
+}
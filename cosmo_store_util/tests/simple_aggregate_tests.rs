@@ -50,6 +50,7 @@ impl From<TodoEvent> for EventWrite<TodoEvent, TodoEvent> {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 struct Todo {
     id: Uuid,
@@ -126,7 +127,7 @@ impl Aggregate<TodoState, TodoCommand, TodoEvent> for TodoAggregate {
         }
     }
 
-    fn execute(&self, state: &TodoState, command: &TodoCommand) -> Result<Vec<TodoEvent>> {
+    fn execute(&self, _state: &TodoState, command: &TodoCommand) -> Result<Vec<TodoEvent>> {
         let res = match command {
             TodoCommand::AddTodo(t) => vec![TodoEvent::TodoAdded(t.clone())],
             TodoCommand::RemoveTodo(t) => vec![TodoEvent::TodoRemoved(t.clone())],
@@ -171,7 +172,7 @@ fn add_state() {
     assert_eq!(events.len(), 1);
     let state = events
         .iter()
-        .fold(TODO_AGGREGATE.init(), |a, b| TODO_AGGREGATE.apply(a, &b));
+        .fold(TODO_AGGREGATE.init(), |a, b| TODO_AGGREGATE.apply(a, b));
 
     assert_eq!(state.todos.len(), 1);
 }
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::snapshot_store::SnapshotStore;
+use cosmo_store::types::delete_mode::DeleteMode;
+use cosmo_store::types::event_write::EventWrite;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store::types::stream_metadata::StreamMetadata;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_in_memory::snapshot_store::SnapshotStoreInMemory;
+use cosmo_store_tests::event_generator::get_stream_id;
+use cosmo_store_util::aggregate::{delete_stream, load_state, make_snapshot_handler, Aggregate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum CounterEvent {
+    Incremented(i64),
+}
+
+impl From<CounterEvent> for EventWrite<CounterEvent, ()> {
+    fn from(e: CounterEvent) -> Self {
+        EventWrite {
+            id: Uuid::new_v4(),
+            correlation_id: None,
+            causation_id: None,
+            name: String::from("counter_event"),
+            data: e,
+            metadata: None,
+        }
+    }
+}
+
+struct Counter;
+
+impl Aggregate<i64, i64, CounterEvent> for Counter {
+    fn init(&self) -> i64 {
+        0
+    }
+
+    fn apply(&self, state: i64, event: &CounterEvent) -> i64 {
+        match event {
+            CounterEvent::Incremented(by) => state + by,
+        }
+    }
+
+    fn execute(&self, _state: &i64, command: &i64) -> Result<Vec<CounterEvent>> {
+        Ok(vec![CounterEvent::Incremented(*command)])
+    }
+}
+
+#[actix_rt::test]
+async fn takes_snapshot_every_n_events() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::<i64, EventVersion>::new();
+    let stream_id = get_stream_id();
+
+    for by in 1..=5 {
+        let _ = make_snapshot_handler(
+            &Counter,
+            &store,
+            &snapshots,
+            &by,
+            &stream_id,
+            &ExpectedVersion::Any,
+            2,
+        )
+        .await
+        .unwrap();
+    }
+
+    let snapshot = snapshots.get_latest_snapshot(&stream_id).await.unwrap();
+    let snapshot = snapshot.unwrap();
+    assert_eq!(snapshot.version.0, 4);
+    assert_eq!(snapshot.state, 1 + 2 + 3 + 4);
+
+    let (state, version) = load_state(&Counter, &store, &snapshots, &stream_id)
+        .await
+        .unwrap();
+    assert_eq!(state, 15);
+    assert_eq!(version.unwrap().0, 5);
+}
+
+#[actix_rt::test]
+async fn loads_state_from_latest_snapshot() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::new();
+    let stream_id = get_stream_id();
+
+    let events = (1..=3)
+        .map(|by| CounterEvent::Incremented(by).into())
+        .collect();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, events)
+        .await
+        .unwrap();
+    // Events up to the snapshot are never folded again
+    let _ = snapshots
+        .save_snapshot(&stream_id, &EventVersion::new(2), &100)
+        .await
+        .unwrap();
+
+    let (state, version) = load_state(&Counter, &store, &snapshots, &stream_id)
+        .await
+        .unwrap();
+    assert_eq!(state, 103);
+    assert_eq!(version.unwrap().0, 3);
+}
+
+#[actix_rt::test]
+async fn loads_state_without_snapshot() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::<i64, EventVersion>::new();
+    let stream_id = get_stream_id();
+
+    let events = (1..=3)
+        .map(|by| CounterEvent::Incremented(by).into())
+        .collect();
+    let _ = store
+        .append_events(&stream_id, &ExpectedVersion::Any, events)
+        .await
+        .unwrap();
+
+    let (state, version) = load_state(&Counter, &store, &snapshots, &stream_id)
+        .await
+        .unwrap();
+    assert_eq!(state, 6);
+    assert_eq!(version.unwrap().0, 3);
+}
+
+async fn increment(
+    store: &(impl EventStore<CounterEvent, (), EventVersion> + Sync),
+    stream_id: &str,
+    by: Vec<i64>,
+) {
+    let events = by
+        .into_iter()
+        .map(|by| CounterEvent::Incremented(by).into())
+        .collect();
+    let _ = store
+        .append_events(stream_id, &ExpectedVersion::Any, events)
+        .await
+        .unwrap();
+}
+
+// Snapshots +100 +100 at version 2, deletes the stream and recreates it with +1
+async fn load_recreated_stream(
+    store: &(impl EventStore<CounterEvent, (), EventVersion> + Sync),
+    snapshots: &(impl SnapshotStore<i64, EventVersion> + Sync),
+    mode: DeleteMode,
+) -> (i64, Option<EventVersion>) {
+    let stream_id = get_stream_id();
+    increment(store, &stream_id, vec![100, 100]).await;
+    let _ = snapshots
+        .save_snapshot(&stream_id, &EventVersion::new(2), &200)
+        .await
+        .unwrap();
+    store.delete_stream(&stream_id, mode).await.unwrap();
+    increment(store, &stream_id, vec![1]).await;
+
+    load_state(&Counter, store, snapshots, &stream_id)
+        .await
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn ignores_snapshots_of_soft_deleted_events() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::new();
+
+    let res = load_recreated_stream(&store, &snapshots, DeleteMode::Soft).await;
+    assert_eq!(res, (1, Some(EventVersion::new(3))));
+}
+
+#[actix_rt::test]
+async fn ignores_snapshots_of_hard_deleted_streams() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::new();
+
+    let res = load_recreated_stream(&store, &snapshots, DeleteMode::Hard).await;
+    assert_eq!(res, (1, Some(EventVersion::new(1))));
+}
+
+#[actix_rt::test]
+async fn ignores_snapshots_of_truncated_events() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::new();
+    let stream_id = get_stream_id();
+
+    increment(&store, &stream_id, vec![100, 100, 1]).await;
+    let _ = snapshots
+        .save_snapshot(&stream_id, &EventVersion::new(2), &200)
+        .await
+        .unwrap();
+    let _ = store
+        .set_stream_metadata(
+            &stream_id,
+            &StreamMetadata {
+                truncate_before: Some(EventVersion::new(3)),
+                ..StreamMetadata::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let res = load_state(&Counter, &store, &snapshots, &stream_id)
+        .await
+        .unwrap();
+    assert_eq!(res, (1, Some(EventVersion::new(3))));
+}
+
+#[actix_rt::test]
+async fn deleting_a_stream_drops_its_snapshots() {
+    let store = EventStoreInMemory::new();
+    let snapshots = SnapshotStoreInMemory::new();
+    let stream_id = get_stream_id();
+
+    increment(&store, &stream_id, vec![100, 100]).await;
+    let _ = snapshots
+        .save_snapshot(&stream_id, &EventVersion::new(2), &200)
+        .await
+        .unwrap();
+    delete_stream(&store, &snapshots, &stream_id, DeleteMode::Hard)
+        .await
+        .unwrap();
+    // Recreated past the version of the dropped snapshot
+    increment(&store, &stream_id, vec![1, 1, 1]).await;
+
+    assert!(snapshots
+        .get_latest_snapshot(&stream_id)
+        .await
+        .unwrap()
+        .is_none());
+    let res = load_state(&Counter, &store, &snapshots, &stream_id)
+        .await
+        .unwrap();
+    assert_eq!(res, (3, Some(EventVersion::new(3))));
+}
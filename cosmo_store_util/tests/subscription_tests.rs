@@ -0,0 +1,302 @@
+use cosmo_store::common::event_version::EventVersion;
+use cosmo_store::traits::event_store::EventStore;
+use cosmo_store::traits::version::Version;
+use cosmo_store::types::expected_version::ExpectedVersion;
+use cosmo_store_in_memory::event_store::EventStoreInMemory;
+use cosmo_store_sqlx_postgres::event_listener_sqlx_postgres::EventListenerSQLXPostgres;
+use cosmo_store_sqlx_postgres::event_store_sqlx_postgres::EventStoreSQLXPostgres;
+use cosmo_store_sqlx_sqlite::event_store_sqlx_sqlite::EventStoreSQLXSqlite;
+use cosmo_store_tests::event_generator::{get_events, get_stream_id};
+use cosmo_store_tests::event_store_basic_tests::{Meta, Payload};
+use cosmo_store_util::subscription::{subscribe, SubscriptionSettings, SubscriptionStart};
+use futures::{FutureExt, StreamExt};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+const PG_CONN_BASE: &str = "postgresql://localhost:5432/";
+const SQLITE_CONN_BASE: &str = "sqlite::memory:";
+
+async fn setup(name: &str) {
+    let pool = PgPoolOptions::new().connect(PG_CONN_BASE).await.unwrap();
+    let create_db = format!("create database \"{}\" encoding = 'UTF8'", name);
+    let _ = sqlx::query(&create_db).execute(&pool).await.unwrap();
+}
+
+async fn teardown(name: &str) {
+    let pool = PgPoolOptions::new().connect(PG_CONN_BASE).await.unwrap();
+    let kill_conn = format!(
+        "select pg_terminate_backend(pid) from pg_stat_activity where datname='{}'",
+        name
+    );
+    let drop_db = format!("drop database if exists \"{}\"", name);
+    let _ = sqlx::query(&kill_conn).execute(&pool).await.unwrap();
+    let _ = sqlx::query(&drop_db).execute(&pool).await.unwrap();
+}
+
+async fn get_pg_store(name: &str) -> EventStoreSQLXPostgres {
+    let conn_str = format!("{}{}", PG_CONN_BASE, name);
+    let pool = PgPoolOptions::new().connect(&conn_str).await.unwrap();
+    EventStoreSQLXPostgres::new(&pool, "person").await.unwrap()
+}
+
+async fn get_sqlite_store() -> EventStoreSQLXSqlite {
+    // Every connection to an in-memory database gets its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(SQLITE_CONN_BASE)
+        .await
+        .unwrap();
+    EventStoreSQLXSqlite::new(&pool, "person").await.unwrap()
+}
+
+fn get_name() -> String {
+    Uuid::new_v4().as_simple().to_string()
+}
+
+fn settings() -> SubscriptionSettings {
+    SubscriptionSettings {
+        page_size: 4,
+        poll_interval: Duration::from_millis(10),
+        wake: None,
+    }
+}
+
+fn names(from: i32, to: i32) -> Vec<String> {
+    (from..=to).map(|i| format!("Created_{}", i)).collect()
+}
+
+async fn replays_stream_then_live_events<S, V>(store: &S, from_version: V)
+where
+    S: EventStore<Payload, Meta, V> + Sync,
+    V: Version<V> + Eq + Clone + Send + Sync,
+{
+    let stream_id = get_stream_id();
+    store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=5))
+        .await
+        .unwrap();
+
+    let subscription = subscribe(
+        store,
+        SubscriptionStart::Stream {
+            stream_id: stream_id.clone(),
+            from_version,
+        },
+        settings(),
+    );
+    let (received, _) = futures::join!(subscription.take(8).collect::<Vec<_>>(), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        store
+            .append_events(&stream_id, &ExpectedVersion::Any, get_events(6..=8))
+            .await
+            .unwrap()
+    });
+
+    let received: Vec<String> = received.into_iter().map(|e| e.unwrap().name).collect();
+    assert_eq!(received, names(1, 8));
+}
+
+async fn replays_all_streams_then_live_events<S, V>(store: &S)
+where
+    S: EventStore<Payload, Meta, V> + Sync,
+    V: Version<V> + Eq + Clone + Send + Sync,
+{
+    let first = get_stream_id();
+    let second = get_stream_id();
+    store
+        .append_events(&first, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+    store
+        .append_events(&second, &ExpectedVersion::Any, get_events(4..=6))
+        .await
+        .unwrap();
+
+    let subscription = subscribe(
+        store,
+        SubscriptionStart::AllStreams { from_position: 2 },
+        settings(),
+    );
+    let (received, _) = futures::join!(subscription.take(7).collect::<Vec<_>>(), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        store
+            .append_events(&first, &ExpectedVersion::Any, get_events(7..=8))
+            .await
+            .unwrap()
+    });
+
+    let received: Vec<(String, i64)> = received
+        .into_iter()
+        .map(|e| e.unwrap())
+        .map(|e| (e.name, e.position))
+        .collect();
+    let expected: Vec<(String, i64)> = names(2, 8).into_iter().zip(2..=8).collect();
+    assert_eq!(received, expected);
+}
+
+async fn cancelled_subscription_ends<S, V>(store: &S, from_version: V)
+where
+    S: EventStore<Payload, Meta, V> + Sync,
+    V: Version<V> + Eq + Clone + Send + Sync,
+{
+    let stream_id = get_stream_id();
+    store
+        .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+        .await
+        .unwrap();
+
+    let mut subscription = subscribe(
+        store,
+        SubscriptionStart::Stream {
+            stream_id,
+            from_version,
+        },
+        settings(),
+    );
+    let first = subscription.next().await.unwrap().unwrap();
+    assert_eq!(first.name, "Created_1");
+
+    subscription.cancel();
+    assert!(subscription.next().await.is_none());
+}
+
+#[actix_rt::test]
+async fn in_memory_replays_stream_then_live_events() {
+    let store = EventStoreInMemory::new();
+    replays_stream_then_live_events(&store, EventVersion::new(1)).await;
+}
+
+#[actix_rt::test]
+async fn in_memory_replays_all_streams_then_live_events() {
+    let store = EventStoreInMemory::<Payload, Meta, EventVersion>::new();
+    replays_all_streams_then_live_events(&store).await;
+}
+
+#[actix_rt::test]
+async fn in_memory_cancelled_subscription_ends() {
+    let store = EventStoreInMemory::new();
+    cancelled_subscription_ends(&store, EventVersion::new(1)).await;
+}
+
+#[actix_rt::test]
+async fn sqlite_replays_stream_then_live_events() {
+    let store = get_sqlite_store().await;
+    replays_stream_then_live_events(&store, EventVersion::new(1)).await;
+}
+
+#[actix_rt::test]
+async fn sqlite_replays_all_streams_then_live_events() {
+    let store = get_sqlite_store().await;
+    replays_all_streams_then_live_events(&store).await;
+}
+
+#[actix_rt::test]
+async fn sqlite_cancelled_subscription_ends() {
+    let store = get_sqlite_store().await;
+    cancelled_subscription_ends(&store, EventVersion::new(1)).await;
+}
+
+#[actix_rt::test]
+async fn postgres_replays_stream_then_live_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let store = get_pg_store(&name).await;
+        replays_stream_then_live_events(&store, EventVersion::new(1)).await;
+    })
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert!(result.is_ok());
+}
+
+#[actix_rt::test]
+async fn postgres_replays_all_streams_then_live_events() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let store = get_pg_store(&name).await;
+        replays_all_streams_then_live_events(&store).await;
+    })
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert!(result.is_ok());
+}
+
+#[actix_rt::test]
+async fn postgres_cancelled_subscription_ends() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let store = get_pg_store(&name).await;
+        cancelled_subscription_ends(&store, EventVersion::new(1)).await;
+    })
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert!(result.is_ok());
+}
+
+#[actix_rt::test]
+async fn postgres_listener_wakes_subscription() {
+    let name = get_name();
+    setup(&name).await;
+    let result = std::panic::AssertUnwindSafe(async {
+        let store = get_pg_store(&name).await;
+        let stream_id = get_stream_id();
+        let wake = Arc::new(Notify::new());
+        let listener = EventListenerSQLXPostgres::new(&store).await.unwrap();
+        let listening = actix_rt::spawn(listener.wake_on_append(wake.clone()));
+
+        // Polling alone would not see the live events before the timeout
+        let subscription = subscribe::<Payload, Meta, _, _>(
+            &store,
+            SubscriptionStart::Stream {
+                stream_id: stream_id.clone(),
+                from_version: EventVersion::new(1),
+            },
+            SubscriptionSettings {
+                page_size: 4,
+                poll_interval: Duration::from_secs(60),
+                wake: Some(wake),
+            },
+        );
+        let (received, _) = futures::join!(
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                subscription.take(3).collect::<Vec<_>>()
+            ),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                store
+                    .append_events(&stream_id, &ExpectedVersion::Any, get_events(1..=3))
+                    .await
+                    .unwrap()
+            }
+        );
+
+        // Listener reconnects on its own, stop it before the database is dropped
+        listening.abort();
+        let _ = listening.await;
+
+        let received: Vec<String> = received
+            .unwrap()
+            .into_iter()
+            .map(|e| e.unwrap().name)
+            .collect();
+        assert_eq!(received, names(1, 3));
+    })
+    .catch_unwind()
+    .await;
+    teardown(&name).await;
+
+    assert!(result.is_ok());
+}